@@ -1,49 +1,327 @@
+use std::io;
+
 use crate::emulation::address_mapper::AddressMapper;
+use crate::emulation::audio::noise_channel::NoiseChannel;
+use crate::emulation::audio::sink::AudioSink;
+use crate::emulation::audio::square_channel::SquareChannel;
+use crate::emulation::audio::wave_channel::WaveChannel;
+use crate::emulation::bitutils::BitExtensions;
+use crate::emulation::constants::{APU_CYCLES_PER_SAMPLE, AUDIO_IO_END, AUDIO_IO_START};
 use crate::emulation::internal_message::InternalMessage;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+
+// The frame sequencer runs at 512 Hz, i.e. once every 8192 CPU cycles.
+const FRAME_SEQUENCER_PERIOD: u16 = 8192;
+
+const REGISTER_COUNT: usize = 0x17;
+const WAVE_RAM_OFFSET: u8 = 0x20;
+
+const NR50: u8 = 0x14;
+const NR51: u8 = 0x15;
+const NR52: u8 = 0x16;
+
+// Bits that always read back as 1, for NR10 through NR52.
+const READ_MASKS: [u8; REGISTER_COUNT] = [
+    0x80, 0x3F, 0x00, 0xFF, 0xBF, // NR10-NR14
+    0xFF, 0x3F, 0x00, 0xFF, 0xBF, // NR20-NR24
+    0x7F, 0xFF, 0x9F, 0xFF, 0xBF, // NR30-NR34
+    0xFF, 0xFF, 0x00, 0x00, 0xBF, // NR40-NR44
+    0x00, 0x00, 0x70 // NR50-NR52
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StereoSample {
+    pub left: f32,
+    pub right: f32
+}
 
 pub struct AudioController {
-    ram: [u8; 0x30]
+    registers: [u8; REGISTER_COUNT],
+    powered: bool,
+    frame_sequencer_timer: u16,
+    frame_sequencer_step: u8,
+    sample_timer: u32,
+    channel_1: SquareChannel,
+    channel_2: SquareChannel,
+    channel_3: WaveChannel,
+    channel_4: NoiseChannel
 }
 
 impl AudioController {
     pub fn new() -> AudioController {
-        AudioController { ram: [0; 0x30] }
+        AudioController {
+            registers: [0; REGISTER_COUNT],
+            powered: false,
+            frame_sequencer_timer: FRAME_SEQUENCER_PERIOD,
+            frame_sequencer_step: 0,
+            sample_timer: APU_CYCLES_PER_SAMPLE,
+            channel_1: SquareChannel::new(true),
+            channel_2: SquareChannel::new(false),
+            channel_3: WaveChannel::new(),
+            channel_4: NoiseChannel::new()
+        }
+    }
+
+    pub fn is_powered(&self) -> bool {
+        self.powered
+    }
+
+    // Samples keep flowing into the sink while the APU is powered off, so that anything paced
+    // by the audio stream keeps running.
+    pub fn update(&mut self, cycles: u32, mut sink: Option<&mut Box<dyn AudioSink>>) {
+        for _ in 0..cycles {
+            if self.powered {
+                self.step();
+            }
+
+            self.sample_timer -= 1;
+            if self.sample_timer == 0 {
+                self.sample_timer = APU_CYCLES_PER_SAMPLE;
+
+                if let Some(ref mut sink) = sink {
+                    let channel_outputs = self.get_channel_outputs();
+                    sink.push_sample(self.mix(channel_outputs));
+                    sink.push_channel_samples(channel_outputs);
+                }
+            }
+        }
+    }
+
+    fn step(&mut self) {
+        self.channel_1.step();
+        self.channel_2.step();
+        self.channel_3.step();
+        self.channel_4.step();
+
+        self.frame_sequencer_timer -= 1;
+        if self.frame_sequencer_timer == 0 {
+            self.frame_sequencer_timer = FRAME_SEQUENCER_PERIOD;
+            self.clock_frame_sequencer();
+        }
+    }
+
+    fn clock_frame_sequencer(&mut self) {
+        let step = self.frame_sequencer_step;
+
+        if step.is_multiple_of(2) {
+            self.channel_1.clock_length();
+            self.channel_2.clock_length();
+            self.channel_3.clock_length();
+            self.channel_4.clock_length();
+        }
+
+        if step == 2 || step == 6 {
+            self.channel_1.clock_sweep();
+        }
+
+        if step == 7 {
+            self.channel_1.clock_envelope();
+            self.channel_2.clock_envelope();
+            self.channel_4.clock_envelope();
+        }
+
+        self.frame_sequencer_step = (step + 1) % 8;
+    }
+
+    fn next_step_clocks_length(&self) -> bool {
+        self.frame_sequencer_step.is_multiple_of(2)
+    }
+
+    fn write_register(&mut self, offset: u8, value: u8) {
+        if offset == NR52 {
+            self.write_power(value.get_bit(7));
+            return;
+        }
+
+        if !self.powered {
+            // The length counters are the only part of the APU writable while it is off.
+            match offset {
+                0x01 => self.channel_1.write_length_while_powered_off(value),
+                0x06 => self.channel_2.write_length_while_powered_off(value),
+                0x0B => self.channel_3.write_length_while_powered_off(value),
+                0x10 => self.channel_4.write_length_while_powered_off(value),
+                _ => ()
+            }
+            return;
+        }
+
+        self.registers[offset as usize] = value;
+
+        let next_step_clocks_length = self.next_step_clocks_length();
+        let register = offset % 5;
+
+        match offset {
+            0x00..=0x04 => self
+                .channel_1
+                .write_register(register, value, next_step_clocks_length),
+            0x05..=0x09 => self
+                .channel_2
+                .write_register(register, value, next_step_clocks_length),
+            0x0A..=0x0E => self
+                .channel_3
+                .write_register(register, value, next_step_clocks_length),
+            0x0F..=0x13 => self
+                .channel_4
+                .write_register(register, value, next_step_clocks_length),
+            _ => ()
+        }
+    }
+
+    fn write_power(&mut self, powered: bool) {
+        if self.powered && !powered {
+            self.registers = [0; REGISTER_COUNT];
+            self.channel_1.power_off();
+            self.channel_2.power_off();
+            self.channel_3.power_off();
+            self.channel_4.power_off();
+        } else if !self.powered && powered {
+            self.frame_sequencer_timer = FRAME_SEQUENCER_PERIOD;
+            self.frame_sequencer_step = 0;
+        }
+
+        self.powered = powered;
+    }
+
+    fn read_status(&self) -> u8 {
+        (self.powered as u8) << 7
+            | READ_MASKS[NR52 as usize]
+            | (self.channel_4.is_enabled() as u8) << 3
+            | (self.channel_3.is_enabled() as u8) << 2
+            | (self.channel_2.is_enabled() as u8) << 1
+            | self.channel_1.is_enabled() as u8
+    }
+
+    // Digital channel outputs (0-15) before the DACs.
+    pub fn get_channel_levels(&self) -> [u8; 4] {
+        [
+            self.channel_1.get_output(),
+            self.channel_2.get_output(),
+            self.channel_3.get_output(),
+            self.channel_4.get_output()
+        ]
+    }
+
+    // Analog channel outputs in the range -1.0..1.0. A channel with its DAC turned off is silent.
+    pub fn get_channel_outputs(&self) -> [f32; 4] {
+        let dacs_enabled = [
+            self.channel_1.is_dac_enabled(),
+            self.channel_2.is_dac_enabled(),
+            self.channel_3.is_dac_enabled(),
+            self.channel_4.is_dac_enabled()
+        ];
+
+        let levels = self.get_channel_levels();
+        let mut outputs = [0.0; 4];
+
+        for i in 0..4 {
+            if self.powered && dacs_enabled[i] {
+                outputs[i] = levels[i] as f32 / 7.5 - 1.0;
+            }
+        }
+
+        outputs
+    }
+
+    // Mixes the channels according to NR51 panning and NR50 master volume. The result is in
+    // the range -1.0..1.0.
+    pub fn get_output(&self) -> StereoSample {
+        self.mix(self.get_channel_outputs())
+    }
+
+    fn mix(&self, outputs: [f32; 4]) -> StereoSample {
+        let panning = self.registers[NR51 as usize];
+        let master_volume = self.registers[NR50 as usize];
+
+        let mut left = 0.0;
+        let mut right = 0.0;
+
+        for (i, output) in outputs.iter().enumerate() {
+            if panning.get_bit(i as u8) {
+                right += output;
+            }
+
+            if panning.get_bit(i as u8 + 4) {
+                left += output;
+            }
+        }
+
+        let left_volume = ((master_volume >> 4) & 0b111) as f32 + 1.0;
+        let right_volume = (master_volume & 0b111) as f32 + 1.0;
+
+        StereoSample {
+            left: left / 4.0 * left_volume / 8.0,
+            right: right / 4.0 * right_volume / 8.0
+        }
     }
 }
 
-type AudioRamOffset = u8;
-pub type AudioRamLocation = Result<AudioRamOffset, u16>;
+#[derive(Debug, Clone, Copy)]
+pub enum AudioRamLocation {
+    Register(u8),
+    Unused(u8),
+    WaveRam(u8)
+}
 
 impl AddressMapper for AudioController {
     type T = AudioRamLocation;
 
     fn resolve_address(&self, address: u16) -> AudioRamLocation {
-        if address < 0xFF10 || address > 0xFF3F {
-            Err(address)
-        } else {
-            Ok((address - 0xFF10) as u8)
+        if !(AUDIO_IO_START..=AUDIO_IO_END).contains(&address) {
+            panic!("Invalid audio RAM address: ${:04x}", address);
+        }
+
+        let offset = (address - AUDIO_IO_START) as u8;
+        match offset {
+            0x00..=0x16 => AudioRamLocation::Register(offset),
+            0x17..=0x1F => AudioRamLocation::Unused(offset),
+            _ => AudioRamLocation::WaveRam(offset - WAVE_RAM_OFFSET)
         }
     }
 
     fn read_8(&self, location: AudioRamLocation) -> u8 {
         match location {
-            Ok(offset) => self.ram[offset as usize],
-            Err(address) => panic!(
-                "Tried to read from invalid audio RAM address: ${:04x}",
-                address
-            )
+            AudioRamLocation::Register(NR52) => self.read_status(),
+            AudioRamLocation::Register(offset) => {
+                self.registers[offset as usize] | READ_MASKS[offset as usize]
+            }
+            AudioRamLocation::Unused(_) => 0xFF,
+            AudioRamLocation::WaveRam(offset) => self.channel_3.wave_ram[offset as usize]
         }
     }
 
     fn write_8(&mut self, location: AudioRamLocation, value: u8) -> InternalMessage {
         match location {
-            Ok(offset) => self.ram[offset as usize] = value,
-            Err(address) => panic!(
-                "Tried to write to invalid audio RAM address: ${:04x}",
-                address
-            )
+            AudioRamLocation::Register(offset) => self.write_register(offset, value),
+            AudioRamLocation::Unused(_) => (),
+            AudioRamLocation::WaveRam(offset) => self.channel_3.wave_ram[offset as usize] = value
         }
 
         InternalMessage::None
     }
 }
+
+impl SaveState for AudioController {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.registers);
+        writer.write_bool(self.powered);
+        writer.write_u16(self.frame_sequencer_timer);
+        writer.write_u8(self.frame_sequencer_step);
+        writer.write_u32(self.sample_timer);
+        self.channel_1.write_state(writer);
+        self.channel_2.write_state(writer);
+        self.channel_3.write_state(writer);
+        self.channel_4.write_state(writer);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        reader.read_bytes_into(&mut self.registers)?;
+        self.powered = reader.read_bool()?;
+        self.frame_sequencer_timer = reader.read_u16()?.clamp(1, FRAME_SEQUENCER_PERIOD);
+        self.frame_sequencer_step = reader.read_u8()? % 8;
+        self.sample_timer = reader.read_u32()?.clamp(1, APU_CYCLES_PER_SAMPLE);
+        self.channel_1.read_state(reader)?;
+        self.channel_2.read_state(reader)?;
+        self.channel_3.read_state(reader)?;
+        self.channel_4.read_state(reader)
+    }
+}
@@ -1 +1,8 @@
 pub mod controller;
+pub mod noise_channel;
+pub mod resampler;
+pub mod sink;
+pub mod square_channel;
+pub mod units;
+pub mod wav_writer;
+pub mod wave_channel;
@@ -0,0 +1,154 @@
+use std::io;
+
+use crate::emulation::audio::units::{LengthCounter, VolumeEnvelope};
+use crate::emulation::bitutils::BitExtensions;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+
+const DIVISORS: [u32; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+
+pub struct NoiseChannel {
+    enabled: bool,
+    clock_shift: u8,
+    width_mode: bool,
+    divisor_code: u8,
+    timer: u32,
+    lfsr: u16,
+    length: LengthCounter,
+    envelope: VolumeEnvelope
+}
+
+impl NoiseChannel {
+    pub fn new() -> NoiseChannel {
+        NoiseChannel {
+            enabled: false,
+            clock_shift: 0,
+            width_mode: false,
+            divisor_code: 0,
+            timer: 0,
+            lfsr: 0x7FFF,
+            length: LengthCounter::new(64),
+            envelope: VolumeEnvelope::new()
+        }
+    }
+
+    fn get_timer_period(&self) -> u32 {
+        DIVISORS[self.divisor_code as usize] << self.clock_shift
+    }
+
+    pub fn write_register(&mut self, register: u8, value: u8, next_step_clocks_length: bool) {
+        match register {
+            0 => (),
+            1 => self.length.load(value & 0b0011_1111),
+            2 => {
+                self.envelope.write_register(value);
+                if !self.envelope.is_dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => {
+                self.clock_shift = value >> 4;
+                self.width_mode = value.get_bit(3);
+                self.divisor_code = value & 0b111;
+            }
+            4 => {
+                if self
+                    .length
+                    .write_enable(value.get_bit(6), next_step_clocks_length)
+                {
+                    self.enabled = false;
+                }
+
+                if value.get_bit(7) {
+                    self.trigger(next_step_clocks_length);
+                }
+            }
+            _ => panic!("Invalid noise channel register: {}", register)
+        }
+    }
+
+    pub fn write_length_while_powered_off(&mut self, value: u8) {
+        self.length.load(value & 0b0011_1111);
+    }
+
+    fn trigger(&mut self, next_step_clocks_length: bool) {
+        self.enabled = self.envelope.is_dac_enabled();
+        self.length.trigger(next_step_clocks_length);
+        self.timer = self.get_timer_period();
+        self.envelope.trigger();
+        self.lfsr = 0x7FFF;
+    }
+
+    pub fn power_off(&mut self) {
+        let length = self.length.clone();
+        *self = NoiseChannel::new();
+        self.length = length;
+        self.length.enabled = false;
+    }
+
+    pub fn step(&mut self) {
+        if self.timer > 1 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.get_timer_period();
+
+        let feedback = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+        self.lfsr = (self.lfsr >> 1) | (feedback << 14);
+
+        // In 7-bit mode the feedback bit is also copied to bit 6, giving a shorter sequence.
+        if self.width_mode {
+            self.lfsr = (self.lfsr & !(1 << 6)) | (feedback << 6);
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn is_dac_enabled(&self) -> bool {
+        self.envelope.is_dac_enabled()
+    }
+
+    pub fn get_output(&self) -> u8 {
+        if self.enabled && self.lfsr & 1 == 0 {
+            self.envelope.volume
+        } else {
+            0
+        }
+    }
+}
+
+impl SaveState for NoiseChannel {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.enabled);
+        writer.write_u8(self.clock_shift);
+        writer.write_bool(self.width_mode);
+        writer.write_u8(self.divisor_code);
+        writer.write_u32(self.timer);
+        writer.write_u16(self.lfsr);
+        self.length.write_state(writer);
+        self.envelope.write_state(writer);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.enabled = reader.read_bool()?;
+        self.clock_shift = reader.read_u8()? & 0x0F;
+        self.width_mode = reader.read_bool()?;
+        self.divisor_code = reader.read_u8()? & 0b111;
+        self.timer = reader.read_u32()?;
+        self.lfsr = reader.read_u16()?;
+        self.length.read_state(reader)?;
+        self.envelope.read_state(reader)
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use crate::emulation::audio::controller::StereoSample;
+
+// Taps on each side of the interpolation kernel.
+const KERNEL_HALF_WIDTH: usize = 16;
+// The kernel is tabulated at this many fractional positions and interpolated in between.
+const KERNEL_PHASES: usize = 256;
+// Passband edge relative to the output rate.
+const CUTOFF_RATIO: f64 = 0.45;
+// Time constant of the DC blocker, roughly matching the capacitor on the hardware output.
+const HIGH_PASS_CUTOFF_HZ: f64 = 20.0;
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+fn blackman(x: f64) -> f64 {
+    // x is in -1.0..1.0
+    let t = (x + 1.0) / 2.0;
+    0.42 - 0.5 * (2.0 * PI * t).cos() + 0.08 * (4.0 * PI * t).cos()
+}
+
+fn scale(sample: StereoSample, factor: f32) -> StereoSample {
+    StereoSample {
+        left: sample.left * factor,
+        right: sample.right * factor
+    }
+}
+
+fn add(a: StereoSample, b: StereoSample) -> StereoSample {
+    StereoSample {
+        left: a.left + b.left,
+        right: a.right + b.right
+    }
+}
+
+const SILENCE: StereoSample = StereoSample {
+    left: 0.0,
+    right: 0.0
+};
+
+struct HighPassFilter {
+    factor: f32,
+    previous_input: StereoSample,
+    previous_output: StereoSample
+}
+
+impl HighPassFilter {
+    fn new(sample_rate: f64) -> HighPassFilter {
+        let rc = 1.0 / (2.0 * PI * HIGH_PASS_CUTOFF_HZ);
+        let dt = 1.0 / sample_rate;
+
+        HighPassFilter {
+            factor: (rc / (rc + dt)) as f32,
+            previous_input: SILENCE,
+            previous_output: SILENCE
+        }
+    }
+
+    fn filter(&mut self, input: StereoSample) -> StereoSample {
+        let output = StereoSample {
+            left: self.factor * (self.previous_output.left + input.left - self.previous_input.left),
+            right: self.factor
+                * (self.previous_output.right + input.right - self.previous_input.right)
+        };
+
+        self.previous_input = input;
+        self.previous_output = output;
+        output
+    }
+}
+
+// Converts the ~1 MHz APU stream to a host sample rate in two stages. The input is first
+// averaged down by an integer factor to a little over twice the output rate, which is cheap and
+// keeps the square wave harmonics from aliasing into the audible range. The intermediate stream
+// is then interpolated with a windowed sinc kernel that cuts off just below the output Nyquist
+// frequency. The output rate can be nudged at runtime to implement dynamic rate control.
+pub struct Resampler {
+    decimation: u32,
+    accumulator: StereoSample,
+    accumulated: u32,
+    intermediate_rate: f64,
+    nominal_output_rate: f64,
+    step: f64,
+    position: f64,
+    history: VecDeque<StereoSample>,
+    kernel: Vec<f32>,
+    high_pass: HighPassFilter,
+    output: Vec<StereoSample>
+}
+
+impl Resampler {
+    pub fn new(input_rate: u32, output_rate: u32) -> Resampler {
+        let input_rate = input_rate as f64;
+        let output_rate = output_rate as f64;
+
+        let decimation = ((input_rate / (output_rate * 2.0)).floor() as u32).max(1);
+        let intermediate_rate = input_rate / decimation as f64;
+        let cutoff = CUTOFF_RATIO * output_rate.min(intermediate_rate) / intermediate_rate;
+
+        let mut resampler = Resampler {
+            decimation,
+            accumulator: SILENCE,
+            accumulated: 0,
+            intermediate_rate,
+            nominal_output_rate: output_rate,
+            step: intermediate_rate / output_rate,
+            position: KERNEL_HALF_WIDTH as f64,
+            history: VecDeque::with_capacity(KERNEL_HALF_WIDTH * 2 + 1),
+            kernel: Resampler::create_kernel(cutoff),
+            high_pass: HighPassFilter::new(output_rate),
+            output: Vec::new()
+        };
+
+        for _ in 0..KERNEL_HALF_WIDTH * 2 {
+            resampler.history.push_back(SILENCE);
+        }
+
+        resampler
+    }
+
+    // The table holds KERNEL_PHASES + 1 rows so that interpolation never runs past the end.
+    // Row p, column k is the kernel weight for a tap at distance (k - half width + 1) - p / phases.
+    fn create_kernel(cutoff: f64) -> Vec<f32> {
+        let width = KERNEL_HALF_WIDTH * 2;
+        let mut kernel = Vec::with_capacity((KERNEL_PHASES + 1) * width);
+
+        for phase in 0..=KERNEL_PHASES {
+            let fraction = phase as f64 / KERNEL_PHASES as f64;
+            for tap in 0..width {
+                let distance = fraction + (KERNEL_HALF_WIDTH - 1) as f64 - tap as f64;
+                let window = blackman(distance / KERNEL_HALF_WIDTH as f64);
+                kernel.push((2.0 * cutoff * sinc(2.0 * cutoff * distance) * window) as f32);
+            }
+        }
+
+        kernel
+    }
+
+    // Scales the output rate by the given factor, e.g. 1.005 to produce 0.5% more samples.
+    pub fn set_rate_adjustment(&mut self, adjustment: f64) {
+        self.step = self.intermediate_rate / (self.nominal_output_rate * adjustment);
+    }
+
+    pub fn push_sample(&mut self, sample: StereoSample) {
+        self.accumulator = add(self.accumulator, sample);
+        self.accumulated += 1;
+
+        if self.accumulated < self.decimation {
+            return;
+        }
+
+        let averaged = scale(self.accumulator, 1.0 / self.decimation as f32);
+        self.accumulator = SILENCE;
+        self.accumulated = 0;
+        self.push_intermediate(averaged);
+    }
+
+    fn push_intermediate(&mut self, sample: StereoSample) {
+        self.history.pop_front();
+        self.history.push_back(sample);
+        self.position -= 1.0;
+
+        // The history always holds 2 * KERNEL_HALF_WIDTH samples, and an output sample can be
+        // produced while its position has a full half kernel of history on both sides.
+        while self.position < KERNEL_HALF_WIDTH as f64 {
+            let output = self.interpolate(self.position - (KERNEL_HALF_WIDTH - 1) as f64);
+            let filtered = self.high_pass.filter(output);
+            self.output.push(filtered);
+            self.position += self.step;
+        }
+    }
+
+    fn interpolate(&self, fraction: f64) -> StereoSample {
+        let width = KERNEL_HALF_WIDTH * 2;
+        let phase = fraction * KERNEL_PHASES as f64;
+        let row = (phase.floor() as usize).min(KERNEL_PHASES - 1);
+        let blend = (phase - row as f64) as f32;
+
+        let first_row = &self.kernel[row * width..(row + 1) * width];
+        let second_row = &self.kernel[(row + 1) * width..(row + 2) * width];
+
+        let mut left = 0.0;
+        let mut right = 0.0;
+
+        for (tap, sample) in self.history.iter().enumerate() {
+            let weight = first_row[tap] + (second_row[tap] - first_row[tap]) * blend;
+            left += sample.left * weight;
+            right += sample.right * weight;
+        }
+
+        StereoSample { left, right }
+    }
+
+    pub fn available(&self) -> usize {
+        self.output.len()
+    }
+
+    pub fn drain_output(&mut self) -> std::vec::Drain<'_, StereoSample> {
+        self.output.drain(..)
+    }
+}
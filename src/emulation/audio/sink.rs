@@ -0,0 +1,54 @@
+use std::io;
+
+use crate::emulation::audio::controller::StereoSample;
+
+// Receives the mixed APU output at APU_SAMPLE_RATE.
+pub trait AudioSink {
+    fn push_sample(&mut self, sample: StereoSample);
+
+    // Called after every push_sample with the unmixed output of each channel.
+    fn push_channel_samples(&mut self, _channels: [f32; 4]) {}
+
+    // Called once output stops, e.g. to finalize a recording. Reports anything that went wrong
+    // along the way, since push_sample can't.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Forwards every sample to several sinks, e.g. to record while listening.
+pub struct AudioSinkGroup {
+    sinks: Vec<Box<dyn AudioSink>>
+}
+
+impl AudioSinkGroup {
+    pub fn new(sinks: Vec<Box<dyn AudioSink>>) -> AudioSinkGroup {
+        AudioSinkGroup { sinks }
+    }
+}
+
+impl AudioSink for AudioSinkGroup {
+    fn push_sample(&mut self, sample: StereoSample) {
+        for sink in &mut self.sinks {
+            sink.push_sample(sample);
+        }
+    }
+
+    fn push_channel_samples(&mut self, channels: [f32; 4]) {
+        for sink in &mut self.sinks {
+            sink.push_channel_samples(channels);
+        }
+    }
+
+    // Every sink is finished, even after one of them fails.
+    fn finish(&mut self) -> io::Result<()> {
+        let mut result = Ok(());
+        for sink in &mut self.sinks {
+            let sink_result = sink.finish();
+            if result.is_ok() {
+                result = sink_result;
+            }
+        }
+        result
+    }
+}
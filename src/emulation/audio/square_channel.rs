@@ -0,0 +1,184 @@
+use std::io;
+
+use crate::emulation::audio::units::{FrequencySweep, LengthCounter, SweepResult, VolumeEnvelope};
+use crate::emulation::bitutils::BitExtensions;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+
+const DUTY_PATTERNS: [u8; 4] = [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+
+pub struct SquareChannel {
+    enabled: bool,
+    duty: u8,
+    duty_position: u8,
+    frequency: u16,
+    timer: u16,
+    length: LengthCounter,
+    envelope: VolumeEnvelope,
+    sweep: Option<FrequencySweep>
+}
+
+impl SquareChannel {
+    pub fn new(has_sweep: bool) -> SquareChannel {
+        SquareChannel {
+            enabled: false,
+            duty: 0,
+            duty_position: 0,
+            frequency: 0,
+            timer: 0,
+            length: LengthCounter::new(64),
+            envelope: VolumeEnvelope::new(),
+            sweep: if has_sweep {
+                Some(FrequencySweep::new())
+            } else {
+                None
+            }
+        }
+    }
+
+    fn get_timer_period(&self) -> u16 {
+        (2048 - self.frequency) * 4
+    }
+
+    pub fn write_register(&mut self, register: u8, value: u8, next_step_clocks_length: bool) {
+        match register {
+            0 => {
+                if let Some(ref mut sweep) = self.sweep {
+                    if sweep.write_register(value) {
+                        self.enabled = false;
+                    }
+                }
+            }
+            1 => {
+                self.duty = value >> 6;
+                self.length.load(value & 0b0011_1111);
+            }
+            2 => {
+                self.envelope.write_register(value);
+                if !self.envelope.is_dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => self.frequency = (self.frequency & 0x700) | value as u16,
+            4 => {
+                self.frequency = (self.frequency & 0xFF) | ((value as u16 & 0b111) << 8);
+
+                if self
+                    .length
+                    .write_enable(value.get_bit(6), next_step_clocks_length)
+                {
+                    self.enabled = false;
+                }
+
+                if value.get_bit(7) {
+                    self.trigger(next_step_clocks_length);
+                }
+            }
+            _ => panic!("Invalid square channel register: {}", register)
+        }
+    }
+
+    // On DMG the length counters keep running while the APU is off and can still be loaded.
+    pub fn write_length_while_powered_off(&mut self, value: u8) {
+        self.length.load(value & 0b0011_1111);
+    }
+
+    fn trigger(&mut self, next_step_clocks_length: bool) {
+        self.enabled = self.envelope.is_dac_enabled();
+        self.length.trigger(next_step_clocks_length);
+        self.timer = self.get_timer_period();
+        self.envelope.trigger();
+
+        if let Some(ref mut sweep) = self.sweep {
+            if !sweep.trigger(self.frequency) {
+                self.enabled = false;
+            }
+        }
+    }
+
+    pub fn power_off(&mut self) {
+        let length = self.length.clone();
+        *self = SquareChannel::new(self.sweep.is_some());
+        self.length = length;
+        self.length.enabled = false;
+    }
+
+    pub fn step(&mut self) {
+        if self.timer > 1 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.get_timer_period();
+        self.duty_position = (self.duty_position + 1) & 0b111;
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_sweep(&mut self) {
+        if let Some(ref mut sweep) = self.sweep {
+            match sweep.clock() {
+                SweepResult::Unchanged => (),
+                SweepResult::NewFrequency(frequency) => self.frequency = frequency,
+                SweepResult::Overflow => self.enabled = false
+            }
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn is_dac_enabled(&self) -> bool {
+        self.envelope.is_dac_enabled()
+    }
+
+    pub fn get_output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+
+        let high = DUTY_PATTERNS[self.duty as usize].get_bit(self.duty_position);
+        if high {
+            self.envelope.volume
+        } else {
+            0
+        }
+    }
+}
+
+impl SaveState for SquareChannel {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.enabled);
+        writer.write_u8(self.duty);
+        writer.write_u8(self.duty_position);
+        writer.write_u16(self.frequency);
+        writer.write_u16(self.timer);
+        self.length.write_state(writer);
+        self.envelope.write_state(writer);
+        if let Some(ref sweep) = self.sweep {
+            sweep.write_state(writer);
+        }
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.enabled = reader.read_bool()?;
+        self.duty = reader.read_u8()? & 0b11;
+        self.duty_position = reader.read_u8()? & 0b111;
+        self.frequency = reader.read_u16()? & 0x7FF;
+        self.timer = reader.read_u16()?;
+        self.length.read_state(reader)?;
+        self.envelope.read_state(reader)?;
+        if let Some(ref mut sweep) = self.sweep {
+            sweep.read_state(reader)?;
+        }
+        Ok(())
+    }
+}
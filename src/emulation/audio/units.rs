@@ -0,0 +1,266 @@
+use std::io;
+
+use crate::emulation::bitutils::BitExtensions;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+
+#[derive(Debug, Clone)]
+pub struct LengthCounter {
+    max_length: u16,
+    counter: u16,
+    pub enabled: bool
+}
+
+impl LengthCounter {
+    pub fn new(max_length: u16) -> LengthCounter {
+        LengthCounter {
+            max_length,
+            counter: 0,
+            enabled: false
+        }
+    }
+
+    pub fn load(&mut self, length_data: u8) {
+        self.counter = self.max_length - length_data as u16;
+    }
+
+    // Returns true when the counter expires and the channel should be disabled.
+    pub fn clock(&mut self) -> bool {
+        if self.enabled && self.counter > 0 {
+            self.counter -= 1;
+            self.counter == 0
+        } else {
+            false
+        }
+    }
+
+    // Enabling the counter during the half of the frame sequencer period that won't clock it
+    // clocks it once immediately.
+    pub fn write_enable(&mut self, enabled: bool, next_step_clocks_length: bool) -> bool {
+        let was_enabled = self.enabled;
+        self.enabled = enabled;
+
+        if !was_enabled && enabled && !next_step_clocks_length {
+            self.clock()
+        } else {
+            false
+        }
+    }
+
+    pub fn trigger(&mut self, next_step_clocks_length: bool) {
+        if self.counter == 0 {
+            self.counter = self.max_length;
+
+            if self.enabled && !next_step_clocks_length {
+                self.counter -= 1;
+            }
+        }
+    }
+}
+
+impl SaveState for LengthCounter {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_u16(self.counter);
+        writer.write_bool(self.enabled);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.counter = reader.read_u16()?;
+        self.enabled = reader.read_bool()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct VolumeEnvelope {
+    initial_volume: u8,
+    increase: bool,
+    period: u8,
+    timer: u8,
+    pub volume: u8
+}
+
+impl VolumeEnvelope {
+    pub fn new() -> VolumeEnvelope {
+        VolumeEnvelope::default()
+    }
+
+    pub fn write_register(&mut self, value: u8) {
+        self.initial_volume = value >> 4;
+        self.increase = value.get_bit(3);
+        self.period = value & 0b111;
+    }
+
+    // The DAC is powered whenever the upper five bits of the envelope register are non-zero.
+    pub fn is_dac_enabled(&self) -> bool {
+        self.initial_volume != 0 || self.increase
+    }
+
+    pub fn trigger(&mut self) {
+        self.volume = self.initial_volume;
+        self.timer = self.get_timer_period();
+    }
+
+    fn get_timer_period(&self) -> u8 {
+        if self.period == 0 {
+            8
+        } else {
+            self.period
+        }
+    }
+
+    pub fn clock(&mut self) {
+        if self.period == 0 {
+            return;
+        }
+
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return;
+        }
+
+        self.timer = self.get_timer_period();
+
+        if self.increase && self.volume < 15 {
+            self.volume += 1;
+        } else if !self.increase && self.volume > 0 {
+            self.volume -= 1;
+        }
+    }
+}
+
+impl SaveState for VolumeEnvelope {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.initial_volume);
+        writer.write_bool(self.increase);
+        writer.write_u8(self.period);
+        writer.write_u8(self.timer);
+        writer.write_u8(self.volume);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.initial_volume = reader.read_u8()?;
+        self.increase = reader.read_bool()?;
+        self.period = reader.read_u8()?;
+        self.timer = reader.read_u8()?;
+        self.volume = reader.read_u8()?;
+        Ok(())
+    }
+}
+
+pub enum SweepResult {
+    Unchanged,
+    NewFrequency(u16),
+    Overflow
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FrequencySweep {
+    period: u8,
+    negate: bool,
+    shift: u8,
+    timer: u8,
+    enabled: bool,
+    shadow_frequency: u16,
+    negated_since_trigger: bool
+}
+
+impl FrequencySweep {
+    pub fn new() -> FrequencySweep {
+        FrequencySweep::default()
+    }
+
+    // Returns true if the write disables the channel: clearing the negate bit after a
+    // subtraction has been calculated since the last trigger.
+    pub fn write_register(&mut self, value: u8) -> bool {
+        self.period = (value >> 4) & 0b111;
+        self.negate = value.get_bit(3);
+        self.shift = value & 0b111;
+
+        !self.negate && self.negated_since_trigger
+    }
+
+    fn get_timer_period(&self) -> u8 {
+        if self.period == 0 {
+            8
+        } else {
+            self.period
+        }
+    }
+
+    fn calculate_frequency(&mut self) -> Option<u16> {
+        let offset = self.shadow_frequency >> self.shift;
+
+        let frequency = if self.negate {
+            self.negated_since_trigger = true;
+            self.shadow_frequency - offset
+        } else {
+            self.shadow_frequency + offset
+        };
+
+        if frequency > 2047 {
+            None
+        } else {
+            Some(frequency)
+        }
+    }
+
+    // Returns false if the initial overflow check disables the channel.
+    pub fn trigger(&mut self, frequency: u16) -> bool {
+        self.shadow_frequency = frequency;
+        self.timer = self.get_timer_period();
+        self.enabled = self.period != 0 || self.shift != 0;
+        self.negated_since_trigger = false;
+
+        self.shift == 0 || self.calculate_frequency().is_some()
+    }
+
+    pub fn clock(&mut self) -> SweepResult {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return SweepResult::Unchanged;
+        }
+
+        self.timer = self.get_timer_period();
+
+        if !self.enabled || self.period == 0 {
+            return SweepResult::Unchanged;
+        }
+
+        match self.calculate_frequency() {
+            Some(frequency) if self.shift != 0 => {
+                self.shadow_frequency = frequency;
+
+                // The new frequency is immediately run through the overflow check again.
+                match self.calculate_frequency() {
+                    Some(_) => SweepResult::NewFrequency(frequency),
+                    None => SweepResult::Overflow
+                }
+            }
+            Some(_) => SweepResult::Unchanged,
+            None => SweepResult::Overflow
+        }
+    }
+}
+
+impl SaveState for FrequencySweep {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.period);
+        writer.write_bool(self.negate);
+        writer.write_u8(self.shift);
+        writer.write_u8(self.timer);
+        writer.write_bool(self.enabled);
+        writer.write_u16(self.shadow_frequency);
+        writer.write_bool(self.negated_since_trigger);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.period = reader.read_u8()?;
+        self.negate = reader.read_bool()?;
+        self.shift = reader.read_u8()?;
+        self.timer = reader.read_u8()?;
+        self.enabled = reader.read_bool()?;
+        self.shadow_frequency = reader.read_u16()?;
+        self.negated_since_trigger = reader.read_bool()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::emulation::audio::controller::StereoSample;
+use crate::emulation::audio::resampler::Resampler;
+use crate::emulation::audio::sink::AudioSink;
+use crate::emulation::constants::APU_SAMPLE_RATE;
+
+pub const WAV_SAMPLE_RATE: u32 = 44100;
+const BITS_PER_SAMPLE: u16 = 16;
+const HEADER_SIZE: u32 = 44;
+// The RIFF chunk size, which counts everything after the first eight bytes, has to fit in 32
+// bits.
+const MAX_DATA_SIZE: u32 = u32::MAX - (HEADER_SIZE - 8);
+
+// A 16-bit PCM WAV file. The chunk sizes in the header are only known once recording stops,
+// so they are written as zero first and patched in by finish().
+struct WavFile {
+    writer: BufWriter<File>,
+    channels: u16,
+    data_size: u32
+}
+
+impl WavFile {
+    fn create(path: &Path, channels: u16) -> io::Result<WavFile> {
+        let mut file = WavFile {
+            writer: BufWriter::new(File::create(path)?),
+            channels,
+            data_size: 0
+        };
+
+        file.write_header()?;
+        Ok(file)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let block_align = self.channels * BITS_PER_SAMPLE / 8;
+        let byte_rate = WAV_SAMPLE_RATE * block_align as u32;
+        let writer = &mut self.writer;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(HEADER_SIZE - 8 + self.data_size).to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        // PCM
+        writer.write_all(&1u16.to_le_bytes())?;
+        writer.write_all(&self.channels.to_le_bytes())?;
+        writer.write_all(&WAV_SAMPLE_RATE.to_le_bytes())?;
+        writer.write_all(&byte_rate.to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+        writer.write_all(b"data")?;
+        writer.write_all(&self.data_size.to_le_bytes())
+    }
+
+    // Takes a sample for every channel, so a frame is never cut in half when the file is full.
+    fn write_frame(&mut self, samples: &[f32]) -> io::Result<()> {
+        let frame_size = samples.len() as u32 * 2;
+        if self.data_size > MAX_DATA_SIZE - frame_size {
+            return Err(io::Error::other("WAV files can't hold more than 4 GiB"));
+        }
+
+        for &value in samples {
+            let value = (value.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+        self.data_size += frame_size;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()
+    }
+}
+
+struct ChannelRecording {
+    resampler: Resampler,
+    file: WavFile
+}
+
+// Records the APU output to a stereo WAV file, and optionally each channel to its own mono
+// file next to it (e.g. music.ch1.wav). The headers are finalized by finish(), or when the
+// writer is dropped.
+pub struct WavWriter {
+    resampler: Resampler,
+    file: WavFile,
+    channels: Vec<ChannelRecording>,
+    error: Option<io::Error>,
+    is_finished: bool
+}
+
+impl WavWriter {
+    pub fn create(path: &Path) -> io::Result<WavWriter> {
+        Ok(WavWriter {
+            resampler: Resampler::new(APU_SAMPLE_RATE, WAV_SAMPLE_RATE),
+            file: WavFile::create(path, 2)?,
+            channels: Vec::new(),
+            error: None,
+            is_finished: false
+        })
+    }
+
+    pub fn create_with_channels(path: &Path) -> io::Result<WavWriter> {
+        let mut writer = WavWriter::create(path)?;
+
+        for channel in 1..=4 {
+            writer.channels.push(ChannelRecording {
+                resampler: Resampler::new(APU_SAMPLE_RATE, WAV_SAMPLE_RATE),
+                file: WavFile::create(&WavWriter::get_channel_path(path, channel), 1)?
+            });
+        }
+
+        Ok(writer)
+    }
+
+    pub fn get_channel_path(path: &Path, channel: usize) -> PathBuf {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        path.with_file_name(format!("{}.ch{}.wav", stem, channel))
+    }
+
+    // The first error stops the recording. AudioSink has no way to report it, so it's kept
+    // until finish().
+    fn handle_result(&mut self, result: io::Result<()>) {
+        if let Err(error) = result {
+            self.error = Some(error);
+        }
+    }
+
+    fn write_mixed(&mut self) -> io::Result<()> {
+        for sample in self.resampler.drain_output() {
+            self.file.write_frame(&[sample.left, sample.right])?;
+        }
+        Ok(())
+    }
+
+    fn write_channels(&mut self, samples: [f32; 4]) -> io::Result<()> {
+        for (channel, &value) in self.channels.iter_mut().zip(samples.iter()) {
+            channel.resampler.push_sample(StereoSample {
+                left: value,
+                right: value
+            });
+
+            for sample in channel.resampler.drain_output() {
+                channel.file.write_frame(&[sample.left])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish_files(&mut self) -> io::Result<()> {
+        self.file.finish()?;
+        for channel in &mut self.channels {
+            channel.file.finish()?;
+        }
+        Ok(())
+    }
+}
+
+impl AudioSink for WavWriter {
+    fn push_sample(&mut self, sample: StereoSample) {
+        if self.error.is_some() || self.is_finished {
+            return;
+        }
+
+        self.resampler.push_sample(sample);
+        let result = self.write_mixed();
+        self.handle_result(result);
+    }
+
+    fn push_channel_samples(&mut self, channels: [f32; 4]) {
+        if self.error.is_some() || self.is_finished || self.channels.is_empty() {
+            return;
+        }
+
+        let result = self.write_channels(channels);
+        self.handle_result(result);
+    }
+
+    // Finalizes the files, and returns the error that stopped the recording if there was one.
+    fn finish(&mut self) -> io::Result<()> {
+        if self.is_finished {
+            return Ok(());
+        }
+
+        self.is_finished = true;
+        let result = self.finish_files();
+
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => result
+        }
+    }
+}
+
+impl Drop for WavWriter {
+    // Errors can't be reported from here, so finish() has to be used to find out about them.
+    fn drop(&mut self) {
+        if !self.is_finished {
+            let _ = self.finish_files();
+        }
+    }
+}
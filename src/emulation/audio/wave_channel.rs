@@ -0,0 +1,155 @@
+use std::io;
+
+use crate::emulation::audio::units::LengthCounter;
+use crate::emulation::bitutils::BitExtensions;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+
+pub struct WaveChannel {
+    enabled: bool,
+    dac_enabled: bool,
+    output_level: u8,
+    frequency: u16,
+    timer: u16,
+    position: u8,
+    sample_buffer: u8,
+    length: LengthCounter,
+    pub wave_ram: [u8; 16]
+}
+
+impl WaveChannel {
+    pub fn new() -> WaveChannel {
+        WaveChannel {
+            enabled: false,
+            dac_enabled: false,
+            output_level: 0,
+            frequency: 0,
+            timer: 0,
+            position: 0,
+            sample_buffer: 0,
+            length: LengthCounter::new(256),
+            wave_ram: [0u8; 16]
+        }
+    }
+
+    fn get_timer_period(&self) -> u16 {
+        (2048 - self.frequency) * 2
+    }
+
+    pub fn write_register(&mut self, register: u8, value: u8, next_step_clocks_length: bool) {
+        match register {
+            0 => {
+                self.dac_enabled = value.get_bit(7);
+                if !self.dac_enabled {
+                    self.enabled = false;
+                }
+            }
+            1 => self.length.load(value),
+            2 => self.output_level = (value >> 5) & 0b11,
+            3 => self.frequency = (self.frequency & 0x700) | value as u16,
+            4 => {
+                self.frequency = (self.frequency & 0xFF) | ((value as u16 & 0b111) << 8);
+
+                if self
+                    .length
+                    .write_enable(value.get_bit(6), next_step_clocks_length)
+                {
+                    self.enabled = false;
+                }
+
+                if value.get_bit(7) {
+                    self.trigger(next_step_clocks_length);
+                }
+            }
+            _ => panic!("Invalid wave channel register: {}", register)
+        }
+    }
+
+    pub fn write_length_while_powered_off(&mut self, value: u8) {
+        self.length.load(value);
+    }
+
+    fn trigger(&mut self, next_step_clocks_length: bool) {
+        self.enabled = self.dac_enabled;
+        self.length.trigger(next_step_clocks_length);
+        self.timer = self.get_timer_period();
+        self.position = 0;
+    }
+
+    pub fn power_off(&mut self) {
+        let length = self.length.clone();
+        let wave_ram = self.wave_ram;
+        *self = WaveChannel::new();
+        self.length = length;
+        self.length.enabled = false;
+        self.wave_ram = wave_ram;
+    }
+
+    pub fn step(&mut self) {
+        if self.timer > 1 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.get_timer_period();
+        self.position = (self.position + 1) & 0b1_1111;
+
+        // Each byte holds two samples, upper nibble first.
+        let byte = self.wave_ram[(self.position / 2) as usize];
+        self.sample_buffer = if self.position.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0F
+        };
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn is_dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    pub fn get_output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+
+        match self.output_level {
+            0 => 0,
+            level => self.sample_buffer >> (level - 1)
+        }
+    }
+}
+
+impl SaveState for WaveChannel {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.enabled);
+        writer.write_bool(self.dac_enabled);
+        writer.write_u8(self.output_level);
+        writer.write_u16(self.frequency);
+        writer.write_u16(self.timer);
+        writer.write_u8(self.position);
+        writer.write_u8(self.sample_buffer);
+        self.length.write_state(writer);
+        writer.write_bytes(&self.wave_ram);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.enabled = reader.read_bool()?;
+        self.dac_enabled = reader.read_bool()?;
+        self.output_level = reader.read_u8()? & 0b11;
+        self.frequency = reader.read_u16()? & 0x7FF;
+        self.timer = reader.read_u16()?;
+        self.position = reader.read_u8()? & 0b1_1111;
+        self.sample_buffer = reader.read_u8()?;
+        self.length.read_state(reader)?;
+        reader.read_bytes_into(&mut self.wave_ram)
+    }
+}
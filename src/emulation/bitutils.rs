@@ -130,6 +130,18 @@ pub fn carry_add_8_prev_carry(a: u8, b: u8, prev_carry: bool) -> CarryAddResult<
     }
 }
 
+pub fn borrow_sub_8_prev_borrow(a: u8, b: u8, prev_borrow: bool) -> CarryAddResult<u8> {
+    let result = a.wrapping_sub(b).wrapping_sub(prev_borrow.to_u8());
+    let carry = (a as i16) - (b as i16) - (prev_borrow as i16) < 0;
+    let half_carry = ((a & 0xf) as i8) - ((b & 0xf) as i8) - (prev_borrow as i8) < 0;
+
+    CarryAddResult {
+        result,
+        carry,
+        half_carry
+    }
+}
+
 pub fn carry_add_16(a: u16, b: u16) -> CarryAddResult<u16> {
     let result = a.wrapping_add(b);
     let carry = (a as u32) + (b as u32) > 0xFFFF;
@@ -141,3 +153,17 @@ pub fn carry_add_16(a: u16, b: u16) -> CarryAddResult<u16> {
         half_carry
     }
 }
+
+// Used by ADD SP, e8 and LD HL, SP+e8. The offset is sign extended, but the flags come from
+// an unsigned addition of the low bytes.
+pub fn add_signed_offset_16(a: u16, offset: i8) -> CarryAddResult<u16> {
+    let CarryAddResult {
+        carry, half_carry, ..
+    } = carry_add_8(a as u8, offset as u8);
+
+    CarryAddResult {
+        result: a.wrapping_add(offset as i16 as u16),
+        carry,
+        half_carry
+    }
+}
@@ -1,3 +1,5 @@
+use std::io;
+
 use crate::emulation::address_mapper::AddressMapper;
 use crate::emulation::audio::controller::{AudioController, AudioRamLocation};
 use crate::emulation::cartridge::Cartridge;
@@ -6,9 +8,12 @@ use crate::emulation::device::DeviceType;
 use crate::emulation::input::InputRegister;
 use crate::emulation::internal_message::InternalMessage;
 use crate::emulation::interrupt::{Interrupt, InterruptRegisters};
+use crate::emulation::oam_dma::OamDma;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
 use crate::emulation::serial::SerialRegister;
 use crate::emulation::timers::{TimerRegister, TimerRegisters};
 use crate::emulation::video::controller::{VideoController, VideoMemoryLocation};
+use crate::emulation::vram_dma::{VramDma, VramDmaRegister, VRAM_DMA_BLOCK_SIZE};
 
 #[derive(Debug)]
 pub enum MemoryLocation {
@@ -23,7 +28,10 @@ pub enum MemoryLocation {
     Timer(TimerRegister),
     Audio(AudioRamLocation),
     Video(VideoMemoryLocation),
+    VramDma(VramDmaRegister),
     BootromUnmap,
+    SpeedSwitch,
+    RamBankSelect,
     HighRam(u8),
     InterruptEnable,
     InterruptRequest
@@ -32,18 +40,27 @@ pub enum MemoryLocation {
 use crate::emulation::bus::MemoryLocation::*;
 
 pub struct Bus {
+    pub device_type: DeviceType,
     pub cartridge: Option<Cartridge>,
     pub bootrom: Option<Vec<u8>>,
     pub is_booting: bool,
     pub ram: Vec<u8>,
     pub high_ram: Vec<u8>,
     selected_ram_bank: usize,
+    pub is_double_speed: bool,
+    // Set through KEY1, after which the next STOP switches speed instead of stopping.
+    speed_switch_armed: bool,
     pub input: InputRegister,
     pub timer: TimerRegisters,
-    audio: AudioController,
+    pub audio: AudioController,
     pub video: VideoController,
+    pub vram_dma: VramDma,
+    pub oam_dma: OamDma,
     pub interrupt: InterruptRegisters,
-    serial_buffer: u8
+    serial_buffer: u8,
+    // Every byte sent over the link cable, which test ROMs use to report their results. Only
+    // captured when set to Some, so it doesn't grow forever during normal play.
+    pub serial_output: Option<Vec<u8>>
 }
 
 impl Bus {
@@ -52,18 +69,24 @@ impl Bus {
         let high_ram = vec![0u8; 128];
 
         Bus {
+            device_type: device,
             cartridge: None,
             bootrom,
             is_booting: true,
             ram,
             high_ram,
             selected_ram_bank: 1,
+            is_double_speed: false,
+            speed_switch_armed: false,
             input: InputRegister::new(),
             timer: TimerRegisters::new(),
             audio: AudioController::new(),
             video: VideoController::new(device),
+            vram_dma: VramDma::new(),
+            oam_dma: OamDma::new(),
             interrupt: InterruptRegisters::new(),
-            serial_buffer: 0
+            serial_buffer: 0,
+            serial_output: None
         }
     }
 
@@ -81,9 +104,38 @@ impl Bus {
         }
     }
 
-    pub fn oam_dma_transfer(&mut self, address: u16) {
-        for i in 0..160 {
-            self.video.oam[i as usize] = self.read_8(self.resolve_address(address + i));
+    // Returns whether STOP switched the CPU speed, which only happens on the CGB after it was
+    // requested through KEY1.
+    pub fn switch_speed_if_armed(&mut self) -> bool {
+        if !self.speed_switch_armed {
+            return false;
+        }
+
+        self.speed_switch_armed = false;
+        self.is_double_speed = !self.is_double_speed;
+        true
+    }
+
+    // Copies the next block of a VRAM DMA transfer into the VRAM bank the CPU has selected.
+    pub fn vram_dma_transfer_block(&mut self) {
+        let (source, destination) = match self.vram_dma.next_block() {
+            Some(block) => block,
+            None => return
+        };
+
+        for i in 0..VRAM_DMA_BLOCK_SIZE {
+            let value = self.read_8(self.resolve_address(source.wrapping_add(i)));
+            let offset = destination + i - VRAM_START;
+            self.video.write_8(VideoMemoryLocation::Vram(offset), value);
+        }
+    }
+
+    // Copies the byte OAM DMA transfers in this machine cycle, if it's running.
+    pub fn step_oam_dma(&mut self) {
+        if let Some((address, index)) = self.oam_dma.step() {
+            let value = self.read_8(self.resolve_address(address));
+            self.video.oam[index as usize] = value;
+            self.oam_dma.set_current_value(value);
         }
     }
 }
@@ -96,7 +148,7 @@ impl AddressMapper for Bus {
             0..=255 if self.is_booting => Bootrom(address as u8),
             ROM_BANK_0_START..=ROM_BANK_N_END | 0xA000..=0xBFFF => Cartridge(address),
             RAM_BANK_0_START..=RAM_BANK_0_END => RamBank0(address - RAM_BANK_0_START),
-            ECHO_RAM_START..=ECHO_RAM_END => RamBank0(address - ECHO_RAM_START),
+            ECHO_RAM_START..=ECHO_RAM_END => self.resolve_address(address - ECHO_RAM_OFFSET),
             RAM_BANK_N_START..=RAM_BANK_N_END => RamBankN(address - RAM_BANK_N_START),
             VRAM_START..=VRAM_END => Video(VideoMemoryLocation::Vram(address - VRAM_START)),
             OAM_START..=OAM_END => Video(VideoMemoryLocation::Oam((address - 0xFE00) as u8)),
@@ -107,8 +159,13 @@ impl AddressMapper for Bus {
             TIMER_IO_START..=TIMER_IO_END => Timer(self.timer.resolve_address(address)),
             INTERRUPT_REQUEST => InterruptRequest,
             AUDIO_IO_START..=AUDIO_IO_END => Audio(self.audio.resolve_address(address)),
-            VIDEO_IO_START..=VIDEO_IO_END => Video(self.video.resolve_address(address)),
+            SPEED_SWITCH => SpeedSwitch,
+            VIDEO_IO_START..=VIDEO_IO_END | COLOR_PALETTE_IO_START..=COLOR_PALETTE_IO_END => {
+                Video(self.video.resolve_address(address))
+            }
             BOOTROM_UNMAP => BootromUnmap,
+            VRAM_DMA_IO_START..=VRAM_DMA_IO_END => VramDma(self.vram_dma.resolve_address(address)),
+            RAM_BANK_SELECT => RamBankSelect,
             0xFF7F => Ignored(address),
             HIGH_RAM_START..=HIGH_RAM_END => HighRam((address - HIGH_RAM_START) as u8),
             INTERRUPT_ENABLE => InterruptEnable,
@@ -144,6 +201,12 @@ impl AddressMapper for Bus {
             Audio(audio_location) => self.audio.read_8(audio_location),
             Video(video_location) => self.video.read_8(video_location),
             BootromUnmap => self.is_booting as u8,
+            SpeedSwitch if self.device_type.is_color() => {
+                ((self.is_double_speed as u8) << 7) | 0x7E | self.speed_switch_armed as u8
+            }
+            RamBankSelect if self.device_type.is_color() => 0xF8 | self.selected_ram_bank as u8,
+            VramDma(register) if self.device_type.is_color() => self.vram_dma.read_8(register),
+            SpeedSwitch | RamBankSelect | VramDma(_) => 0xFF,
             HighRam(offset) => self.high_ram[offset as usize],
             InterruptEnable => self.interrupt.get_enable(),
             Ignored(_) => 0,
@@ -159,12 +222,19 @@ impl AddressMapper for Bus {
         match location {
             Audio(audio_location) => self.audio.write_8(audio_location, value),
             Video(video_location) => self.video.write_8(video_location, value),
+            VramDma(register) if self.device_type.is_color() => {
+                let is_lcd_on = self.video.is_lcd_on();
+                self.vram_dma.write_8(register, value, is_lcd_on);
+                InternalMessage::None
+            }
             Serial(SerialRegister::Data) => {
                 self.serial_buffer = value;
                 InternalMessage::None
             }
             Serial(SerialRegister::Control) if value == 0x81 => {
-                println!("Serial: {}", self.serial_buffer);
+                if let Some(ref mut output) = self.serial_output {
+                    output.push(self.serial_buffer);
+                }
                 // TODO: This should not be immediate.
                 InternalMessage::TriggerInterrupt(Interrupt::EndOfSerialIO)
             }
@@ -186,6 +256,14 @@ impl AddressMapper for Bus {
                     Timer(register) => self.timer.write_8(register, value),
                     InterruptRequest => self.interrupt.set_request(value),
                     BootromUnmap => self.is_booting = value != 1,
+                    SpeedSwitch if self.device_type.is_color() => {
+                        self.speed_switch_armed = value & 1 != 0
+                    }
+                    // Bank 0 can't be selected, so selecting it selects bank 1 instead.
+                    RamBankSelect if self.device_type.is_color() => {
+                        self.selected_ram_bank = ((value & 0b111) as usize).max(1)
+                    }
+                    SpeedSwitch | RamBankSelect | VramDma(_) => (),
                     HighRam(offset) => {
                         //println!("high ram: {} <- {}", offset, value);
                         self.high_ram[offset as usize] = value
@@ -200,3 +278,61 @@ impl AddressMapper for Bus {
         }
     }
 }
+
+impl SaveState for Bus {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.is_booting);
+        writer.write_bytes(&self.ram);
+        writer.write_bytes(&self.high_ram);
+        writer.write_u8(self.selected_ram_bank as u8);
+        writer.write_bool(self.is_double_speed);
+        writer.write_bool(self.speed_switch_armed);
+        writer.write_u8(self.serial_buffer);
+        self.input.write_state(writer);
+        self.timer.write_state(writer);
+        self.audio.write_state(writer);
+        self.video.write_state(writer);
+        self.vram_dma.write_state(writer);
+        self.oam_dma.write_state(writer);
+        self.interrupt.write_state(writer);
+
+        writer.write_bool(self.cartridge.is_some());
+        if let Some(ref cartridge) = self.cartridge {
+            cartridge.write_state(writer);
+        }
+    }
+
+    // The ROM and boot ROM aren't part of the state, so the same cartridge must be inserted.
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.is_booting = reader.read_bool()?;
+        reader.read_bytes_into(&mut self.ram)?;
+        reader.read_bytes_into(&mut self.high_ram)?;
+        self.selected_ram_bank = reader.read_u8()? as usize;
+        if !(1..self.ram.len() / RAM_BANK_SIZE).contains(&self.selected_ram_bank) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Save state selects a work RAM bank that doesn't exist"
+            ));
+        }
+        self.is_double_speed = reader.read_bool()?;
+        self.speed_switch_armed = reader.read_bool()?;
+        self.serial_buffer = reader.read_u8()?;
+        self.input.read_state(reader)?;
+        self.timer.read_state(reader)?;
+        self.audio.read_state(reader)?;
+        self.video.read_state(reader)?;
+        self.vram_dma.read_state(reader)?;
+        self.oam_dma.read_state(reader)?;
+        self.interrupt.read_state(reader)?;
+
+        let has_cartridge = reader.read_bool()?;
+        match self.cartridge {
+            Some(ref mut cartridge) if has_cartridge => cartridge.read_state(reader),
+            None if !has_cartridge => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Save state doesn't match the inserted cartridge"
+            ))
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use std::fmt;
+use std::io;
 
 use crate::emulation::constants::*;
+use crate::emulation::device::DeviceType;
 use crate::emulation::mappers::{Mapper, MapperType};
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
 
 pub struct Cartridge {
     pub memory: CartridgeMemory,
@@ -45,6 +48,16 @@ impl Cartridge {
         Some(cartridge)
     }
 
+    // A cartridge of the same type with blank RAM, a fresh mapper and no ROM, for loading save
+    // states into.
+    pub fn empty_copy(&self) -> Cartridge {
+        Cartridge {
+            header: self.header.clone(),
+            memory: CartridgeMemory::new(0, self.memory.ram.len()),
+            mapper: <dyn Mapper>::from_cartridge_type(self.header.cartridge_type)
+        }
+    }
+
     pub fn read_8(&self, address: u16) -> u8 {
         self.mapper.read_8(&self.memory, address)
     }
@@ -52,6 +65,60 @@ impl Cartridge {
     pub fn write_8(&mut self, address: u16, value: u8) {
         self.mapper.write_8(&mut self.memory, address, value);
     }
+
+    pub fn is_rumbling(&self) -> bool {
+        self.mapper.is_rumbling()
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.header.cartridge_type.has_battery
+    }
+
+    // Produces the contents of a .sav file: the raw cartridge RAM, followed by the clock state
+    // for carts that have one.
+    pub fn save_battery_data(&self) -> Vec<u8> {
+        let mut data = self.memory.ram.clone();
+
+        if let Some(rtc) = self.mapper.get_rtc() {
+            rtc.write_save_data(&mut data);
+        }
+
+        data
+    }
+
+    pub fn load_battery_data(&mut self, data: &[u8]) -> io::Result<()> {
+        let ram_size = self.memory.ram.len();
+
+        if data.len() < ram_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Save data is {} bytes, but the cartridge has {} bytes of RAM",
+                    data.len(),
+                    ram_size
+                )
+            ));
+        }
+
+        // The clock is read into a copy first, so a bad footer leaves both it and RAM as they
+        // were.
+        let (ram, rtc_data) = data.split_at(ram_size);
+        let loaded_rtc = match self.mapper.get_rtc() {
+            Some(rtc) if !rtc_data.is_empty() => {
+                let mut loaded_rtc = rtc.clone();
+                loaded_rtc.read_save_data(rtc_data)?;
+                Some(loaded_rtc)
+            }
+            _ => None
+        };
+
+        self.memory.ram.copy_from_slice(ram);
+        if let (Some(rtc), Some(loaded_rtc)) = (self.mapper.get_rtc_mut(), loaded_rtc) {
+            *rtc = loaded_rtc;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -59,6 +126,8 @@ pub struct CartridgeType {
     pub mapper: MapperType,
     pub has_ram: bool,
     pub has_battery: bool,
+    pub has_timer: bool,
+    pub has_rumble: bool,
     pub rom_size: usize,
     pub ram_size: usize
 }
@@ -67,7 +136,7 @@ impl CartridgeType {
     pub fn new(id: u8, rom_size_id: u8, ram_size_id: u8) -> CartridgeType {
         // 32 kb << N
         let rom_size = match rom_size_id {
-            b @ 0..=7 => 0x8000 << b,
+            b @ 0..=8 => 0x8000 << b,
             0x52 => 72 * ROM_BANK_SIZE,
             0x53 => 80 * ROM_BANK_SIZE,
             0x54 => 96 * ROM_BANK_SIZE,
@@ -84,22 +153,42 @@ impl CartridgeType {
             invalid => panic!("Invalid RAM size: {}", invalid)
         };
 
-        let (mapper, has_ram, has_battery) = match id {
-            0x00 => (MapperType::RomOnly, false, false),
-            0x01 => (MapperType::MBC1, false, false),
-            0x02 => (MapperType::MBC1, true, false),
-            0x03 => (MapperType::MBC1, true, true),
-            0x05 => (MapperType::MBC2, false, false),
-            0x06 => (MapperType::MBC2, true, true),
-            0x08 => (MapperType::RomOnly, true, false),
-            0x09 => (MapperType::RomOnly, true, true),
+        let (mapper, has_ram, has_battery, has_timer, has_rumble) = match id {
+            0x00 => (MapperType::RomOnly, false, false, false, false),
+            0x01 => (MapperType::MBC1, false, false, false, false),
+            0x02 => (MapperType::MBC1, true, false, false, false),
+            0x03 => (MapperType::MBC1, true, true, false, false),
+            0x05 => (MapperType::MBC2, true, false, false, false),
+            0x06 => (MapperType::MBC2, true, true, false, false),
+            0x08 => (MapperType::RomOnly, true, false, false, false),
+            0x09 => (MapperType::RomOnly, true, true, false, false),
+            0x0F => (MapperType::MBC3, false, true, true, false),
+            0x10 => (MapperType::MBC3, true, true, true, false),
+            0x11 => (MapperType::MBC3, false, false, false, false),
+            0x12 => (MapperType::MBC3, true, false, false, false),
+            0x13 => (MapperType::MBC3, true, true, false, false),
+            0x19 => (MapperType::MBC5, false, false, false, false),
+            0x1A => (MapperType::MBC5, true, false, false, false),
+            0x1B => (MapperType::MBC5, true, true, false, false),
+            0x1C => (MapperType::MBC5, false, false, false, true),
+            0x1D => (MapperType::MBC5, true, false, false, true),
+            0x1E => (MapperType::MBC5, true, true, false, true),
             _ => panic!("Unsupported mapper: 0x{:02x}", id)
         };
 
+        // MBC2 has its RAM built into the mapper chip, so the header always reports none.
+        let ram_size = if mapper == MapperType::MBC2 {
+            MBC2_RAM_SIZE
+        } else {
+            ram_size
+        };
+
         CartridgeType {
             mapper,
             has_ram,
             has_battery,
+            has_timer,
+            has_rumble,
             rom_size,
             ram_size
         }
@@ -131,7 +220,12 @@ impl CartridgeHeader {
           logo[i] = *b;
         }*/
 
-        let title_bytes = &bytes[0x134..0x144];
+        // Bit 7 of the CGB flag is set by cartridges that support the CGB's color features. On
+        // those, the flag takes up the last byte of the title.
+        let supports_gbc = bytes[0x143] & 0x80 != 0;
+        let title_end = if supports_gbc { 0x143 } else { 0x144 };
+
+        let title_bytes = &bytes[0x134..title_end];
         let first_zero = title_bytes.iter().position(|b| *b == 0).unwrap_or(15);
         let title = std::str::from_utf8(&title_bytes[..first_zero])
             .map(|s| s.to_string())
@@ -145,10 +239,31 @@ impl CartridgeHeader {
         CartridgeHeader {
             //logo: LogoWrapper(logo),
             title: title,
-            supports_gbc: false,
+            supports_gbc,
             supports_sgb: false,
             cartridge_type,
             is_japanese: false
         }
     }
+
+    // Cartridges run on CGB hardware only if they use its features.
+    pub fn get_device_type(&self) -> DeviceType {
+        if self.supports_gbc {
+            DeviceType::GameBoyColor
+        } else {
+            DeviceType::GameBoy
+        }
+    }
+}
+
+impl SaveState for Cartridge {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.memory.ram);
+        self.mapper.write_state(writer);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        reader.read_bytes_into(&mut self.memory.ram)?;
+        self.mapper.read_state(reader)
+    }
 }
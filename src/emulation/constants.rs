@@ -1,14 +1,20 @@
 pub const GB_CYCLES_PER_SEC: u32 = 4194000;
 pub const GB_FRAME_RATE: f64 = 59.7;
+// The APU is sampled once per machine cycle.
+pub const APU_CYCLES_PER_SAMPLE: u32 = 4;
+pub const APU_SAMPLE_RATE: u32 = GB_CYCLES_PER_SEC / APU_CYCLES_PER_SAMPLE;
 
 pub const SCREEN_WIDTH: u32 = 160;
 pub const SCREEN_HEIGHT: u32 = 144;
 pub const TILE_SIZE: usize = 8;
+// Tile data fills 0x8000-0x97FF.
+pub const VRAM_TILE_COUNT: usize = 384;
 
 pub const RAM_BANK_SIZE: usize = 4096;
 pub const VRAM_BANK_SIZE: usize = 8192;
 pub const CARTRIDGE_RAM_BANK_SIZE: usize = 8192;
 pub const ROM_BANK_SIZE: usize = 16384;
+pub const MBC2_RAM_SIZE: usize = 512;
 
 pub const GB_RAM_SIZE: usize = 8192;
 pub const GB_VRAM_SIZE: usize = 8192;
@@ -34,6 +40,7 @@ pub const RAM_BANK_N_END: u16 = 0xDFFF;
 
 pub const ECHO_RAM_START: u16 = 0xE000;
 pub const ECHO_RAM_END: u16 = 0xFDFF;
+pub const ECHO_RAM_OFFSET: u16 = ECHO_RAM_START - RAM_BANK_0_START;
 pub const OAM_START: u16 = 0xFE00;
 pub const OAM_END: u16 = 0xFE9F;
 pub const IO_START: u16 = 0xFF00;
@@ -42,9 +49,15 @@ pub const TIMER_IO_END: u16 = 0xFF07;
 pub const INTERRUPT_REQUEST: u16 = 0xFF0F;
 pub const AUDIO_IO_START: u16 = 0xFF10;
 pub const AUDIO_IO_END: u16 = 0xFF3F;
+pub const SPEED_SWITCH: u16 = 0xFF4D;
 pub const VIDEO_IO_START: u16 = 0xFF40;
 pub const VIDEO_IO_END: u16 = 0xFF4F;
 pub const BOOTROM_UNMAP: u16 = 0xFF50;
+pub const VRAM_DMA_IO_START: u16 = 0xFF51;
+pub const VRAM_DMA_IO_END: u16 = 0xFF55;
+pub const COLOR_PALETTE_IO_START: u16 = 0xFF68;
+pub const COLOR_PALETTE_IO_END: u16 = 0xFF6B;
+pub const RAM_BANK_SELECT: u16 = 0xFF70;
 pub const IO_END: u16 = 0xFF7F;
 pub const HIGH_RAM_START: u16 = 0xFF80;
 pub const HIGH_RAM_END: u16 = 0xFFFE;
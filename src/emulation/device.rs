@@ -1,13 +1,17 @@
 use std::collections::HashSet;
+use std::io;
 use std::io::{stdin, Read};
 
-use crate::emulation::address_mapper::{AddressMapper, Addressable};
+use crate::emulation::address_mapper::Addressable;
+use crate::emulation::audio::sink::AudioSink;
 use crate::emulation::bitutils::*;
 use crate::emulation::bus::Bus;
+use crate::emulation::cartridge::Cartridge;
 use crate::emulation::constants::*;
 use crate::emulation::input::InputState;
 use crate::emulation::internal_message::{InternalMessage, RendererMessage};
 use crate::emulation::registers::Registers;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
 
 use crate::emulation::instruction::Operand16::*;
 use crate::emulation::instruction::Operand8::*;
@@ -56,13 +60,34 @@ impl DeviceType {
     pub fn get_vram_bank_count(&self) -> usize {
         (self.get_device_info().vram_size / VRAM_BANK_SIZE) as usize
     }
+
+    pub fn is_color(&self) -> bool {
+        matches!(*self, DeviceType::GameBoyColor)
+    }
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExecutionState {
     Halted,
     Paused,
-    Running
+    Running,
+    // Executing an illegal opcode hangs the CPU until the system is reset.
+    Locked,
+    // STOP halts the system clock until a button is pressed.
+    Stopped
+}
+
+impl ExecutionState {
+    fn decode(value: u8) -> Option<ExecutionState> {
+        match value {
+            0 => Some(ExecutionState::Halted),
+            1 => Some(ExecutionState::Paused),
+            2 => Some(ExecutionState::Running),
+            3 => Some(ExecutionState::Locked),
+            4 => Some(ExecutionState::Stopped),
+            _ => None
+        }
+    }
 }
 
 pub enum DebugState {
@@ -75,10 +100,22 @@ pub struct Device {
     pub bus: Bus,
     pub execution_state: ExecutionState,
     pub interrupts_enabled: bool,
+    // EI only sets IME after the instruction following it has executed.
+    pub enable_interrupts_pending: bool,
     pub debug_state: DebugState,
+    // Set when LD B, B is executed. Never cleared by the device itself.
+    pub software_breakpoint_hit: bool,
 
     breakpoints: HashSet<u16>,
-    renderer_messages: Vec<RendererMessage>
+    // Set when HALT is skipped because of the HALT bug, which causes PC to not be incremented
+    // after the next opcode fetch.
+    halt_bug: bool,
+    // T-cycles the rest of the system has been advanced by since the start of the tick.
+    tick_cycles: u32,
+    renderer_messages: Vec<RendererMessage>,
+    // Reused for the messages the PPU produces during a machine cycle.
+    video_messages: Vec<InternalMessage>,
+    audio_sink: Option<Box<dyn AudioSink>>
 }
 
 impl Device {
@@ -87,10 +124,16 @@ impl Device {
             regs: Registers::new(),
             bus: Bus::new(device, bootrom),
             execution_state: ExecutionState::Running,
-            interrupts_enabled: true,
+            interrupts_enabled: false,
+            enable_interrupts_pending: false,
             debug_state: DebugState::Default,
+            software_breakpoint_hit: false,
             breakpoints: HashSet::new(),
-            renderer_messages: Vec::with_capacity(16)
+            halt_bug: false,
+            tick_cycles: 0,
+            renderer_messages: Vec::with_capacity(16),
+            video_messages: Vec::with_capacity(4),
+            audio_sink: None
         };
 
         if device.bus.bootrom.is_none() {
@@ -103,16 +146,28 @@ impl Device {
     pub fn simulate_bootrom(&mut self) {
         self.regs.pc = 0x100;
         self.regs.sp = 0xFFFE;
-        self.regs.set_af(0x01B0);
-        self.regs.set_bc(0x0013);
-        self.regs.set_de(0x00D8);
-        self.regs.set_hl(0x014D);
+
+        // Games tell the CGB apart by the value the boot ROM leaves in A.
+        if self.bus.device_type.is_color() {
+            self.regs.set_af(0x1180);
+            self.regs.set_bc(0x0000);
+            self.regs.set_de(0xFF56);
+            self.regs.set_hl(0x000D);
+        } else {
+            self.regs.set_af(0x01B0);
+            self.regs.set_bc(0x0013);
+            self.regs.set_de(0x00D8);
+            self.regs.set_hl(0x014D);
+        }
 
         self.bus.is_booting = false;
 
         self.write_addr_8(0xFF05, 0x00); // TIMA
         self.write_addr_8(0xFF06, 0x00); // TMA
         self.write_addr_8(0xFF07, 0x00); // TAC
+
+        // The APU ignores register writes while it is powered off.
+        self.write_addr_8(0xFF26, 0xF1); // NR52
         self.write_addr_8(0xFF10, 0x80); // NR10
         self.write_addr_8(0xFF11, 0xBF); // NR11
         self.write_addr_8(0xFF12, 0xF3); // NR12
@@ -130,7 +185,6 @@ impl Device {
         self.write_addr_8(0xFF23, 0xBF); // NR30
         self.write_addr_8(0xFF24, 0x77); // NR50
         self.write_addr_8(0xFF25, 0xF3); // NR51
-        self.write_addr_8(0xFF26, 0xF1); // NR52
         self.write_addr_8(0xFF40, 0x91); // LCDC
         self.write_addr_8(0xFF42, 0x00); // SCY
         self.write_addr_8(0xFF43, 0x00); // SCX
@@ -149,8 +203,78 @@ impl Device {
 
     pub fn read_next_byte(&mut self) -> u8 {
         let pc = self.regs.pc;
-        self.regs.pc += 1;
-        self.bus.read_8(self.bus.resolve_address(pc))
+
+        if self.halt_bug {
+            self.halt_bug = false;
+        } else {
+            self.regs.pc = pc.wrapping_add(1);
+        }
+
+        self.read_cycle(pc)
+    }
+
+    // Advances everything except the CPU by one machine cycle. In double speed mode, the PPU and
+    // APU keep their pace, so they only see half as many cycles, while the timer runs along
+    // with the CPU.
+    pub fn tick_m_cycle(&mut self) {
+        let cycles = if self.bus.is_double_speed { 2 } else { 4 };
+
+        self.bus.step_oam_dma();
+
+        if self.bus.video.is_lcd_on() {
+            let mut messages = std::mem::take(&mut self.video_messages);
+            self.bus.video.update(cycles, &mut messages);
+
+            for message in messages.drain(..) {
+                self.handle_message(message);
+            }
+
+            self.video_messages = messages;
+        }
+
+        self.bus.audio.update(cycles, self.audio_sink.as_mut());
+
+        let timer_message = self.bus.timer.update();
+        self.handle_message(timer_message);
+
+        self.tick_cycles += 4;
+    }
+
+    // Every CPU memory access takes one machine cycle, and the access itself happens at the
+    // end of it, so the rest of the system is advanced first.
+    pub fn read_cycle(&mut self, addr: u16) -> u8 {
+        self.tick_m_cycle();
+
+        if let Some(value) = self.bus.oam_dma.get_conflicting_value(addr) {
+            value
+        } else if self.bus.video.is_cpu_access_blocked(addr) {
+            0xFF
+        } else {
+            self.bus.read_addr_8(addr)
+        }
+    }
+
+    // Writes that conflict with OAM DMA are lost.
+    pub fn write_cycle(&mut self, addr: u16, value: u8) {
+        self.tick_m_cycle();
+
+        if self.bus.oam_dma.get_conflicting_value(addr).is_none()
+            && !self.bus.video.is_cpu_access_blocked(addr)
+        {
+            self.write_addr_8(addr, value);
+        }
+    }
+
+    pub fn read_cycle_16(&mut self, addr: u16) -> u16 {
+        let low = self.read_cycle(addr);
+        let high = self.read_cycle(addr.wrapping_add(1));
+        u16_from_bytes(high, low)
+    }
+
+    pub fn write_cycle_16(&mut self, addr: u16, value: u16) {
+        let BytePair { high, low } = u16_to_pair(value);
+        self.write_cycle(addr, low);
+        self.write_cycle(addr.wrapping_add(1), high);
     }
 
     fn decode_next_instruction(&mut self) -> Instruction {
@@ -166,29 +290,21 @@ impl Device {
         self.handle_message(msg);
     }
 
-    pub fn write_addr_16(&mut self, addr: u16, value: u16) {
-        let BytePair { high, low } = u16_to_pair(value);
-        let msg1 = self.bus.write_addr_8(addr, low);
-        self.handle_message(msg1);
-        let msg2 = self.bus.write_addr_8(addr + 1, high);
-        self.handle_message(msg2);
-    }
-
+    // Pushing spends an internal cycle decrementing SP before writing the high byte.
     pub fn push_16(&mut self, value: u16) {
         let BytePair { high, low } = u16_to_pair(value);
-        let sp = self.regs.sp;
 
-        self.write_addr_8(sp, low);
-        self.write_addr_8(sp - 1, high);
-
-        self.regs.sp -= 2;
+        self.tick_m_cycle();
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.write_cycle(self.regs.sp, high);
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.write_cycle(self.regs.sp, low);
     }
 
     pub fn pop_16(&mut self) -> u16 {
-        let high = self.bus.read_addr_8(self.regs.sp + 1);
-        let low = self.bus.read_addr_8(self.regs.sp + 2);
-        self.regs.sp += 2;
-        u16_from_bytes(high, low)
+        let value = self.read_cycle_16(self.regs.sp);
+        self.regs.sp = self.regs.sp.wrapping_add(2);
+        value
     }
 
     fn handle_message(&mut self, message: InternalMessage) {
@@ -201,25 +317,61 @@ impl Device {
                 self.bus.interrupt.request_interrupt(interrupt);
             }
             InternalMessage::RendererMessage(msg) => self.renderer_messages.push(msg),
-            InternalMessage::DMATransfer { from } => {
-                self.bus.oam_dma_transfer(from);
-            }
+            InternalMessage::DMATransfer { from } => self.bus.oam_dma.start((from >> 8) as u8),
+            InternalMessage::HblankStarted => self.bus.vram_dma.start_hblank()
+        }
+    }
+
+    // Each block of a VRAM DMA transfer takes 8 microseconds in either speed mode.
+    fn run_vram_dma_block(&mut self) {
+        self.bus.vram_dma_transfer_block();
+
+        let m_cycles = if self.bus.is_double_speed { 16 } else { 8 };
+        for _ in 0..m_cycles {
+            self.tick_m_cycle();
         }
     }
 
     fn check_interrupts(&mut self) {
-        if let Some(interrupt) = self.bus.interrupt.handle_next_interrupt() {
-            if self.execution_state == ExecutionState::Halted {
-                self.execution_state = ExecutionState::Running;
-            }
+        if !self.bus.interrupt.has_pending_interrupt() {
+            return;
+        }
 
-            self.interrupts_enabled = false;
-            let pc = self.regs.pc;
-            self.push_16(pc);
-            self.regs.pc = interrupt.get_handler_address();
+        // A pending interrupt ends HALT even with IME off, in which case execution simply
+        // continues after the HALT instruction.
+        if self.execution_state == ExecutionState::Halted {
+            self.execution_state = ExecutionState::Running;
         }
+
+        if self.interrupts_enabled {
+            self.dispatch_interrupt();
+        }
+    }
+
+    // Dispatching takes five machine cycles: two wait states, pushing PC and the jump.
+    fn dispatch_interrupt(&mut self) {
+        self.interrupts_enabled = false;
+        self.enable_interrupts_pending = false;
+
+        let BytePair { high, low } = u16_to_pair(self.regs.pc);
+
+        self.tick_m_cycle();
+        self.tick_m_cycle();
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.write_cycle(self.regs.sp, high);
+
+        // The interrupt is only chosen after the high byte of PC has been pushed. If that
+        // write lands on IE and disables the interrupt, execution continues at 0x0000.
+        let interrupt = self.bus.interrupt.handle_next_interrupt();
+
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.write_cycle(self.regs.sp, low);
+        self.tick_m_cycle();
+
+        self.regs.pc = interrupt.map_or(0, |interrupt| interrupt.get_handler_address());
     }
 
+    // Returns the time taken in normal speed cycles.
     pub fn run_tick(&mut self) -> u32 {
         if self.breakpoints.contains(&self.regs.pc) {
             self.debug_state = DebugState::HandlingBreakpoint;
@@ -227,27 +379,55 @@ impl Device {
             self.debug_state = DebugState::Default;
         }
 
-        let mut elapsed_cycles = 4;
-
-        if self.execution_state != ExecutionState::Halted {
-            elapsed_cycles = self.run_instruction();
+        if self.execution_state == ExecutionState::Stopped {
+            // Nothing is clocked until a button press brings the system out of STOP.
+            return 4;
         }
 
-        if self.bus.video.is_lcd_on() {
-            let gpu_message = self.bus.video.update(elapsed_cycles);
-            self.handle_message(gpu_message);
-        }
+        self.tick_cycles = 0;
 
-        for _ in 0..elapsed_cycles {
-            let timer_message = self.bus.timer.update();
-            self.handle_message(timer_message);
+        // The CPU is stopped while VRAM DMA copies a block, so a general purpose transfer takes
+        // a tick per block.
+        if self.bus.vram_dma.is_block_due() {
+            self.run_vram_dma_block();
+        } else {
+            let enable_interrupts = self.enable_interrupts_pending;
+
+            let instruction_cycles = if self.execution_state == ExecutionState::Running {
+                self.run_instruction()
+            } else {
+                4
+            };
+
+            // Memory accesses have already ticked the system. What's left are the internal
+            // cycles the instruction spent without touching the bus.
+            while self.tick_cycles < instruction_cycles {
+                self.tick_m_cycle();
+            }
+
+            // A DI right after EI cancels the pending enable.
+            if enable_interrupts && self.enable_interrupts_pending {
+                self.enable_interrupts_pending = false;
+                self.interrupts_enabled = true;
+            }
         }
 
-        if self.interrupts_enabled {
+        if self.execution_state != ExecutionState::Locked && !self.bus.vram_dma.is_block_due() {
             self.check_interrupts();
         }
 
-        elapsed_cycles
+        if self.bus.is_double_speed {
+            self.tick_cycles / 2
+        } else {
+            self.tick_cycles
+        }
+    }
+
+    pub fn set_audio_sink(
+        &mut self,
+        sink: Option<Box<dyn AudioSink>>
+    ) -> Option<Box<dyn AudioSink>> {
+        std::mem::replace(&mut self.audio_sink, sink)
     }
 
     pub fn next_renderer_message(&mut self) -> Option<RendererMessage> {
@@ -263,11 +443,95 @@ impl Device {
     }
 
     pub fn update_input(&mut self, state: InputState) {
-        self.bus.input.update(state)
+        self.bus.input.update(state);
+
+        if self.execution_state == ExecutionState::Stopped && self.bus.input.is_any_pressed() {
+            self.execution_state = ExecutionState::Running;
+        }
     }
 
     pub fn halt(&mut self) {
-        self.execution_state = ExecutionState::Halted;
+        if !self.interrupts_enabled && self.bus.interrupt.has_pending_interrupt() {
+            // The HALT bug: with IME off and an interrupt already pending, HALT exits
+            // immediately and the byte after it is read twice.
+            self.halt_bug = true;
+        } else {
+            self.execution_state = ExecutionState::Halted;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        // Skip the padding byte that follows STOP.
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        // STOP resets the divider.
+        self.write_addr_8(0xFF04, 0);
+
+        // Once a speed switch has been armed, STOP performs it and execution carries on.
+        if !self.bus.switch_speed_if_armed() {
+            self.execution_state = ExecutionState::Stopped;
+        }
+    }
+
+    pub fn lock_up(&mut self) {
+        self.execution_state = ExecutionState::Locked;
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+        self.write_state(&mut writer);
+        writer.into_bytes()
+    }
+
+    // The state is loaded into a fresh device with the same cartridge inserted, which only
+    // replaces this one once it has been read completely, so a bad state leaves it untouched.
+    pub fn load_state(&mut self, state: &[u8]) -> io::Result<()> {
+        let mut reader = StateReader::new(state)?;
+        let mut device = Device::new(self.bus.device_type, self.bus.bootrom.clone());
+        device.bus.cartridge = self.bus.cartridge.as_ref().map(Cartridge::empty_copy);
+        device.read_state(&mut reader)?;
+
+        if !reader.is_at_end() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Save state has trailing data"
+            ));
+        }
+
+        // Everything that isn't part of the state carries over.
+        device.debug_state = std::mem::replace(&mut self.debug_state, DebugState::Default);
+        device.software_breakpoint_hit = self.software_breakpoint_hit;
+        device.breakpoints = std::mem::take(&mut self.breakpoints);
+        device.audio_sink = self.audio_sink.take();
+        device.bus.serial_output = std::mem::take(&mut self.bus.serial_output);
+        if let (Some(loaded), Some(inserted)) = (&mut device.bus.cartridge, &mut self.bus.cartridge)
+        {
+            loaded.memory.rom = std::mem::take(&mut inserted.memory.rom);
+        }
+        *self = device;
+
+        Ok(())
+    }
+}
+
+impl SaveState for Device {
+    fn write_state(&self, writer: &mut StateWriter) {
+        self.regs.write_state(writer);
+        writer.write_u8(self.execution_state as u8);
+        writer.write_bool(self.interrupts_enabled);
+        writer.write_bool(self.enable_interrupts_pending);
+        writer.write_bool(self.halt_bug);
+        self.bus.write_state(writer);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.regs.read_state(reader)?;
+        self.execution_state = reader.read_enum(ExecutionState::decode)?;
+        self.interrupts_enabled = reader.read_bool()?;
+        self.enable_interrupts_pending = reader.read_bool()?;
+        self.halt_bug = reader.read_bool()?;
+        self.bus.read_state(reader)?;
+        self.renderer_messages.clear();
+        Ok(())
     }
 }
 
@@ -282,14 +546,14 @@ impl ReadOnlyByteStream for Device {
 }
 
 pub trait ReadWriteRegisters {
-    fn get_operand_8(&self, operand: Operand8) -> u8;
+    fn get_operand_8(&mut self, operand: Operand8) -> u8;
     fn get_operand_16(&self, operand: Operand16) -> u16;
     fn set_operand_8(&mut self, operand: Operand8, value: u8);
     fn set_operand_16(&mut self, operand: Operand16, value: u16);
 }
 
 impl ReadWriteRegisters for Device {
-    fn get_operand_8(&self, operand: Operand8) -> u8 {
+    fn get_operand_8(&mut self, operand: Operand8) -> u8 {
         match operand {
             A => self.regs.a,
             B => self.regs.b,
@@ -298,7 +562,10 @@ impl ReadWriteRegisters for Device {
             E => self.regs.e,
             H => self.regs.h,
             L => self.regs.l,
-            MemoryReference => self.bus.read_addr_8(self.regs.hl()),
+            MemoryReference => {
+                let addr = self.regs.hl();
+                self.read_cycle(addr)
+            }
             Immediate(value) => value
         }
     }
@@ -314,7 +581,7 @@ impl ReadWriteRegisters for Device {
             L => self.regs.l = value,
             MemoryReference => {
                 let addr = self.regs.hl();
-                self.write_addr_8(addr, value);
+                self.write_cycle(addr, value);
             }
             Immediate(_) => panic!("Tried to set an immediate value")
         }
@@ -1,4 +1,7 @@
+use std::io;
+
 use crate::emulation::bitutils::BitExtensions;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
 
 const RIGHT_OR_A_BIT: u8 = 0;
 const LEFT_OR_B_BIT: u8 = 1;
@@ -12,6 +15,17 @@ enum ButtonSet {
     Actions
 }
 
+impl ButtonSet {
+    fn decode(value: u8) -> Option<ButtonSet> {
+        match value {
+            0 => Some(ButtonSet::None),
+            1 => Some(ButtonSet::Arrows),
+            2 => Some(ButtonSet::Actions),
+            _ => None
+        }
+    }
+}
+
 fn encode_joypad_state(state: InputState, selected_set: ButtonSet) -> u8 {
     let mut value = match selected_set {
         ButtonSet::None => 0b1111_1111,
@@ -75,6 +89,18 @@ impl InputRegister {
     pub fn update(&mut self, new_state: InputState) {
         self.input_state = new_state;
     }
+
+    pub fn is_any_pressed(&self) -> bool {
+        let state = self.input_state;
+        state.left
+            || state.right
+            || state.up
+            || state.down
+            || state.a
+            || state.b
+            || state.select
+            || state.start
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -92,3 +118,15 @@ pub struct InputState {
 trait InputHandler {
     fn get_input_state(&self) -> InputState;
 }
+
+impl SaveState for InputRegister {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.selected_set as u8);
+    }
+
+    // The pressed buttons are host input rather than emulated state, so they are left alone.
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.selected_set = reader.read_enum(ButtonSet::decode)?;
+        Ok(())
+    }
+}
@@ -42,7 +42,7 @@ impl Operand8 {
         }
     }
 
-    pub fn get(self, device: &Device) -> u8 {
+    pub fn get(self, device: &mut Device) -> u8 {
         device.get_operand_8(self)
     }
 
@@ -109,7 +109,8 @@ pub enum Instruction {
     LoadAHigh(u8),
     StoreAHigh(u8),
     StoreAHighC,
-    MoveSPOffsetToHL(u8),
+    LoadAHighC,
+    MoveSPOffsetToHL(i8),
     MoveHLToSP,
     StoreSP(u16),
     AddOperandToA(Operand8),
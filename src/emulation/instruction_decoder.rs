@@ -84,6 +84,7 @@ pub fn decode_instruction(device: &mut impl ReadOnlyByteStream) -> Instruction {
         (1, 1, 1, 1, 0, 0, 0, 0) => LoadAHigh(device.read_next_byte()),
         (1, 1, 1, 0, 0, 0, 0, 0) => StoreAHigh(device.read_next_byte()),
         (1, 1, 1, 0, 0, 0, 1, 0) => StoreAHighC,
+        (1, 1, 1, 1, 0, 0, 1, 0) => LoadAHighC,
         (0, 0, 0, 0, 1, 0, 0, 0) => StoreSP(device.read_next_16()),
         (1, 0, 0, 0, carry, s2, s1, s0) => {
             let register = to_operand(s2, s1, s0);
@@ -133,8 +134,9 @@ pub fn decode_instruction(device: &mut impl ReadOnlyByteStream) -> Instruction {
                 IncrementOperand16(operand_16)
             }
         }
-        (1, 1, 1, 1, 1, 0, 0, 0) => MoveSPOffsetToHL(device.read_next_byte()),
+        (1, 1, 1, 1, 1, 0, 0, 0) => MoveSPOffsetToHL(device.read_next_byte() as i8),
         (1, 1, 1, 1, 1, 0, 0, 1) => MoveHLToSP,
+        (1, 1, 1, 0, 1, 0, 0, 0) => AddSignedImmediateToSP(device.read_next_byte() as i8),
         (0, 0, r1, r0, 1, 0, 0, 1) => AddOperandToHL(as_operand_16(r1, r0)),
         (0, 0, 1, 0, 0, 1, 1, 1) => BCDCorrectA,
         (1, 0, 1, 0, 0, s2, s1, s0) => AndOperandWithA(to_operand(s2, s1, s0)),
@@ -157,8 +159,8 @@ pub fn decode_instruction(device: &mut impl ReadOnlyByteStream) -> Instruction {
         (0, 0, 1, 1, 1, 1, 1, 1) => ComplementCarry,
         (0, 0, 1, 1, 0, 1, 1, 1) => SetCarry,
         (1, 1, 0, 0, 0, 0, 1, 1) => Jump(device.read_next_16()),
-        (1, 1, c2, c1, c0, 0, 1, 0) => {
-            ConditionalJump(to_condition_code(c2, c1, c0), device.read_next_16())
+        (1, 1, 0, c1, c0, 0, 1, 0) => {
+            ConditionalJump(to_condition_code(0, c1, c0), device.read_next_16())
         }
         (0, 0, 0, 1, 1, 0, 0, 0) => RelativeJump(device.read_next_byte() as i8),
         (0, 0, 1, 0, 0, 0, 0, 0) => {
@@ -174,12 +176,12 @@ pub fn decode_instruction(device: &mut impl ReadOnlyByteStream) -> Instruction {
             ConditionalRelativeJump(ConditionCode::Carry(true), device.read_next_byte() as i8)
         }
         (1, 1, 0, 0, 1, 1, 0, 1) => Call(device.read_next_16()),
-        (1, 1, c2, c1, c0, 1, 0, 0) => {
-            ConditionalCall(to_condition_code(c2, c1, c0), device.read_next_16())
+        (1, 1, 0, c1, c0, 1, 0, 0) => {
+            ConditionalCall(to_condition_code(0, c1, c0), device.read_next_16())
         }
         (1, 1, 0, 0, 1, 0, 0, 1) => Return,
         (1, 1, 0, 1, 1, 0, 0, 1) => ReturnFromInterrupt,
-        (1, 1, c2, c1, c0, 0, 0, 0) => ConditionalReturn(to_condition_code(c2, c1, c0)),
+        (1, 1, 0, c1, c0, 0, 0, 0) => ConditionalReturn(to_condition_code(0, c1, c0)),
         (1, 1, n2, n1, n0, 1, 1, 1) => Restart(to_byte_3(n2, n1, n0)),
         (1, 1, 1, 0, 1, 0, 0, 1) => JumpToHL,
         (1, 1, r1, r0, 0, 1, 0, 1) => Push(as_operand_16(r1, r0)),
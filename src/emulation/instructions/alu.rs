@@ -52,6 +52,31 @@ pub fn subtract_operand_8_from_a(device: &mut Device, operand: Operand8) -> u32
     }
 }
 
+pub fn subtract_operand_and_borrow_from_a(device: &mut Device, operand: Operand8) -> u32 {
+    let a = A.get(device);
+    let op = operand.get(device);
+    let borrow = device.regs.get_flag(StatusFlag::C);
+
+    let CarryAddResult {
+        result,
+        carry,
+        half_carry
+    } = borrow_sub_8_prev_borrow(a, op, borrow);
+
+    A.set(device, result);
+
+    device.regs.set_flag(StatusFlag::N);
+    device.regs.set_flag_to(StatusFlag::Z, result == 0);
+    device.regs.set_flag_to(StatusFlag::C, carry);
+    device.regs.set_flag_to(StatusFlag::H, half_carry);
+
+    if operand.is_memref() || operand.is_immediate() {
+        8
+    } else {
+        4
+    }
+}
+
 pub fn increment_operand_8(device: &mut Device, operand: Operand8) -> u32 {
     let a = operand.get(device);
 
@@ -104,7 +129,7 @@ pub fn add_operand_and_carry_to_a(device: &mut Device, operand: Operand8) -> u32
     device.regs.clear_flag(StatusFlag::N);
     device.regs.set_flag_to(StatusFlag::Z, result == 0);
     device.regs.set_flag_to(StatusFlag::C, carry);
-    device.regs.set_flag_to(StatusFlag::Z, half_carry);
+    device.regs.set_flag_to(StatusFlag::H, half_carry);
 
     A.set(device, result);
 
@@ -132,6 +157,21 @@ pub fn add_operand_to_hl(device: &mut Device, operand: Operand16) -> u32 {
     8
 }
 
+pub fn add_signed_immediate_to_sp(device: &mut Device, offset: i8) -> u32 {
+    let CarryAddResult {
+        result,
+        carry,
+        half_carry
+    } = add_signed_offset_16(device.regs.sp, offset);
+
+    device.regs.sp = result;
+    device.regs.clear_flag(StatusFlag::Z);
+    device.regs.clear_flag(StatusFlag::N);
+    device.regs.set_flag_to(StatusFlag::H, half_carry);
+    device.regs.set_flag_to(StatusFlag::C, carry);
+    16
+}
+
 pub fn set_carry_flag(device: &mut Device) -> u32 {
     device.regs.set_flag(StatusFlag::C);
     device.regs.clear_flag(StatusFlag::N);
@@ -173,7 +213,7 @@ mod tests {
 
         add_operand_8_to_a(&mut device, A);
 
-        assert_eq!(200, A.get(&device));
+        assert_eq!(200, A.get(&mut device));
         assert_eq!(initial_flags, device.regs.f);
     }
 
@@ -184,7 +224,7 @@ mod tests {
 
         add_operand_8_to_a(&mut device, A);
 
-        assert_eq!(0, A.get(&device));
+        assert_eq!(0, A.get(&mut device));
         assert_eq!(
             true,
             device.regs.get_flag(StatusFlag::Z),
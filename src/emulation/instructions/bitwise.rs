@@ -71,12 +71,13 @@ pub fn shift_left_logical(device: &mut Device, operand: Operand8) -> u32 {
 
     device.regs.set_flag_to(StatusFlag::C, last_bit);
     device.regs.set_flag_to(StatusFlag::Z, op == 0);
+    device.regs.clear_flag(StatusFlag::N);
     device.regs.clear_flag(StatusFlag::H);
 
     operand.set(device, op);
 
     if operand.is_memref() {
-        12
+        16
     } else {
         8
     }
@@ -102,12 +103,13 @@ fn shift_right_internal(device: &mut Device, operand: Operand8, bit_7: Bit7Value
 
     device.regs.set_flag_to(StatusFlag::C, first_bit);
     device.regs.set_flag_to(StatusFlag::Z, op == 0);
+    device.regs.clear_flag(StatusFlag::N);
     device.regs.clear_flag(StatusFlag::H);
 
     operand.set(device, op);
 
     if operand.is_memref() {
-        12
+        16
     } else {
         8
     }
@@ -129,12 +131,13 @@ pub fn shift_left(device: &mut Device, operand: Operand8) -> u32 {
 
     device.regs.set_flag_to(StatusFlag::C, last_bit);
     device.regs.set_flag_to(StatusFlag::Z, op == 0);
+    device.regs.clear_flag(StatusFlag::N);
     device.regs.clear_flag(StatusFlag::H);
 
     operand.set(device, op);
 
     if operand.is_memref() {
-        12
+        16
     } else {
         8
     }
@@ -161,7 +164,7 @@ fn rotate_right_internal(device: &mut Device, operand: Operand8, modify_zero: bo
 pub fn rotate_right(device: &mut Device, operand: Operand8) -> u32 {
     rotate_right_internal(device, operand, true);
     if operand.is_memref() {
-        12
+        16
     } else {
         8
     }
@@ -193,7 +196,7 @@ fn rotate_left_internal(device: &mut Device, operand: Operand8, modify_zero: boo
 pub fn rotate_left(device: &mut Device, operand: Operand8) -> u32 {
     rotate_left_internal(device, operand, true);
     if operand.is_memref() {
-        12
+        16
     } else {
         8
     }
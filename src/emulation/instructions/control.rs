@@ -50,6 +50,9 @@ pub fn unconditional_return(device: &mut Device) -> u32 {
 }
 
 pub fn conditional_return(device: &mut Device, condition: ConditionCode) -> u32 {
+    // The condition is evaluated in an internal cycle before the return address is popped.
+    device.tick_m_cycle();
+
     if check_condition(device, condition) {
         unconditional_return(device);
         20
@@ -2,12 +2,13 @@ use crate::emulation::device::Device;
 use crate::emulation::registers::StatusFlag;
 
 pub fn enable_interrupts(device: &mut Device) -> u32 {
-    device.interrupts_enabled = true;
+    device.enable_interrupts_pending = true;
     4
 }
 
 pub fn disable_interrupts(device: &mut Device) -> u32 {
     device.interrupts_enabled = false;
+    device.enable_interrupts_pending = false;
     4
 }
 
@@ -38,5 +39,5 @@ pub fn bcd_correct_a(device: &mut Device) -> u32 {
         .regs
         .set_flag_to(StatusFlag::Z, corrected_value_byte == 0);
 
-    8
+    4
 }
@@ -19,23 +19,6 @@ pub fn move_hl_to_sp(device: &mut Device) -> u32 {
     8
 }
 
-pub fn move_sp_with_offset_to_hl(device: &mut Device, offset: u8) -> u32 {
-    let sp = device.regs.sp;
-    let CarryAddResult {
-        result,
-        carry,
-        half_carry
-    } = carry_add_16(sp, offset as u16);
-
-    device.regs.set_hl(result);
-    device.regs.clear_flag(StatusFlag::Z);
-    device.regs.clear_flag(StatusFlag::N);
-    device.regs.set_flag_to(StatusFlag::H, half_carry);
-    device.regs.set_flag_to(StatusFlag::C, carry);
-
-    12
-}
-
 pub fn push_16(device: &mut Device, operand: Operand16) -> u32 {
     let op = operand.get(device);
     device.push_16(op);
@@ -50,22 +33,22 @@ pub fn pop_16(device: &mut Device, operand: Operand16) -> u32 {
 
 pub fn store_sp(device: &mut Device, address: u16) -> u32 {
     let sp = device.regs.sp;
-    device.write_addr_16(address, sp);
-    12
+    device.write_cycle_16(address, sp);
+    20
 }
 
-pub fn move_sp_offset_to_hl(device: &mut Device, offset: u8) -> u32 {
-    let a = offset as u16;
-    let b = device.regs.sp;
+pub fn move_sp_offset_to_hl(device: &mut Device, offset: i8) -> u32 {
     let CarryAddResult {
         result,
         carry,
         half_carry
-    } = carry_add_16(a, b);
+    } = add_signed_offset_16(device.regs.sp, offset);
 
+    device.regs.set_hl(result);
+    device.regs.clear_flag(StatusFlag::Z);
+    device.regs.clear_flag(StatusFlag::N);
     device.regs.set_flag_to(StatusFlag::H, half_carry);
     device.regs.set_flag_to(StatusFlag::C, carry);
-    device.regs.set_hl(result);
 
     12
 }
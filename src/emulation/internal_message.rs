@@ -5,6 +5,8 @@ pub enum InternalMessage {
     None,
     TriggerInterrupt(Interrupt),
     DMATransfer { from: u16 },
+    // Sent by the PPU when it enters H-blank on a visible line.
+    HblankStarted,
     RendererMessage(RendererMessage)
 }
 
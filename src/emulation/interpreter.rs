@@ -1,4 +1,3 @@
-use crate::emulation::address_mapper::Addressable;
 use crate::emulation::bitutils::*;
 use crate::emulation::device::{DebugState, Device, ReadWriteRegisters};
 use crate::emulation::instruction::Instruction::*;
@@ -35,10 +34,6 @@ pub fn run_instruction(device: &mut Device) -> u32 {
     let instruction = decode_instruction(device);
     // println!("{:?}", instruction);
 
-    if let Unknown(opcode) = instruction {
-        panic!("Unimplemented instruction: 0x{:04X}", opcode)
-    }
-
     if let DebugState::HandlingBreakpoint = device.debug_state {
         println!("BEFORE: {:?}, f: {:08b}", device.regs, device.regs.f);
         println!("{:?}", instruction);
@@ -51,18 +46,26 @@ pub fn run_instruction(device: &mut Device) -> u32 {
         //
         // 8-bit transfers
         //
+        // Mooneye's test ROMs use LD B, B as a software breakpoint to signal that they are done.
+        MoveOperand8 { to: B, from: B } => {
+            device.software_breakpoint_hit = true;
+            4
+        }
         MoveOperand8 { to, from } => {
             let value = device.get_operand_8(from);
             device.set_operand_8(to, value);
-            if from.is_memref() {
-                8
-            } else {
-                4
+            let mut cycles = 4;
+            if from.is_memref() || from.is_immediate() {
+                cycles += 4;
+            }
+            if to.is_memref() {
+                cycles += 4;
             }
+            cycles
         }
         LoadAHigh(offset) => {
             let addr = 0xFF00 + (offset as u16);
-            let value = device.bus.read_addr_8(addr);
+            let value = device.read_cycle(addr);
             A.set(device, value);
             12
         }
@@ -70,45 +73,52 @@ pub fn run_instruction(device: &mut Device) -> u32 {
             let c = device.get_operand_8(C);
             let a = device.get_operand_8(A);
             let address = 0xFF00 + c as u16;
-            device.write_addr_8(address, a);
+            device.write_cycle(address, a);
+            8
+        }
+        LoadAHighC => {
+            let c = device.get_operand_8(C);
+            let address = 0xFF00 + c as u16;
+            let value = device.read_cycle(address);
+            A.set(device, value);
             8
         }
         StoreAHigh(offset) => {
             let a = device.get_operand_8(A);
             let address = 0xFF00 + offset as u16;
-            device.write_addr_8(address, a);
+            device.write_cycle(address, a);
             12
         }
         LoadAIndirect(operand) => {
             let op = device.get_operand_16(operand);
-            let value = device.bus.read_addr_8(op);
+            let value = device.read_cycle(op);
             device.set_operand_8(A, value);
             8
         }
         StoreAIndirectHLIncrement => {
             let hl = HL.get(device);
             let a = A.get(device);
-            device.write_addr_8(hl, a);
-            device.regs.set_hl(hl + 1);
+            device.write_cycle(hl, a);
+            device.regs.set_hl(hl.wrapping_add(1));
             8
         }
         StoreAIndirectHLDecrement => {
             let hl = HL.get(device);
             let a = A.get(device);
-            device.write_addr_8(hl, a);
-            device.regs.set_hl(hl - 1);
+            device.write_cycle(hl, a);
+            device.regs.set_hl(hl.wrapping_sub(1));
             8
         }
         LoadAIndirectHLIncrement => {
             let hl = device.regs.hl();
-            let a = device.bus.read_addr_8(hl);
+            let a = device.read_cycle(hl);
             device.regs.a = a;
             device.regs.set_hl(hl.wrapping_add(1));
             8
         }
         LoadAIndirectHLDecrement => {
             let hl = device.regs.hl();
-            let a = device.bus.read_addr_8(hl);
+            let a = device.read_cycle(hl);
             device.regs.a = a;
             device.regs.set_hl(hl.wrapping_sub(1));
             8
@@ -116,16 +126,16 @@ pub fn run_instruction(device: &mut Device) -> u32 {
         StoreAIndirect(operand) => {
             let addr = operand.get(device);
             let a = device.regs.a;
-            device.bus.write_addr_8(addr, a);
+            device.write_cycle(addr, a);
             8
         }
         StoreA(addr) => {
             let a = A.get(device);
-            device.write_addr_8(addr, a);
+            device.write_cycle(addr, a);
             16
         }
         LoadA(addr) => {
-            let value = device.bus.read_addr_8(addr);
+            let value = device.read_cycle(addr);
             device.regs.a = value;
             16
         }
@@ -138,6 +148,7 @@ pub fn run_instruction(device: &mut Device) -> u32 {
         Pop(operand) => pop_16(device, operand),
         StoreSP(address) => store_sp(device, address),
         MoveSPOffsetToHL(offset) => move_sp_offset_to_hl(device, offset),
+        MoveHLToSP => move_hl_to_sp(device),
 
         //
         // 8-bit ALU
@@ -145,6 +156,7 @@ pub fn run_instruction(device: &mut Device) -> u32 {
         IncrementOperand8(operand) => increment_operand_8(device, operand),
         DecrementOperand8(operand) => decrement_operand_8(device, operand),
         SubtractOperandFromA(operand) => subtract_operand_8_from_a(device, operand),
+        SubtractOperandFromABorrow(operand) => subtract_operand_and_borrow_from_a(device, operand),
         AddOperandToA(operand) => add_operand_8_to_a(device, operand),
         AddOperandToACarry(operand) => add_operand_and_carry_to_a(device, operand),
         CompareOperandWithA(operand) => {
@@ -177,6 +189,7 @@ pub fn run_instruction(device: &mut Device) -> u32 {
         IncrementOperand16(operand) => increment_operand_16(device, operand),
         DecrementOperand16(operand) => decrement_operand_16(device, operand),
         AddOperandToHL(operand) => add_operand_to_hl(device, operand),
+        AddSignedImmediateToSP(offset) => add_signed_immediate_to_sp(device, offset),
 
         //
         // Bitwise
@@ -194,6 +207,7 @@ pub fn run_instruction(device: &mut Device) -> u32 {
         RotateRightCarryA => rotate_right_carry_a(device),
         ShiftLeftArithmetic(operand) => shift_left(device, operand),
         ShiftLeftLogical(operand) => shift_left_logical(device, operand),
+        ShiftRightArithmetic(operand) => shift_right(device, operand),
         ShiftRightLogical(operand) => shift_right_logical(device, operand),
         ComplementA => complement_a(device),
         TestBit(n, operand) => test_bit(device, operand, n),
@@ -234,7 +248,13 @@ pub fn run_instruction(device: &mut Device) -> u32 {
             device.halt();
             4
         }
-
-        _ => panic!("Unimplemented instruction: {:?}", instruction)
+        Stop => {
+            device.stop();
+            4
+        }
+        Unknown(_) => {
+            device.lock_up();
+            4
+        }
     }
 }
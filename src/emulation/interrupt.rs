@@ -1,4 +1,7 @@
+use std::io;
+
 use crate::emulation::constants::*;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Interrupt {
@@ -106,6 +109,10 @@ impl InterruptRegisters {
         self.requested = InterruptRegisterFlags::from_bits(value).unwrap();
     }
 
+    pub fn has_pending_interrupt(&self) -> bool {
+        self.enabled.intersects(self.requested)
+    }
+
     pub fn handle_next_interrupt(&mut self) -> Option<Interrupt> {
         let enabled_requested = self.enabled & self.requested;
 
@@ -119,3 +126,16 @@ impl InterruptRegisters {
         None
     }
 }
+
+impl SaveState for InterruptRegisters {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.enabled.bits());
+        writer.write_u8(self.requested.bits());
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.enabled = InterruptRegisterFlags::from_bits_truncate(reader.read_u8()?);
+        self.requested = InterruptRegisterFlags::from_bits_truncate(reader.read_u8()?);
+        Ok(())
+    }
+}
@@ -1,19 +1,36 @@
+use std::io;
+
 use crate::emulation::cartridge::{CartridgeMemory, CartridgeType};
 use crate::emulation::constants::*;
+use crate::emulation::rtc::{RealTimeClock, RtcRegister};
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
 
-pub trait Mapper {
+pub trait Mapper: SaveState {
     //const TYPE: MapperType;
 
     fn read_8(&self, cart: &CartridgeMemory, address: u16) -> u8;
     fn write_8(&mut self, cart: &mut CartridgeMemory, address: u16, value: u8);
+
+    fn is_rumbling(&self) -> bool {
+        false
+    }
+
+    fn get_rtc(&self) -> Option<&RealTimeClock> {
+        None
+    }
+
+    fn get_rtc_mut(&mut self) -> Option<&mut RealTimeClock> {
+        None
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MapperType {
     RomOnly,
     MBC1,
     MBC2,
-    MBC3
+    MBC3,
+    MBC5
 }
 
 pub enum RomOnlyLocation {
@@ -69,15 +86,22 @@ impl Mapper for RomOnly {
     }
 }
 
+impl SaveState for RomOnly {
+    fn write_state(&self, _writer: &mut StateWriter) {}
+
+    fn read_state(&mut self, _reader: &mut StateReader) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 impl dyn Mapper {
     pub fn from_cartridge_type(cartridge_type: CartridgeType) -> Box<dyn Mapper> {
         match cartridge_type.mapper {
             MapperType::RomOnly => RomOnly::new(),
             MapperType::MBC1 => MBC1::new(cartridge_type),
-            _ => panic!(
-                "Tried to create create an unsupported mapper: {:?}",
-                cartridge_type.mapper
-            )
+            MapperType::MBC2 => MBC2::new(),
+            MapperType::MBC3 => MBC3::new(cartridge_type),
+            MapperType::MBC5 => MBC5::new(cartridge_type)
         }
     }
 }
@@ -96,6 +120,23 @@ pub struct MBC1 {
     ram_bank_or_upper_rom_bits: u8
 }
 
+impl SaveState for MBC1 {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.ram_enabled);
+        writer.write_bool(self.rom_banking_mode);
+        writer.write_u8(self.rom_bank);
+        writer.write_u8(self.ram_bank_or_upper_rom_bits);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.ram_enabled = reader.read_bool()?;
+        self.rom_banking_mode = reader.read_bool()?;
+        self.rom_bank = reader.read_u8()?;
+        self.ram_bank_or_upper_rom_bits = reader.read_u8()?;
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 enum MBC1Location {
     RomBank0(u32),
@@ -215,3 +256,331 @@ impl Mapper for MBC1 {
         }*/
     }
 }
+
+#[derive(Debug)]
+pub struct MBC2 {
+    ram_enabled: bool,
+    rom_bank: u8
+}
+
+impl SaveState for MBC2 {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.ram_enabled);
+        writer.write_u8(self.rom_bank);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.ram_enabled = reader.read_bool()?;
+        self.rom_bank = reader.read_u8()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum MBC2Location {
+    RomBank0(u32),
+    RomBankN(u32),
+    Ram(u16)
+}
+
+impl MBC2 {
+    pub fn new() -> Box<MBC2> {
+        Box::new(MBC2 {
+            ram_enabled: false,
+            rom_bank: 1
+        })
+    }
+
+    fn resolve_address(&self, address: u16) -> MBC2Location {
+        use crate::emulation::mappers::MBC2Location::*;
+
+        match address {
+            ROM_BANK_0_START..=ROM_BANK_0_END => RomBank0((address - ROM_BANK_0_START) as u32),
+            ROM_BANK_N_START..=ROM_BANK_N_END => RomBankN(
+                self.rom_bank as u32 * ROM_BANK_SIZE as u32 + (address - ROM_BANK_N_START) as u32
+            ),
+            // Only the bottom 9 address bits are decoded, so the RAM echoes across the area.
+            CARTRIDGE_RAM_START..=CARTRIDGE_RAM_END => Ram((address - CARTRIDGE_RAM_START) & 0x1FF),
+            _ => panic!("Tried to resolve an invalid address: ${:04x}", address)
+        }
+    }
+}
+
+impl Mapper for MBC2 {
+    fn read_8(&self, memory: &CartridgeMemory, address: u16) -> u8 {
+        use crate::emulation::mappers::MBC2Location::*;
+
+        match self.resolve_address(address) {
+            RomBank0(offs) => memory.rom[offs as usize],
+            RomBankN(offs) => memory.rom[offs as usize % memory.rom.len()],
+            Ram(offs) if self.ram_enabled => 0xF0 | memory.ram[offs as usize],
+            Ram(_) => 0xFF
+        }
+    }
+
+    fn write_8(&mut self, memory: &mut CartridgeMemory, address: u16, value: u8) {
+        use crate::emulation::mappers::MBC2Location::*;
+
+        match address {
+            // Address bit 8 selects between the RAM enable and ROM bank registers.
+            0x0000..=0x3FFF if address & 0x0100 == 0 => {
+                self.ram_enabled = value & 0x0F == 0x0A;
+            }
+            0x0000..=0x3FFF => {
+                let bank = value & 0x0F;
+                self.rom_bank = if bank == 0 { 1 } else { bank };
+            }
+            0x4000..=0x7FFF => (),
+            CARTRIDGE_RAM_START..=CARTRIDGE_RAM_END => {
+                if let Ram(offs) = self.resolve_address(address) {
+                    if self.ram_enabled {
+                        memory.ram[offs as usize] = value & 0x0F;
+                    }
+                }
+            }
+            _ => panic!("unhandled location: {:?}", address)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MBC3 {
+    cartridge_type: CartridgeType,
+    ram_and_timer_enabled: bool,
+    rom_bank: u8,
+    ram_bank_or_rtc_register: u8,
+    pub rtc: RealTimeClock
+}
+
+impl SaveState for MBC3 {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.ram_and_timer_enabled);
+        writer.write_u8(self.rom_bank);
+        writer.write_u8(self.ram_bank_or_rtc_register);
+        self.rtc.write_state(writer);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.ram_and_timer_enabled = reader.read_bool()?;
+        self.rom_bank = reader.read_u8()?;
+        self.ram_bank_or_rtc_register = reader.read_u8()?;
+        self.rtc.read_state(reader)
+    }
+}
+
+#[derive(Debug)]
+enum MBC3Location {
+    RomBank0(u32),
+    RomBankN(u32),
+    Ram(u32),
+    Rtc(RtcRegister),
+    Unmapped
+}
+
+impl MBC3 {
+    pub fn new(cartridge_type: CartridgeType) -> Box<MBC3> {
+        Box::new(MBC3 {
+            cartridge_type,
+            ram_and_timer_enabled: false,
+            rom_bank: 1,
+            ram_bank_or_rtc_register: 0,
+            rtc: RealTimeClock::new()
+        })
+    }
+
+    fn resolve_address(&self, address: u16) -> MBC3Location {
+        use crate::emulation::mappers::MBC3Location::*;
+
+        match address {
+            ROM_BANK_0_START..=ROM_BANK_0_END => RomBank0((address - ROM_BANK_0_START) as u32),
+            ROM_BANK_N_START..=ROM_BANK_N_END => RomBankN(
+                self.rom_bank as u32 * ROM_BANK_SIZE as u32 + (address - ROM_BANK_N_START) as u32
+            ),
+            CARTRIDGE_RAM_START..=CARTRIDGE_RAM_END => match self.ram_bank_or_rtc_register {
+                bank @ 0x00..=0x03 => Ram(bank as u32 * CARTRIDGE_RAM_BANK_SIZE as u32
+                    + (address - CARTRIDGE_RAM_START) as u32),
+                register => match RtcRegister::from_select(register) {
+                    Some(register) if self.cartridge_type.has_timer => Rtc(register),
+                    _ => Unmapped
+                }
+            },
+            _ => panic!("Tried to resolve an invalid address: ${:04x}", address)
+        }
+    }
+}
+
+impl Mapper for MBC3 {
+    fn read_8(&self, memory: &CartridgeMemory, address: u16) -> u8 {
+        use crate::emulation::mappers::MBC3Location::*;
+
+        match self.resolve_address(address) {
+            RomBank0(offs) => memory.rom[offs as usize],
+            RomBankN(offs) => memory.rom[offs as usize % memory.rom.len()],
+            _ if !self.ram_and_timer_enabled => 0xFF,
+            Ram(offs) if (offs as usize) < memory.ram.len() => memory.ram[offs as usize],
+            Rtc(register) => self.rtc.read_8(register),
+            Ram(_) | Unmapped => 0xFF
+        }
+    }
+
+    fn write_8(&mut self, memory: &mut CartridgeMemory, address: u16, value: u8) {
+        use crate::emulation::mappers::MBC3Location::*;
+
+        match address {
+            0x0000..=0x1FFF => {
+                self.ram_and_timer_enabled = value & 0x0F == 0x0A;
+            }
+            0x2000..=0x3FFF => {
+                let bank = value & 0b0111_1111;
+                self.rom_bank = if bank == 0 { 1 } else { bank };
+            }
+            0x4000..=0x5FFF => {
+                self.ram_bank_or_rtc_register = value;
+            }
+            0x6000..=0x7FFF => {
+                if self.cartridge_type.has_timer {
+                    self.rtc.write_latch(value);
+                }
+            }
+            CARTRIDGE_RAM_START..=CARTRIDGE_RAM_END if self.ram_and_timer_enabled => {
+                match self.resolve_address(address) {
+                    Ram(offs) if (offs as usize) < memory.ram.len() => {
+                        memory.ram[offs as usize] = value
+                    }
+                    Rtc(register) => self.rtc.write_8(register, value),
+                    _ => ()
+                }
+            }
+            CARTRIDGE_RAM_START..=CARTRIDGE_RAM_END => (),
+            _ => panic!("unhandled location: {:?}", address)
+        }
+    }
+
+    fn get_rtc(&self) -> Option<&RealTimeClock> {
+        if self.cartridge_type.has_timer {
+            Some(&self.rtc)
+        } else {
+            None
+        }
+    }
+
+    fn get_rtc_mut(&mut self) -> Option<&mut RealTimeClock> {
+        if self.cartridge_type.has_timer {
+            Some(&mut self.rtc)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MBC5 {
+    cartridge_type: CartridgeType,
+    ram_enabled: bool,
+    rom_bank: u16,
+    ram_bank: u8,
+    rumble_motor: bool
+}
+
+impl SaveState for MBC5 {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.ram_enabled);
+        writer.write_u16(self.rom_bank);
+        writer.write_u8(self.ram_bank);
+        writer.write_bool(self.rumble_motor);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.ram_enabled = reader.read_bool()?;
+        self.rom_bank = reader.read_u16()?;
+        self.ram_bank = reader.read_u8()?;
+        self.rumble_motor = reader.read_bool()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum MBC5Location {
+    RomBank0(u32),
+    RomBankN(u32),
+    Ram(u32)
+}
+
+impl MBC5 {
+    pub fn new(cartridge_type: CartridgeType) -> Box<MBC5> {
+        Box::new(MBC5 {
+            cartridge_type,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rumble_motor: false
+        })
+    }
+
+    fn resolve_address(&self, address: u16) -> MBC5Location {
+        use crate::emulation::mappers::MBC5Location::*;
+
+        match address {
+            ROM_BANK_0_START..=ROM_BANK_0_END => RomBank0((address - ROM_BANK_0_START) as u32),
+            ROM_BANK_N_START..=ROM_BANK_N_END => RomBankN(
+                self.rom_bank as u32 * ROM_BANK_SIZE as u32 + (address - ROM_BANK_N_START) as u32
+            ),
+            CARTRIDGE_RAM_START..=CARTRIDGE_RAM_END => Ram(self.ram_bank as u32
+                * CARTRIDGE_RAM_BANK_SIZE as u32
+                + (address - CARTRIDGE_RAM_START) as u32),
+            _ => panic!("Tried to resolve an invalid address: ${:04x}", address)
+        }
+    }
+}
+
+impl Mapper for MBC5 {
+    fn read_8(&self, memory: &CartridgeMemory, address: u16) -> u8 {
+        use crate::emulation::mappers::MBC5Location::*;
+
+        match self.resolve_address(address) {
+            RomBank0(offs) => memory.rom[offs as usize],
+            RomBankN(offs) => memory.rom[offs as usize % memory.rom.len()],
+            Ram(offs) if self.ram_enabled && (offs as usize) < memory.ram.len() => {
+                memory.ram[offs as usize]
+            }
+            Ram(_) => 0xFF
+        }
+    }
+
+    fn write_8(&mut self, memory: &mut CartridgeMemory, address: u16, value: u8) {
+        use crate::emulation::mappers::MBC5Location::*;
+
+        match address {
+            0x0000..=0x1FFF => {
+                self.ram_enabled = value == 0x0A;
+            }
+            0x2000..=0x2FFF => {
+                self.rom_bank = (self.rom_bank & 0x100) | value as u16;
+            }
+            0x3000..=0x3FFF => {
+                self.rom_bank = (self.rom_bank & 0xFF) | ((value as u16 & 1) << 8);
+            }
+            0x4000..=0x5FFF => {
+                // Rumble carts wire the motor to bit 3, leaving only 8 selectable RAM banks.
+                if self.cartridge_type.has_rumble {
+                    self.rumble_motor = value & 0b1000 != 0;
+                    self.ram_bank = value & 0b0111;
+                } else {
+                    self.ram_bank = value & 0b1111;
+                }
+            }
+            0x6000..=0x7FFF => (),
+            CARTRIDGE_RAM_START..=CARTRIDGE_RAM_END => match self.resolve_address(address) {
+                Ram(offs) if self.ram_enabled && (offs as usize) < memory.ram.len() => {
+                    memory.ram[offs as usize] = value
+                }
+                _ => ()
+            },
+            _ => panic!("unhandled location: {:?}", address)
+        }
+    }
+
+    fn is_rumbling(&self) -> bool {
+        self.rumble_motor
+    }
+}
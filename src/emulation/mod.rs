@@ -12,9 +12,14 @@ pub mod internal_message;
 pub mod interpreter;
 pub mod interrupt;
 pub mod mappers;
+pub mod oam_dma;
 pub mod registers;
+pub mod rewind;
+pub mod rtc;
+pub mod save_state;
 pub mod serial;
 pub mod timers;
+pub mod vram_dma;
 
 pub mod audio;
 pub mod video;
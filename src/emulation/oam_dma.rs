@@ -0,0 +1,151 @@
+use std::io;
+
+use crate::emulation::constants::*;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+
+pub const OAM_DMA_LENGTH: u8 = 160;
+
+// The whole page is unusable during a transfer, not just OAM itself.
+const OAM_DMA_END: u16 = 0xFEFF;
+
+// The buses the CPU shares with OAM DMA. Everything from 0xFF00 up is on neither, which is
+// why code waiting for DMA to finish has to run from HRAM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MemoryBus {
+    External,
+    Video
+}
+
+impl MemoryBus {
+    fn from_address(address: u16) -> Option<MemoryBus> {
+        match address {
+            VRAM_START..=VRAM_END => Some(MemoryBus::Video),
+            ROM_BANK_0_START..=ROM_BANK_N_END | CARTRIDGE_RAM_START..=ECHO_RAM_END => {
+                Some(MemoryBus::External)
+            }
+            _ => None
+        }
+    }
+}
+
+// Copies 160 bytes into OAM, one per machine cycle. A transfer starts two cycles after FF46 is
+// written, the first of which sets it up, so OAM is still accessible then unless an earlier
+// transfer is still running. Writing FF46 again restarts the transfer the same way.
+#[derive(Debug)]
+pub struct OamDma {
+    requested_source: Option<u8>,
+    starting_source: Option<u8>,
+    active_source: Option<u16>,
+    index: u8,
+    // The byte copied last, which the CPU reads instead when it conflicts with the transfer.
+    current_value: u8
+}
+
+impl OamDma {
+    pub fn new() -> OamDma {
+        OamDma {
+            requested_source: None,
+            starting_source: None,
+            active_source: None,
+            index: 0,
+            current_value: 0xFF
+        }
+    }
+
+    pub fn start(&mut self, source_high: u8) {
+        self.requested_source = Some(source_high);
+    }
+
+    // Advances the transfer by a machine cycle, and returns the address of the byte to copy in
+    // it along with its offset in OAM.
+    pub fn step(&mut self) -> Option<(u16, u8)> {
+        if self.index == OAM_DMA_LENGTH {
+            self.active_source = None;
+        }
+
+        if let Some(source_high) = self.starting_source.take() {
+            let source = (source_high as u16) << 8;
+
+            // Sources from 0xE000 up read from work RAM, like echo RAM does.
+            self.active_source = if source >= ECHO_RAM_START {
+                Some(source - ECHO_RAM_OFFSET)
+            } else {
+                Some(source)
+            };
+            self.index = 0;
+        }
+
+        self.starting_source = self.requested_source.take();
+
+        let address = self.active_source? + self.index as u16;
+        let index = self.index;
+        self.index += 1;
+
+        Some((address, index))
+    }
+
+    pub fn set_current_value(&mut self, value: u8) {
+        self.current_value = value;
+    }
+
+    // Returns what the CPU sees instead of the memory at `address` while a transfer is
+    // running, if anything. OAM reads as 0xFF, and the bus the transfer reads from only
+    // returns the byte being copied.
+    pub fn get_conflicting_value(&self, address: u16) -> Option<u8> {
+        let source = self.active_source?;
+
+        if (OAM_START..=OAM_DMA_END).contains(&address) {
+            return Some(0xFF);
+        }
+
+        let bus = MemoryBus::from_address(address)?;
+        if Some(bus) == MemoryBus::from_address(source) {
+            Some(self.current_value)
+        } else {
+            None
+        }
+    }
+}
+
+impl SaveState for OamDma {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.requested_source.is_some());
+        writer.write_u8(self.requested_source.unwrap_or(0));
+        writer.write_bool(self.starting_source.is_some());
+        writer.write_u8(self.starting_source.unwrap_or(0));
+        writer.write_bool(self.active_source.is_some());
+        writer.write_u16(self.active_source.unwrap_or(0));
+        writer.write_u8(self.index);
+        writer.write_u8(self.current_value);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        let has_requested_source = reader.read_bool()?;
+        let requested_source = reader.read_u8()?;
+        self.requested_source = if has_requested_source {
+            Some(requested_source)
+        } else {
+            None
+        };
+
+        let has_starting_source = reader.read_bool()?;
+        let starting_source = reader.read_u8()?;
+        self.starting_source = if has_starting_source {
+            Some(starting_source)
+        } else {
+            None
+        };
+
+        let has_active_source = reader.read_bool()?;
+        let active_source = reader.read_u16()?;
+        self.active_source = if has_active_source {
+            Some(active_source)
+        } else {
+            None
+        };
+
+        self.index = reader.read_u8()?;
+        self.current_value = reader.read_u8()?;
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
+use std::io;
+
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Registers {
     pub a: u8,
@@ -101,3 +105,33 @@ impl Registers {
         set_16!(self, h, l, value);
     }
 }
+
+impl SaveState for Registers {
+    fn write_state(&self, writer: &mut StateWriter) {
+        for &value in &[
+            self.a, self.f, self.b, self.c, self.d, self.e, self.h, self.l
+        ] {
+            writer.write_u8(value);
+        }
+        writer.write_u16(self.sp);
+        writer.write_u16(self.pc);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        for register in &mut [
+            &mut self.a,
+            &mut self.f,
+            &mut self.b,
+            &mut self.c,
+            &mut self.d,
+            &mut self.e,
+            &mut self.h,
+            &mut self.l
+        ] {
+            **register = reader.read_u8()?;
+        }
+        self.sp = reader.read_u16()?;
+        self.pc = reader.read_u16()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use crate::emulation::device::Device;
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+
+    loop {
+        let byte = bytes[*position];
+        *position += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            return value;
+        }
+    }
+}
+
+// Consecutive frames differ in only a handful of bytes, so a snapshot is stored as the XOR
+// against its neighbour with the runs of zeroes squeezed out. The encoding is the target
+// length followed by (zero run length, literal length, literal bytes) triples.
+fn encode_delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    write_varint(&mut delta, to.len());
+
+    let xor_at = |i: usize| to[i] ^ from.get(i).cloned().unwrap_or(0);
+    let mut i = 0;
+
+    while i < to.len() {
+        let zeroes_start = i;
+        while i < to.len() && xor_at(i) == 0 {
+            i += 1;
+        }
+
+        let literal_start = i;
+        while i < to.len() && xor_at(i) != 0 {
+            i += 1;
+        }
+
+        write_varint(&mut delta, literal_start - zeroes_start);
+        write_varint(&mut delta, i - literal_start);
+        delta.extend((literal_start..i).map(xor_at));
+    }
+
+    delta
+}
+
+fn apply_delta(from: &[u8], delta: &[u8]) -> Vec<u8> {
+    let mut position = 0;
+    let length = read_varint(delta, &mut position);
+
+    let mut result = vec![0u8; length];
+    let common_length = length.min(from.len());
+    result[..common_length].copy_from_slice(&from[..common_length]);
+
+    let mut i = 0;
+    while position < delta.len() {
+        i += read_varint(delta, &mut position);
+        let literal_length = read_varint(delta, &mut position);
+
+        for byte in &delta[position..position + literal_length] {
+            result[i] ^= byte;
+            i += 1;
+        }
+
+        position += literal_length;
+    }
+
+    result
+}
+
+// Only the newest snapshot is kept in full. Every older snapshot is stored as a delta against
+// the one after it, so stepping back walks the chain from the newest end and the oldest
+// entries can be dropped without touching the rest.
+pub struct RewindBuffer {
+    capacity: usize,
+    interval: u32,
+    frames_since_snapshot: u32,
+    newest: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>
+}
+
+impl RewindBuffer {
+    pub fn new(capacity: usize, interval: u32) -> RewindBuffer {
+        assert!(
+            capacity > 0,
+            "Rewind buffer needs room for at least one snapshot"
+        );
+        assert!(interval > 0, "Snapshot interval must be at least one frame");
+
+        RewindBuffer {
+            capacity,
+            interval,
+            frames_since_snapshot: 0,
+            newest: None,
+            deltas: VecDeque::with_capacity(capacity)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.deltas.len() + self.newest.is_some() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.newest.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.deltas.clear();
+        self.frames_since_snapshot = 0;
+    }
+
+    // Should be called once per emulated frame; a snapshot is taken every `interval` frames.
+    pub fn push_frame(&mut self, device: &Device) {
+        self.frames_since_snapshot += 1;
+
+        if self.frames_since_snapshot >= self.interval {
+            self.frames_since_snapshot = 0;
+            self.push_state(device.save_state());
+        }
+    }
+
+    pub fn push_state(&mut self, state: Vec<u8>) {
+        if let Some(previous) = self.newest.take() {
+            self.deltas.push_back(encode_delta(&state, &previous));
+        }
+
+        self.newest = Some(state);
+
+        while self.len() > self.capacity {
+            self.deltas.pop_front();
+        }
+    }
+
+    pub fn pop_state(&mut self) -> Option<Vec<u8>> {
+        let newest = self.newest.take()?;
+        self.newest = self
+            .deltas
+            .pop_back()
+            .map(|delta| apply_delta(&newest, &delta));
+        Some(newest)
+    }
+
+    // Restores the most recent snapshot and removes it from the buffer, so calling this
+    // repeatedly keeps moving further back. Returns false once the buffer is exhausted.
+    pub fn step_back(&mut self, device: &mut Device) -> bool {
+        self.frames_since_snapshot = 0;
+
+        match self.pop_state() {
+            Some(state) => {
+                device
+                    .load_state(&state)
+                    .expect("Rewind snapshots should always be loadable");
+                true
+            }
+            None => false
+        }
+    }
+}
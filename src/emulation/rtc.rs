@@ -0,0 +1,319 @@
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::emulation::bitutils::BitExtensions;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+
+const SECONDS_PER_MINUTE: u64 = 60;
+const SECONDS_PER_HOUR: u64 = 60 * SECONDS_PER_MINUTE;
+const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
+const DAY_COUNTER_LIMIT: u64 = 512;
+
+// VBA and BGB append the clock to the RAM dump as ten 32-bit words (live and latched registers)
+// followed by a UNIX timestamp, which older versions stored as 32 bits instead of 64.
+pub const RTC_SAVE_SIZE: usize = 48;
+const RTC_SAVE_SIZE_32_BIT_TIMESTAMP: usize = 44;
+const RTC_SAVE_REGISTERS_SIZE: usize = 40;
+
+const DAY_HIGH_BIT: u8 = 0;
+const HALT_BIT: u8 = 6;
+const DAY_CARRY_BIT: u8 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtcRegister {
+    Seconds,
+    Minutes,
+    Hours,
+    DayLow,
+    DayHigh
+}
+
+impl RtcRegister {
+    pub fn from_select(value: u8) -> Option<RtcRegister> {
+        match value {
+            0x08 => Some(RtcRegister::Seconds),
+            0x09 => Some(RtcRegister::Minutes),
+            0x0A => Some(RtcRegister::Hours),
+            0x0B => Some(RtcRegister::DayLow),
+            0x0C => Some(RtcRegister::DayHigh),
+            _ => None
+        }
+    }
+
+    fn get_mask(self) -> u8 {
+        match self {
+            RtcRegister::Seconds | RtcRegister::Minutes => 0b0011_1111,
+            RtcRegister::Hours => 0b0001_1111,
+            RtcRegister::DayLow => 0b1111_1111,
+            RtcRegister::DayHigh => 0b1100_0001
+        }
+    }
+}
+
+const ALL_RTC_REGISTERS: [RtcRegister; 5] = [
+    RtcRegister::Seconds,
+    RtcRegister::Minutes,
+    RtcRegister::Hours,
+    RtcRegister::DayLow,
+    RtcRegister::DayHigh
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RtcRegisters {
+    pub seconds: u8,
+    pub minutes: u8,
+    pub hours: u8,
+    pub day_low: u8,
+    pub day_high: u8
+}
+
+impl RtcRegisters {
+    pub fn read_8(&self, register: RtcRegister) -> u8 {
+        match register {
+            RtcRegister::Seconds => self.seconds,
+            RtcRegister::Minutes => self.minutes,
+            RtcRegister::Hours => self.hours,
+            RtcRegister::DayLow => self.day_low,
+            RtcRegister::DayHigh => self.day_high
+        }
+    }
+
+    pub fn write_8(&mut self, register: RtcRegister, value: u8) {
+        let value = value & register.get_mask();
+        match register {
+            RtcRegister::Seconds => self.seconds = value,
+            RtcRegister::Minutes => self.minutes = value,
+            RtcRegister::Hours => self.hours = value,
+            RtcRegister::DayLow => self.day_low = value,
+            RtcRegister::DayHigh => self.day_high = value
+        }
+    }
+
+    fn write_save_data(&self, buffer: &mut Vec<u8>) {
+        for &register in &ALL_RTC_REGISTERS {
+            buffer.extend_from_slice(&(self.read_8(register) as u32).to_le_bytes());
+        }
+    }
+
+    fn read_save_data(&mut self, bytes: &[u8]) {
+        for (&register, word) in ALL_RTC_REGISTERS.iter().zip(bytes.chunks(4)) {
+            self.write_8(register, word[0]);
+        }
+    }
+
+    pub fn get_days(&self) -> u16 {
+        (self.day_high.get_bit(DAY_HIGH_BIT) as u16) << 8 | self.day_low as u16
+    }
+
+    fn set_days(&mut self, days: u16) {
+        self.day_low = (days & 0xFF) as u8;
+        self.day_high = self.day_high.set_bit_to(DAY_HIGH_BIT, days & 0x100 != 0);
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.day_high.get_bit(HALT_BIT)
+    }
+
+    pub fn has_day_carry(&self) -> bool {
+        self.day_high.get_bit(DAY_CARRY_BIT)
+    }
+
+    fn is_in_range(&self) -> bool {
+        self.seconds < 60 && self.minutes < 60 && self.hours < 24
+    }
+
+    // The counters are plain 6/5-bit registers, so a value written out of range counts up to
+    // the register's maximum and wraps to zero without carrying into the next unit.
+    fn tick(&mut self) {
+        let seconds = (self.seconds + 1) & RtcRegister::Seconds.get_mask();
+        self.seconds = if seconds == 60 { 0 } else { seconds };
+        if seconds != 60 {
+            return;
+        }
+
+        let minutes = (self.minutes + 1) & RtcRegister::Minutes.get_mask();
+        self.minutes = if minutes == 60 { 0 } else { minutes };
+        if minutes != 60 {
+            return;
+        }
+
+        let hours = (self.hours + 1) & RtcRegister::Hours.get_mask();
+        self.hours = if hours == 24 { 0 } else { hours };
+        if hours != 24 {
+            return;
+        }
+
+        self.advance_days(1);
+    }
+
+    fn advance_days(&mut self, days: u64) {
+        let days = self.get_days() as u64 + days;
+        if days >= DAY_COUNTER_LIMIT {
+            self.day_high = self.day_high.set_bit(DAY_CARRY_BIT);
+        }
+        self.set_days((days % DAY_COUNTER_LIMIT) as u16);
+    }
+
+    pub fn advance(&mut self, mut seconds: u64) {
+        while seconds > 0 && !self.is_in_range() {
+            self.tick();
+            seconds -= 1;
+        }
+
+        if seconds == 0 {
+            return;
+        }
+
+        let time_of_day = self.hours as u64 * SECONDS_PER_HOUR
+            + self.minutes as u64 * SECONDS_PER_MINUTE
+            + self.seconds as u64
+            + seconds;
+
+        self.hours = ((time_of_day % SECONDS_PER_DAY) / SECONDS_PER_HOUR) as u8;
+        self.minutes = ((time_of_day % SECONDS_PER_HOUR) / SECONDS_PER_MINUTE) as u8;
+        self.seconds = (time_of_day % SECONDS_PER_MINUTE) as u8;
+        self.advance_days(time_of_day / SECONDS_PER_DAY);
+    }
+}
+
+// The MBC3 clock keeps running while the emulator is closed, so it is driven by wall-clock
+// time rather than by emulated cycles.
+#[derive(Debug, Clone)]
+pub struct RealTimeClock {
+    pub live: RtcRegisters,
+    pub latched: RtcRegisters,
+    last_update: SystemTime,
+    latch_armed: bool
+}
+
+impl RealTimeClock {
+    pub fn new() -> RealTimeClock {
+        RealTimeClock {
+            live: RtcRegisters::default(),
+            latched: RtcRegisters::default(),
+            last_update: SystemTime::now(),
+            latch_armed: false
+        }
+    }
+
+    pub fn update(&mut self) {
+        let now = SystemTime::now();
+        let elapsed = now.duration_since(self.last_update).unwrap_or_default();
+
+        if self.live.is_halted() {
+            self.last_update = now;
+            return;
+        }
+
+        let elapsed_seconds = elapsed.as_secs();
+        self.live.advance(elapsed_seconds);
+        self.last_update += Duration::from_secs(elapsed_seconds);
+    }
+
+    // Latching happens on a 0x00 -> 0x01 write sequence.
+    pub fn write_latch(&mut self, value: u8) {
+        if self.latch_armed && value == 0x01 {
+            self.update();
+            self.latched = self.live;
+        }
+
+        self.latch_armed = value == 0x00;
+    }
+
+    pub fn read_8(&self, register: RtcRegister) -> u8 {
+        self.latched.read_8(register)
+    }
+
+    pub fn write_save_data(&self, buffer: &mut Vec<u8>) {
+        let timestamp = self
+            .last_update
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        self.live.write_save_data(buffer);
+        self.latched.write_save_data(buffer);
+        buffer.extend_from_slice(&timestamp.to_le_bytes());
+    }
+
+    pub fn read_save_data(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let timestamp = match bytes.len() {
+            RTC_SAVE_SIZE => {
+                let mut timestamp_bytes = [0u8; 8];
+                timestamp_bytes.copy_from_slice(&bytes[RTC_SAVE_REGISTERS_SIZE..]);
+                u64::from_le_bytes(timestamp_bytes)
+            }
+            RTC_SAVE_SIZE_32_BIT_TIMESTAMP => {
+                let mut timestamp_bytes = [0u8; 4];
+                timestamp_bytes.copy_from_slice(&bytes[RTC_SAVE_REGISTERS_SIZE..]);
+                u32::from_le_bytes(timestamp_bytes) as u64
+            }
+            invalid => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid RTC save data size: {}", invalid)
+                ))
+            }
+        };
+
+        let (live, latched) =
+            bytes[..RTC_SAVE_REGISTERS_SIZE].split_at(RTC_SAVE_REGISTERS_SIZE / 2);
+        self.live.read_save_data(live);
+        self.latched.read_save_data(latched);
+        self.last_update = UNIX_EPOCH + Duration::from_secs(timestamp);
+        self.update();
+
+        Ok(())
+    }
+
+    pub fn write_8(&mut self, register: RtcRegister, value: u8) {
+        self.update();
+        self.live.write_8(register, value);
+        self.latched.write_8(register, value);
+
+        // Writing the seconds register resets the sub-second divider.
+        if register == RtcRegister::Seconds {
+            self.last_update = SystemTime::now();
+        }
+    }
+}
+
+impl SaveState for RtcRegisters {
+    fn write_state(&self, writer: &mut StateWriter) {
+        for &register in &ALL_RTC_REGISTERS {
+            writer.write_u8(self.read_8(register));
+        }
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        for &register in &ALL_RTC_REGISTERS {
+            self.write_8(register, reader.read_u8()?);
+        }
+        Ok(())
+    }
+}
+
+impl SaveState for RealTimeClock {
+    fn write_state(&self, writer: &mut StateWriter) {
+        let since_epoch = self
+            .last_update
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        self.live.write_state(writer);
+        self.latched.write_state(writer);
+        writer.write_u64(since_epoch.as_secs());
+        writer.write_u32(since_epoch.subsec_nanos());
+        writer.write_bool(self.latch_armed);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.live.read_state(reader)?;
+        self.latched.read_state(reader)?;
+        let seconds = reader.read_u64()?;
+        let nanos = reader.read_u32()?;
+        self.last_update = UNIX_EPOCH + Duration::new(seconds, nanos);
+        self.latch_armed = reader.read_bool()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use std::io;
+
+const SAVE_STATE_MAGIC: &[u8; 8] = b"RGBSTATE";
+pub const SAVE_STATE_VERSION: u32 = 12;
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub trait SaveState {
+    fn write_state(&self, writer: &mut StateWriter);
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()>;
+}
+
+// All values are stored little endian, in the order the components write them. Any change to
+// that order or to the set of saved fields must bump SAVE_STATE_VERSION.
+pub struct StateWriter {
+    buffer: Vec<u8>
+}
+
+impl StateWriter {
+    pub fn new() -> StateWriter {
+        let mut writer = StateWriter { buffer: Vec::new() };
+        writer.buffer.extend_from_slice(SAVE_STATE_MAGIC);
+        writer.write_u32(SAVE_STATE_VERSION);
+        writer
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+pub struct StateReader<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> io::Result<StateReader<'a>> {
+        let mut reader = StateReader { bytes, position: 0 };
+
+        if reader.take(SAVE_STATE_MAGIC.len())? != SAVE_STATE_MAGIC {
+            return Err(invalid_data("Not a save state".to_string()));
+        }
+
+        let version = reader.read_u32()?;
+        if version != SAVE_STATE_VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported save state version {} (expected {})",
+                version, SAVE_STATE_VERSION
+            )));
+        }
+
+        Ok(reader)
+    }
+
+    fn take(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        let end = self.position + length;
+
+        if end > self.bytes.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Save state is truncated"
+            ));
+        }
+
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    pub fn read_u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn read_bool(&mut self) -> io::Result<bool> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    pub fn read_u16(&mut self) -> io::Result<u16> {
+        let mut bytes = [0u8; 2];
+        bytes.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    pub fn read_u32(&mut self) -> io::Result<u32> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    pub fn read_u64(&mut self) -> io::Result<u64> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    // Memory regions have a fixed size for a given device and cartridge, so a length mismatch
+    // means the state was made with a different configuration.
+    pub fn read_bytes_into(&mut self, buffer: &mut [u8]) -> io::Result<()> {
+        let length = self.read_u32()? as usize;
+
+        if length != buffer.len() {
+            return Err(invalid_data(format!(
+                "Expected a {} byte memory region, found {} bytes",
+                buffer.len(),
+                length
+            )));
+        }
+
+        buffer.copy_from_slice(self.take(length)?);
+        Ok(())
+    }
+
+    pub fn read_enum<T>(&mut self, decode: impl Fn(u8) -> Option<T>) -> io::Result<T> {
+        let value = self.read_u8()?;
+        decode(value).ok_or_else(|| invalid_data(format!("Invalid enum value: {}", value)))
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+}
@@ -1,5 +1,8 @@
+use std::io;
+
 use crate::emulation::internal_message::InternalMessage;
 use crate::emulation::interrupt::Interrupt;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
 
 #[derive(Debug, Clone, Copy)]
 pub enum TimerRegister {
@@ -9,14 +12,37 @@ pub enum TimerRegister {
     Control
 }
 
+// After TIMA overflows it reads as zero for one machine cycle before being reloaded from TMA,
+// and the reload cycle itself ignores writes to TIMA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReloadState {
+    Idle,
+    Overflowed,
+    Reloading
+}
+
+impl ReloadState {
+    fn decode(value: u8) -> Option<ReloadState> {
+        match value {
+            0 => Some(ReloadState::Idle),
+            1 => Some(ReloadState::Overflowed),
+            2 => Some(ReloadState::Reloading),
+            _ => None
+        }
+    }
+}
+
+// DIV is the upper byte of a 16-bit counter that is incremented every cycle. TIMA is clocked by
+// the falling edge of one of the counter's bits, gated by the enable bit in TAC, so anything
+// that makes that signal drop increments it: the counter ticking over, resetting DIV or
+// writing TAC.
 #[derive(Debug)]
 pub struct TimerRegisters {
-    divider_tick: u16,
-    counter_tick: u16,
+    system_counter: u16,
     modulo: u8,
     control: TimerControlRegister,
-    divider: u8,
-    counter: u8
+    counter: u8,
+    reload_state: ReloadState
 }
 
 bitfield! {
@@ -29,12 +55,12 @@ bitfield! {
 }
 
 impl TimerControlRegister {
-    pub fn get_counter_tick_target(self) -> u16 {
+    pub fn get_counter_bit(self) -> u16 {
         match self.get_input_clock() {
-            0b00 => 1024,
-            0b01 => 16,
-            0b10 => 64,
-            0b11 => 256,
+            0b00 => 9,
+            0b01 => 3,
+            0b10 => 5,
+            0b11 => 7,
             _ => panic!("This can't happen.")
         }
     }
@@ -43,46 +69,61 @@ impl TimerControlRegister {
 impl TimerRegisters {
     pub fn new() -> TimerRegisters {
         TimerRegisters {
-            divider_tick: 0,
-            counter_tick: 0,
+            system_counter: 0,
             modulo: 0,
             control: TimerControlRegister::default(),
-            divider: 0,
-            counter: 0
+            counter: 0,
+            reload_state: ReloadState::Idle
         }
     }
 
-    pub fn update(&mut self) -> InternalMessage {
-        if !self.control.get_timer_enabled() {
-            return InternalMessage::None;
-        }
-
-        self.divider_tick += 1;
+    fn get_timer_signal(&self) -> bool {
+        self.control.get_timer_enabled()
+            && self.system_counter & (1 << self.control.get_counter_bit()) != 0
+    }
 
-        if self.divider_tick >= 255 {
-            self.divider_tick = 0;
-            self.divider = self.divider.wrapping_add(1);
+    fn increment_counter(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = 0;
+            self.reload_state = ReloadState::Overflowed;
+        } else {
+            self.counter += 1;
         }
+    }
 
-        self.counter_tick += 1;
+    fn set_system_counter(&mut self, value: u16) {
+        let previous_signal = self.get_timer_signal();
+        self.system_counter = value;
 
-        if self.counter_tick >= self.control.get_counter_tick_target() - 1 {
-            self.counter_tick = 0;
+        if previous_signal && !self.get_timer_signal() {
+            self.increment_counter();
+        }
+    }
 
-            if self.counter == 255 {
+    // Advances the timer by one machine cycle.
+    pub fn update(&mut self) -> InternalMessage {
+        let message = match self.reload_state {
+            ReloadState::Overflowed => {
                 self.counter = self.modulo;
+                self.reload_state = ReloadState::Reloading;
                 InternalMessage::TriggerInterrupt(Interrupt::TimerOverflow)
-            } else {
-                self.counter += 1;
+            }
+            ReloadState::Reloading => {
+                self.reload_state = ReloadState::Idle;
                 InternalMessage::None
             }
-        } else {
-            InternalMessage::None
+            ReloadState::Idle => InternalMessage::None
+        };
+
+        for _ in 0..4 {
+            let counter = self.system_counter.wrapping_add(1);
+            self.set_system_counter(counter);
         }
+
+        message
     }
 
     pub fn resolve_address(&self, addr: u16) -> TimerRegister {
-        println!("Timer register: {:04x}", addr);
         match addr {
             0xFF04 => TimerRegister::Divider,
             0xFF05 => TimerRegister::Counter,
@@ -94,24 +135,60 @@ impl TimerRegisters {
 
     pub fn write_8(&mut self, register: TimerRegister, value: u8) {
         match register {
-            TimerRegister::Divider | TimerRegister::Counter => {
-                self.divider = 0;
-                self.counter = 0;
+            TimerRegister::Divider => self.set_system_counter(0),
+            TimerRegister::Counter => match self.reload_state {
+                // Writing TIMA before the reload cancels it, along with the interrupt.
+                ReloadState::Overflowed => {
+                    self.counter = value;
+                    self.reload_state = ReloadState::Idle;
+                }
+                ReloadState::Reloading => (),
+                ReloadState::Idle => self.counter = value
+            },
+            TimerRegister::Modulo => {
+                self.modulo = value;
+
+                // TIMA is loaded from TMA throughout the reload cycle.
+                if self.reload_state == ReloadState::Reloading {
+                    self.counter = value;
+                }
             }
-            TimerRegister::Modulo => self.modulo = value,
             TimerRegister::Control => {
-                self.control = TimerControlRegister(value);
-                println!("Timer control: {:b}", value);
+                let previous_signal = self.get_timer_signal();
+                self.control = TimerControlRegister(value & 0b111);
+
+                if previous_signal && !self.get_timer_signal() {
+                    self.increment_counter();
+                }
             }
         }
     }
 
     pub fn read_8(&self, register: TimerRegister) -> u8 {
         match register {
-            TimerRegister::Divider => self.divider,
+            TimerRegister::Divider => (self.system_counter >> 8) as u8,
             TimerRegister::Counter => self.counter,
             TimerRegister::Modulo => self.modulo,
-            TimerRegister::Control => self.control.0
+            TimerRegister::Control => self.control.0 | 0b1111_1000
         }
     }
 }
+
+impl SaveState for TimerRegisters {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_u16(self.system_counter);
+        writer.write_u8(self.modulo);
+        writer.write_u8(self.control.0);
+        writer.write_u8(self.counter);
+        writer.write_u8(self.reload_state as u8);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.system_counter = reader.read_u16()?;
+        self.modulo = reader.read_u8()?;
+        self.control = TimerControlRegister(reader.read_u8()?);
+        self.counter = reader.read_u8()?;
+        self.reload_state = reader.read_enum(ReloadState::decode)?;
+        Ok(())
+    }
+}
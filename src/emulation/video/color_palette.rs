@@ -0,0 +1,73 @@
+use std::io;
+
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+
+pub const COLOR_PALETTE_COUNT: usize = 8;
+
+const COLOR_PALETTE_RAM_SIZE: usize = COLOR_PALETTE_COUNT * 4 * 2;
+
+// The CGB's palette memory for either the background or objects: eight palettes of four
+// 15-bit colors, stored little endian. The CPU only reaches it through an index register,
+// which can advance by itself after every write to the data register.
+#[derive(Debug)]
+pub struct ColorPaletteRam {
+    data: [u8; COLOR_PALETTE_RAM_SIZE],
+    index: u8,
+    auto_increment: bool
+}
+
+impl ColorPaletteRam {
+    // All colors start out white.
+    pub fn new() -> ColorPaletteRam {
+        ColorPaletteRam {
+            data: [0xFF; COLOR_PALETTE_RAM_SIZE],
+            index: 0,
+            auto_increment: false
+        }
+    }
+
+    pub fn read_index(&self) -> u8 {
+        ((self.auto_increment as u8) << 7) | 0x40 | self.index
+    }
+
+    pub fn write_index(&mut self, value: u8) {
+        self.auto_increment = value & 0x80 != 0;
+        self.index = value & 0x3F;
+    }
+
+    pub fn read_data(&self) -> u8 {
+        self.data[self.index as usize]
+    }
+
+    // The index still advances when the write itself is ignored because the PPU is drawing.
+    pub fn write_data(&mut self, value: u8, is_accessible: bool) {
+        if is_accessible {
+            self.data[self.index as usize] = value;
+        }
+
+        if self.auto_increment {
+            self.index = (self.index + 1) & 0x3F;
+        }
+    }
+
+    // Bits 0-4 are red, 5-9 green and 10-14 blue.
+    pub fn get_color(&self, palette: u8, color: u8) -> u16 {
+        let offset = (palette as usize * 4 + color as usize) * 2;
+        u16::from_le_bytes([self.data[offset], self.data[offset + 1]]) & 0x7FFF
+    }
+}
+
+impl SaveState for ColorPaletteRam {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.data);
+        writer.write_u8(self.index);
+        writer.write_bool(self.auto_increment);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        reader.read_bytes_into(&mut self.data)?;
+        self.index = reader.read_u8()?;
+        self.auto_increment = reader.read_bool()?;
+        Ok(())
+    }
+}
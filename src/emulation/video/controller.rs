@@ -1,9 +1,19 @@
+use std::io;
+
 use crate::emulation::constants::{
-    DARKEST_GREEN, DARK_GREEN, LIGHTEST_GREEN, LIGHT_GREEN, SCREEN_HEIGHT
+    DARKEST_GREEN, DARK_GREEN, LIGHTEST_GREEN, LIGHT_GREEN, OAM_END, OAM_START, SCREEN_HEIGHT,
+    VRAM_BANK_SIZE, VRAM_END, VRAM_START
 };
 use crate::emulation::device::DeviceType;
 use crate::emulation::internal_message::{InternalMessage, RendererMessage};
 use crate::emulation::interrupt::Interrupt;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+use crate::emulation::video::color_palette::ColorPaletteRam;
+use crate::emulation::video::pixel_fifo::{PixelTransfer, MAX_SPRITES_PER_LINE};
+
+const DOTS_PER_LINE: u32 = 456;
+const OAM_SCAN_DOTS: u32 = 80;
+const LINES_PER_FRAME: u8 = 154;
 
 const DEFAULT_COLORS: [(u8, u8, u8, bool); 4] = [
     (LIGHTEST_GREEN.0, LIGHTEST_GREEN.1, LIGHTEST_GREEN.2, true),
@@ -49,14 +59,20 @@ bitfield! {
 }
 
 impl GbPalette {
+    pub fn get_shade(self, color: u8) -> u8 {
+        match color {
+            0 => self.get_color_0(),
+            1 => self.get_color_1(),
+            2 => self.get_color_2(),
+            3 => self.get_color_3(),
+            _ => panic!("Invalid color: {}", color)
+        }
+    }
+
     pub fn get_color(self, color: u8, is_sprite: bool) -> (u8, u8, u8, bool) {
         match color {
             0 if is_sprite => (0, 0, 0, false),
-            0 => DEFAULT_COLORS[self.get_color_0() as usize],
-            1 => DEFAULT_COLORS[self.get_color_1() as usize],
-            2 => DEFAULT_COLORS[self.get_color_2() as usize],
-            3 => DEFAULT_COLORS[self.get_color_3() as usize],
-            _ => panic!("Invalid color: {}", color)
+            _ => DEFAULT_COLORS[self.get_shade(color) as usize]
         }
     }
 }
@@ -78,32 +94,63 @@ pub enum VideoMemoryLocation {
     WindowX,
     DMATransferControl,
     VramBank,
+    BackgroundPaletteIndex,
+    BackgroundPaletteData,
+    SpritePaletteIndex,
+    SpritePaletteData,
     Ignored(u16)
 }
 
 use crate::emulation::video::controller::VideoMemoryLocation::*;
 
-#[derive(Debug)]
+// Numbered like the mode bits in STAT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum RenderingMode {
-    OamRead,
-    VramRead,
-    Hblank,
-    Vblank
+    Hblank = 0,
+    Vblank = 1,
+    OamRead = 2,
+    VramRead = 3
+}
+
+impl RenderingMode {
+    fn decode(value: u8) -> Option<RenderingMode> {
+        match value {
+            0 => Some(RenderingMode::Hblank),
+            1 => Some(RenderingMode::Vblank),
+            2 => Some(RenderingMode::OamRead),
+            3 => Some(RenderingMode::VramRead),
+            _ => None
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct RenderingState {
     mode: RenderingMode,
     line: u8,
-    clock: u32
+    // Dots into the current line.
+    clock: u32,
+    // Set once LY has matched WY during the frame, which the window needs to be drawn.
+    window_line_reached: bool,
+    // The line of the window drawn next. It only advances past lines the window was drawn on,
+    // so hiding the window for a few lines doesn't skip any of it.
+    window_line: u8,
+    // The OR of all enabled STAT interrupt sources. Interrupts are only requested when it
+    // goes high, so a source becoming active while another one already is goes unnoticed.
+    stat_line: bool,
+    transfer: PixelTransfer
 }
 
 impl RenderingState {
     pub fn new() -> RenderingState {
         RenderingState {
-            mode: RenderingMode::OamRead,
+            mode: RenderingMode::Hblank,
             line: 0,
-            clock: 0
+            clock: 0,
+            window_line_reached: false,
+            window_line: 0,
+            stat_line: false,
+            transfer: PixelTransfer::new()
         }
     }
 }
@@ -123,10 +170,19 @@ pub struct VideoController {
     pub sprite_palette_1: GbPalette,
     pub window_y: u8,
     pub window_x: u8,
+    // The last value written to FF46, which reads back unchanged.
+    oam_dma_source: u8,
+    pub background_color_palettes: ColorPaletteRam,
+    pub sprite_color_palettes: ColorPaletteRam,
     rendering_state: RenderingState
 }
 
 impl VideoController {
+    const STATUS_READ_ONLY: LCDStatusRegister = LCDStatusRegister {
+        bits: LCDStatusRegister::LcdControllerMode.bits
+            | LCDStatusRegister::ScanlineCoincidence.bits
+    };
+
     pub fn new(device: DeviceType) -> VideoController {
         VideoController {
             device_type: device,
@@ -143,6 +199,9 @@ impl VideoController {
             sprite_palette_1: GbPalette(0xFC),
             window_y: 0,
             window_x: 0,
+            oam_dma_source: 0xFF,
+            background_color_palettes: ColorPaletteRam::new(),
+            sprite_color_palettes: ColorPaletteRam::new(),
             rendering_state: RenderingState::new()
         }
     }
@@ -162,27 +221,45 @@ impl VideoController {
             0xFF4A => WindowY,
             0xFF4B => WindowX,
             0xFF4F => VramBank,
+            0xFF68 => BackgroundPaletteIndex,
+            0xFF69 => BackgroundPaletteData,
+            0xFF6A => SpritePaletteIndex,
+            0xFF6B => SpritePaletteData,
             _ => Ignored(address)
         }
     }
 
     pub fn read_8(&self, location: VideoMemoryLocation) -> u8 {
         match location {
-            Vram(offs) => self.vram[offs as usize],
+            Vram(offs) => self.vram[self.get_vram_index(offs)],
             Oam(offs) => self.oam[offs as usize],
             LCDControlRegister => self.lcd_control.bits(),
-            LCDStatusRegister => self.lcd_status.bits(),
+            LCDStatusRegister => self.lcd_status.bits() | 0b1000_0000,
             ScrollY => self.scroll_y,
             ScrollX => self.scroll_x,
-            CurrentScanline => self.rendering_state.line,
+            CurrentScanline => self.get_current_scanline(),
             ComparisonScanline => self.comparison_scanline,
             BackgroundPalette => self.background_palette.0,
             SpritePalette0 => self.sprite_palette_0.0,
             SpritePalette1 => self.sprite_palette_1.0,
             WindowY => self.window_y,
             WindowX => self.window_x,
-            DMATransferControl => 0,
-            VramBank => self.vram_bank,
+            DMATransferControl => self.oam_dma_source,
+            VramBank if self.device_type.is_color() => 0xFE | self.vram_bank,
+            VramBank => 0xFF,
+            BackgroundPaletteIndex
+            | BackgroundPaletteData
+            | SpritePaletteIndex
+            | SpritePaletteData
+                if !self.device_type.is_color() =>
+            {
+                0xFF
+            }
+            BackgroundPaletteIndex => self.background_color_palettes.read_index(),
+            SpritePaletteIndex => self.sprite_color_palettes.read_index(),
+            BackgroundPaletteData | SpritePaletteData if !self.is_palette_accessible() => 0xFF,
+            BackgroundPaletteData => self.background_color_palettes.read_data(),
+            SpritePaletteData => self.sprite_color_palettes.read_data(),
             Ignored(address) => {
                 println!(
                     "Tried to read from an invalid GPU address: ${:04x}",
@@ -195,22 +272,47 @@ impl VideoController {
 
     pub fn write_8(&mut self, location: VideoMemoryLocation, value: u8) -> InternalMessage {
         match location {
-            Vram(offs) => self.vram[offs as usize] = value,
+            Vram(offs) => {
+                let index = self.get_vram_index(offs);
+                self.vram[index] = value
+            }
             Oam(offs) => self.oam[offs as usize] = value,
-            LCDControlRegister => self.lcd_control = LCDControlRegister::from_bits(value).unwrap(),
-            LCDStatusRegister => self.lcd_status = LCDStatusRegister::from_bits(value).unwrap(),
+            LCDControlRegister => {
+                self.set_lcd_control(LCDControlRegister::from_bits(value).unwrap())
+            }
+            LCDStatusRegister => {
+                // The mode and coincidence bits are read only.
+                let interrupt_sources = LCDStatusRegister::from_bits_truncate(value & 0b0111_1000);
+                self.lcd_status = (self.lcd_status & Self::STATUS_READ_ONLY) | interrupt_sources;
+            }
             ScrollY => self.scroll_y = value,
             ScrollX => self.scroll_x = value,
-            CurrentScanline => self.rendering_state.line = value,
+            CurrentScanline => (),
             ComparisonScanline => self.comparison_scanline = value,
             BackgroundPalette => self.background_palette = GbPalette(value),
             SpritePalette0 => self.sprite_palette_0 = GbPalette(value),
             SpritePalette1 => self.sprite_palette_1 = GbPalette(value),
             WindowY => self.window_y = value,
             WindowX => self.window_x = value,
-            DMATransferControl => (),
-            // TODO: handle special behavior here, or in the VRAM case?
-            VramBank => self.vram_bank = value,
+            DMATransferControl => self.oam_dma_source = value,
+            VramBank if self.device_type.is_color() => self.vram_bank = value & 1,
+            VramBank => (),
+            BackgroundPaletteIndex
+            | BackgroundPaletteData
+            | SpritePaletteIndex
+            | SpritePaletteData
+                if !self.device_type.is_color() => {}
+            BackgroundPaletteIndex => self.background_color_palettes.write_index(value),
+            SpritePaletteIndex => self.sprite_color_palettes.write_index(value),
+            BackgroundPaletteData => {
+                let is_accessible = self.is_palette_accessible();
+                self.background_color_palettes
+                    .write_data(value, is_accessible)
+            }
+            SpritePaletteData => {
+                let is_accessible = self.is_palette_accessible();
+                self.sprite_color_palettes.write_data(value, is_accessible)
+            }
             Ignored(address) => {
                 println!(
                     "Tried to write to an invalid GPU address: ${:04x} <- {:02x}",
@@ -223,10 +325,26 @@ impl VideoController {
             DMATransferControl => InternalMessage::DMATransfer {
                 from: (value as u16) << 8
             },
+            // These can make the STAT interrupt line go high right away.
+            LCDControlRegister | LCDStatusRegister | ComparisonScanline => self.update_status(),
             _ => InternalMessage::None
         }
     }
 
+    fn get_vram_index(&self, offset: u16) -> usize {
+        self.vram_bank as usize * VRAM_BANK_SIZE + offset as usize
+    }
+
+    // The renderer reads VRAM directly, regardless of the bank the CPU has selected.
+    pub fn get_vram_bank(&self, bank: usize) -> &[u8] {
+        &self.vram[bank * VRAM_BANK_SIZE..(bank + 1) * VRAM_BANK_SIZE]
+    }
+
+    // Palette memory is in use while the PPU is drawing.
+    fn is_palette_accessible(&self) -> bool {
+        self.rendering_state.mode != RenderingMode::VramRead
+    }
+
     pub fn is_lcd_on(&self) -> bool {
         self.lcd_control.contains(LCDControlRegister::LcdPower)
     }
@@ -239,10 +357,21 @@ impl VideoController {
         self.lcd_control.contains(LCDControlRegister::BGDisplay)
     }
 
+    // On the CGB, LCDC bit 0 no longer hides the background and window. Clearing it takes away
+    // their priority over objects instead.
+    pub fn has_bg_master_priority(&self) -> bool {
+        self.lcd_control.contains(LCDControlRegister::BGDisplay)
+    }
+
+    pub fn is_color(&self) -> bool {
+        self.device_type.is_color()
+    }
+
     pub fn are_sprites_enabled(&self) -> bool {
         self.lcd_control.contains(LCDControlRegister::ObjDisplay)
     }
 
+    // Background and window tile indices are unsigned from 0x8000, or signed from 0x9000.
     pub fn get_tile_pattern_table_addr(&self) -> u16 {
         if self
             .lcd_control
@@ -250,10 +379,16 @@ impl VideoController {
         {
             0x8000
         } else {
-            0x8800
+            0x9000
         }
     }
 
+    pub fn has_signed_tile_indices(&self) -> bool {
+        !self
+            .lcd_control
+            .contains(LCDControlRegister::TilePatternTable)
+    }
+
     pub fn get_bg_tile_table_addr(&self) -> u16 {
         if self.lcd_control.contains(LCDControlRegister::BgTileTable) {
             0x9C00
@@ -281,52 +416,290 @@ impl VideoController {
         }
     }
 
-    // Timings and basic principle from
-    // http://imrannazar.com/GameBoy-Emulation-in-JavaScript:-GPU-Timings
-    pub fn update(&mut self, elapsed_clocks: u32) -> InternalMessage {
-        self.rendering_state.clock = self.rendering_state.clock.saturating_add(elapsed_clocks);
-        let clock = self.rendering_state.clock;
+    // The CPU can't access OAM while the PPU is scanning or drawing from it, nor VRAM while a
+    // line is being drawn. Reads return 0xFF and writes are ignored.
+    pub fn is_cpu_access_blocked(&self, address: u16) -> bool {
+        matches!(
+            (address, self.rendering_state.mode),
+            (VRAM_START..=VRAM_END, RenderingMode::VramRead)
+                | (OAM_START..=OAM_END, RenderingMode::OamRead)
+                | (OAM_START..=OAM_END, RenderingMode::VramRead)
+        )
+    }
+
+    // LY already reads 0 a machine cycle into the last line of the frame.
+    fn get_current_scanline(&self) -> u8 {
+        let state = &self.rendering_state;
 
-        match self.rendering_state.mode {
-            RenderingMode::OamRead if clock >= 80 => {
-                self.rendering_state.clock = 0;
-                self.rendering_state.mode = RenderingMode::VramRead;
-                InternalMessage::None
+        if state.line == LINES_PER_FRAME - 1 && state.clock >= 4 {
+            0
+        } else {
+            state.line
+        }
+    }
+
+    // Turning the LCD off resets LY and the mode, and it starts over at the beginning of a
+    // frame when turned back on.
+    fn set_lcd_control(&mut self, value: LCDControlRegister) {
+        let was_on = self.is_lcd_on();
+        self.lcd_control = value;
+
+        if was_on != self.is_lcd_on() {
+            let state = &mut self.rendering_state;
+            state.line = 0;
+            state.clock = 0;
+            state.window_line_reached = false;
+            state.window_line = 0;
+            state.transfer = PixelTransfer::new();
+            state.mode = if was_on {
+                RenderingMode::Hblank
+            } else {
+                RenderingMode::OamRead
+            };
+
+            if !was_on {
+                self.start_line();
             }
-            RenderingMode::VramRead if clock >= 172 => {
-                self.rendering_state.clock = 0;
+        }
+    }
+
+    fn is_stat_line_high(&self) -> bool {
+        if !self.is_lcd_on() {
+            return false;
+        }
+
+        let status = self.lcd_status;
+        let state = &self.rendering_state;
+        // The mode 2 source also fires when VBlank starts.
+        let is_vblank_start = state.line == SCREEN_HEIGHT as u8 && state.clock == 0;
+
+        let mode_0 = state.mode == RenderingMode::Hblank;
+        let mode_1 = state.mode == RenderingMode::Vblank;
+        let mode_2 = state.mode == RenderingMode::OamRead || is_vblank_start;
+        let coincidence = status.contains(LCDStatusRegister::ScanlineCoincidence);
+
+        (mode_0 && status.contains(LCDStatusRegister::InterruptOnMode0))
+            || (mode_1 && status.contains(LCDStatusRegister::InterruptOnMode1))
+            || (mode_2 && status.contains(LCDStatusRegister::InterruptOnMode2))
+            || (coincidence && status.contains(LCDStatusRegister::ScanlineCoincidenceInterrupt))
+    }
 
-                // Handle interrupt here?
+    // Brings the mode and coincidence bits of STAT up to date and requests the STAT interrupt
+    // if that made the interrupt line go high.
+    fn update_status(&mut self) -> InternalMessage {
+        let mode = LCDStatusRegister::from_bits_truncate(self.rendering_state.mode as u8);
+        self.lcd_status.remove(Self::STATUS_READ_ONLY);
+        self.lcd_status.insert(mode);
+
+        if self.is_lcd_on() {
+            self.lcd_status.set(
+                LCDStatusRegister::ScanlineCoincidence,
+                self.get_current_scanline() == self.comparison_scanline
+            );
+        }
+
+        let stat_line = self.is_stat_line_high();
+        let was_high = std::mem::replace(&mut self.rendering_state.stat_line, stat_line);
+
+        if stat_line && !was_high {
+            InternalMessage::TriggerInterrupt(Interrupt::LCDController)
+        } else {
+            InternalMessage::None
+        }
+    }
+
+    fn start_line(&mut self) {
+        let state = &mut self.rendering_state;
+
+        if state.line == self.window_y {
+            state.window_line_reached = true;
+        }
+    }
+
+    // Finds the first objects in OAM that overlap the current line. Their X coordinates are
+    // all the pixel transfer needs, and only if objects are being drawn at all.
+    fn scan_oam(&mut self) {
+        let height = self.get_sprite_height() as u16;
+        let line = self.rendering_state.line as u16 + 16;
+
+        let mut sprites = [0u8; MAX_SPRITES_PER_LINE];
+        let mut count = 0;
+
+        for attributes in self.oam.chunks(4) {
+            let y = attributes[0] as u16;
+
+            if line >= y && line < y + height {
+                sprites[count] = attributes[1];
+                count += 1;
+
+                if count == MAX_SPRITES_PER_LINE {
+                    break;
+                }
+            }
+        }
+
+        if !self.are_sprites_enabled() {
+            count = 0;
+        }
+
+        self.rendering_state
+            .transfer
+            .start_line(self.scroll_x, &sprites[..count]);
+    }
+
+    // The line of the window shown on the current line, if it was drawn there. Only valid once
+    // the line has been drawn, which is when renderers are asked to draw it.
+    pub fn get_window_line(&self) -> Option<u8> {
+        let state = &self.rendering_state;
+
+        if state.line < SCREEN_HEIGHT as u8 && state.transfer.is_window_active() {
+            Some(state.window_line)
+        } else {
+            None
+        }
+    }
+
+    fn get_transfer_window_x(&self) -> Option<u8> {
+        if self.is_window_enabled() && self.rendering_state.window_line_reached {
+            Some(self.window_x)
+        } else {
+            None
+        }
+    }
+
+    fn update_dot(&mut self, messages: &mut Vec<InternalMessage>) {
+        if self.rendering_state.mode == RenderingMode::VramRead {
+            let window_x = self.get_transfer_window_x();
+
+            if self.rendering_state.transfer.update(window_x) {
                 self.rendering_state.mode = RenderingMode::Hblank;
-                InternalMessage::RendererMessage(RendererMessage::RenderScanline(
-                    self.rendering_state.line
-                ))
+                messages.push(InternalMessage::RendererMessage(
+                    RendererMessage::RenderScanline(self.rendering_state.line)
+                ));
+                messages.push(InternalMessage::HblankStarted);
             }
-            RenderingMode::Hblank if clock >= 204 => {
-                self.rendering_state.clock = 0;
-                self.rendering_state.line += 1;
-
-                if self.rendering_state.line == SCREEN_HEIGHT as u8 {
-                    self.rendering_state.mode = RenderingMode::Vblank;
-                    InternalMessage::TriggerInterrupt(Interrupt::LCDVBlank)
-                } else {
-                    self.rendering_state.mode = RenderingMode::OamRead;
-                    InternalMessage::None
-                }
+        }
+
+        self.rendering_state.clock += 1;
+
+        if self.rendering_state.mode == RenderingMode::OamRead
+            && self.rendering_state.clock == OAM_SCAN_DOTS
+        {
+            self.scan_oam();
+            self.rendering_state.mode = RenderingMode::VramRead;
+        }
+
+        if self.rendering_state.clock == DOTS_PER_LINE {
+            let state = &mut self.rendering_state;
+            state.clock = 0;
+
+            if state.line < SCREEN_HEIGHT as u8 && state.transfer.is_window_active() {
+                state.window_line += 1;
             }
-            RenderingMode::Vblank if clock >= 456 => {
-                self.rendering_state.clock = 0;
-                self.rendering_state.line += 1;
-
-                if self.rendering_state.line > 153 {
-                    self.rendering_state.mode = RenderingMode::OamRead;
-                    self.rendering_state.line = 0;
-                    InternalMessage::RendererMessage(RendererMessage::PrepareNextFrame)
-                } else {
-                    InternalMessage::None
-                }
+
+            state.line += 1;
+
+            if state.line == SCREEN_HEIGHT as u8 {
+                state.mode = RenderingMode::Vblank;
+                messages.push(InternalMessage::TriggerInterrupt(Interrupt::LCDVBlank));
+            } else if state.line == LINES_PER_FRAME {
+                state.mode = RenderingMode::OamRead;
+                state.line = 0;
+                state.window_line_reached = false;
+                state.window_line = 0;
+                messages.push(InternalMessage::RendererMessage(
+                    RendererMessage::PrepareNextFrame
+                ));
+            } else if state.line < SCREEN_HEIGHT as u8 {
+                state.mode = RenderingMode::OamRead;
             }
-            _ => InternalMessage::None
+
+            if self.rendering_state.mode == RenderingMode::OamRead {
+                self.start_line();
+            }
+        }
+
+        let message = self.update_status();
+        if let InternalMessage::TriggerInterrupt(_) = message {
+            messages.push(message);
+        }
+    }
+
+    // Runs the PPU for the given number of dots, collecting the interrupts and renderer
+    // messages that happened along the way.
+    pub fn update(&mut self, elapsed_dots: u32, messages: &mut Vec<InternalMessage>) {
+        for _ in 0..elapsed_dots {
+            self.update_dot(messages);
+        }
+    }
+}
+
+impl SaveState for RenderingState {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.mode as u8);
+        writer.write_u8(self.line);
+        writer.write_u32(self.clock);
+        writer.write_bool(self.window_line_reached);
+        writer.write_u8(self.window_line);
+        writer.write_bool(self.stat_line);
+        self.transfer.write_state(writer);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.mode = reader.read_enum(RenderingMode::decode)?;
+        self.line = reader.read_u8()?;
+        self.clock = reader.read_u32()?;
+        self.window_line_reached = reader.read_bool()?;
+        self.window_line = reader.read_u8()?;
+        self.stat_line = reader.read_bool()?;
+        self.transfer.read_state(reader)
+    }
+}
+
+impl SaveState for VideoController {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.vram);
+        writer.write_bytes(&self.oam);
+        writer.write_u8(self.vram_bank);
+        writer.write_u8(self.lcd_control.bits());
+        writer.write_u8(self.lcd_status.bits());
+        writer.write_u8(self.scroll_y);
+        writer.write_u8(self.scroll_x);
+        writer.write_u8(self.comparison_scanline);
+        writer.write_u8(self.background_palette.0);
+        writer.write_u8(self.sprite_palette_0.0);
+        writer.write_u8(self.sprite_palette_1.0);
+        writer.write_u8(self.window_y);
+        writer.write_u8(self.window_x);
+        writer.write_u8(self.oam_dma_source);
+        self.background_color_palettes.write_state(writer);
+        self.sprite_color_palettes.write_state(writer);
+        self.rendering_state.write_state(writer);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        reader.read_bytes_into(&mut self.vram)?;
+        reader.read_bytes_into(&mut self.oam)?;
+        self.vram_bank = reader.read_u8()?;
+        if self.vram_bank as usize >= self.vram.len() / VRAM_BANK_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Save state selects a VRAM bank that doesn't exist"
+            ));
         }
+        self.lcd_control = LCDControlRegister::from_bits_truncate(reader.read_u8()?);
+        self.lcd_status = LCDStatusRegister::from_bits_truncate(reader.read_u8()?);
+        self.scroll_y = reader.read_u8()?;
+        self.scroll_x = reader.read_u8()?;
+        self.comparison_scanline = reader.read_u8()?;
+        self.background_palette = GbPalette(reader.read_u8()?);
+        self.sprite_palette_0 = GbPalette(reader.read_u8()?);
+        self.sprite_palette_1 = GbPalette(reader.read_u8()?);
+        self.window_y = reader.read_u8()?;
+        self.window_x = reader.read_u8()?;
+        self.oam_dma_source = reader.read_u8()?;
+        self.background_color_palettes.read_state(reader)?;
+        self.sprite_color_palettes.read_state(reader)?;
+        self.rendering_state.read_state(reader)
     }
 }
@@ -1 +1,3 @@
+pub mod color_palette;
 pub mod controller;
+pub mod pixel_fifo;
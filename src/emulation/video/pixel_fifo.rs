@@ -0,0 +1,248 @@
+use std::io;
+
+use crate::emulation::constants::SCREEN_WIDTH;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+
+pub const MAX_SPRITES_PER_LINE: usize = 10;
+
+// Dots taken by fetching an object's tile once the background fetcher is out of the way.
+const SPRITE_FETCH_DOTS: u8 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FetcherStep {
+    TileNumber,
+    TileDataLow,
+    TileDataHigh,
+    Push
+}
+
+impl FetcherStep {
+    fn decode(value: u8) -> Option<FetcherStep> {
+        match value {
+            0 => Some(FetcherStep::TileNumber),
+            1 => Some(FetcherStep::TileDataLow),
+            2 => Some(FetcherStep::TileDataHigh),
+            3 => Some(FetcherStep::Push),
+            _ => None
+        }
+    }
+}
+
+// Models the pixel transfer of a single line (mode 3) to find out how long it takes. The
+// background fetcher needs two dots for each of its three reads and only pushes a tile into the
+// FIFO once it's empty, and one pixel is shifted out each dot the FIFO isn't stalled. Pixels
+// scrolled off to the left are shifted out and discarded, starting the window flushes the FIFO
+// and restarts the fetcher, and objects stall the FIFO while their tile is fetched. The
+// renderer draws the line itself, so only the number of pixels in the FIFO is kept track of.
+#[derive(Debug)]
+pub struct PixelTransfer {
+    fetcher_step: FetcherStep,
+    fetcher_clock: u8,
+    // The first tile of every line is fetched twice.
+    is_first_fetch: bool,
+    fifo_length: u8,
+    pixels_to_discard: u8,
+    scroll_x: u8,
+    x: u8,
+    is_window_active: bool,
+    // X coordinates from OAM of the objects on this line, in the order they are fetched.
+    sprites: [u8; MAX_SPRITES_PER_LINE],
+    sprite_count: u8,
+    next_sprite: u8,
+    // The background tile the fetcher was last waited for, which only happens once per tile.
+    waited_tile: Option<u8>,
+    stall_dots: u8
+}
+
+impl PixelTransfer {
+    pub fn new() -> PixelTransfer {
+        PixelTransfer {
+            fetcher_step: FetcherStep::TileNumber,
+            fetcher_clock: 0,
+            is_first_fetch: true,
+            fifo_length: 0,
+            pixels_to_discard: 0,
+            scroll_x: 0,
+            x: 0,
+            is_window_active: false,
+            sprites: [0; MAX_SPRITES_PER_LINE],
+            sprite_count: 0,
+            next_sprite: 0,
+            waited_tile: None,
+            stall_dots: 0
+        }
+    }
+
+    // Objects further right than the screen are never fetched.
+    pub fn start_line(&mut self, scroll_x: u8, sprites: &[u8]) {
+        *self = PixelTransfer::new();
+        self.scroll_x = scroll_x;
+        self.pixels_to_discard = scroll_x % 8;
+
+        for &sprite_x in sprites.iter().filter(|&&x| x < SCREEN_WIDTH as u8 + 8) {
+            self.sprites[self.sprite_count as usize] = sprite_x;
+            self.sprite_count += 1;
+        }
+
+        self.sprites[..self.sprite_count as usize].sort();
+    }
+
+    pub fn get_x(&self) -> u8 {
+        self.x
+    }
+
+    pub fn is_window_active(&self) -> bool {
+        self.is_window_active
+    }
+
+    fn restart_fetcher(&mut self) {
+        self.fetcher_step = FetcherStep::TileNumber;
+        self.fetcher_clock = 0;
+    }
+
+    fn update_fetcher(&mut self) {
+        match self.fetcher_step {
+            FetcherStep::Push => {
+                if self.fifo_length == 0 {
+                    self.fifo_length = 8;
+                    self.restart_fetcher();
+                }
+            }
+            step => {
+                self.fetcher_clock += 1;
+
+                if self.fetcher_clock == 2 {
+                    self.fetcher_clock = 0;
+                    self.fetcher_step = match step {
+                        FetcherStep::TileNumber => FetcherStep::TileDataLow,
+                        FetcherStep::TileDataLow => FetcherStep::TileDataHigh,
+                        _ if self.is_first_fetch => {
+                            self.is_first_fetch = false;
+                            FetcherStep::TileNumber
+                        }
+                        _ => FetcherStep::Push
+                    };
+                }
+            }
+        }
+    }
+
+    // The wait for the background fetcher depends on how far into the current tile the object
+    // starts, from five dots at its first pixel down to none from the sixth pixel on.
+    fn get_sprite_stall_dots(&mut self, sprite_x: u8) -> u8 {
+        let position = sprite_x as u16 + self.scroll_x as u16;
+        let tile = (position / 8) as u8;
+
+        if self.waited_tile == Some(tile) {
+            return SPRITE_FETCH_DOTS;
+        }
+
+        self.waited_tile = Some(tile);
+        SPRITE_FETCH_DOTS + 5 - (position % 8).min(5) as u8
+    }
+
+    fn find_sprite_at_x(&self) -> Option<u8> {
+        if self.next_sprite == self.sprite_count {
+            return None;
+        }
+
+        let sprite_x = self.sprites[self.next_sprite as usize];
+        if sprite_x.saturating_sub(8) <= self.x {
+            Some(sprite_x)
+        } else {
+            None
+        }
+    }
+
+    // Advances the transfer by one dot and returns whether the whole line has been shifted
+    // out. The window position is given when the window is enabled and has been reached
+    // vertically.
+    pub fn update(&mut self, window_x: Option<u8>) -> bool {
+        if self.stall_dots > 0 {
+            self.stall_dots -= 1;
+            return false;
+        }
+
+        if self.pixels_to_discard == 0 {
+            if let Some(window_x) = window_x {
+                if !self.is_window_active && self.x + 7 >= window_x {
+                    self.is_window_active = true;
+                    self.fifo_length = 0;
+                    self.is_first_fetch = false;
+                    self.restart_fetcher();
+                }
+            }
+
+            if let Some(sprite_x) = self.find_sprite_at_x() {
+                self.next_sprite += 1;
+                // This dot is the first one of the stall.
+                self.stall_dots = self.get_sprite_stall_dots(sprite_x) - 1;
+                return false;
+            }
+        }
+
+        self.update_fetcher();
+
+        if self.fifo_length > 0 {
+            self.fifo_length -= 1;
+
+            if self.pixels_to_discard > 0 {
+                self.pixels_to_discard -= 1;
+            } else {
+                self.x += 1;
+            }
+        }
+
+        self.x == SCREEN_WIDTH as u8
+    }
+}
+
+impl SaveState for PixelTransfer {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.fetcher_step as u8);
+        writer.write_u8(self.fetcher_clock);
+        writer.write_bool(self.is_first_fetch);
+        writer.write_u8(self.fifo_length);
+        writer.write_u8(self.pixels_to_discard);
+        writer.write_u8(self.scroll_x);
+        writer.write_u8(self.x);
+        writer.write_bool(self.is_window_active);
+        writer.write_bytes(&self.sprites);
+        writer.write_u8(self.sprite_count);
+        writer.write_u8(self.next_sprite);
+        writer.write_bool(self.waited_tile.is_some());
+        writer.write_u8(self.waited_tile.unwrap_or(0));
+        writer.write_u8(self.stall_dots);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.fetcher_step = reader.read_enum(FetcherStep::decode)?;
+        self.fetcher_clock = reader.read_u8()?;
+        self.is_first_fetch = reader.read_bool()?;
+        self.fifo_length = reader.read_u8()?;
+        self.pixels_to_discard = reader.read_u8()?;
+        self.scroll_x = reader.read_u8()?;
+        self.x = reader.read_u8()?;
+        self.is_window_active = reader.read_bool()?;
+        reader.read_bytes_into(&mut self.sprites)?;
+        let sprite_count = reader.read_u8()?;
+        let next_sprite = reader.read_u8()?;
+        if sprite_count as usize > MAX_SPRITES_PER_LINE || next_sprite > sprite_count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Save state has an invalid object count for the current line"
+            ));
+        }
+        self.sprite_count = sprite_count;
+        self.next_sprite = next_sprite;
+        let has_waited_tile = reader.read_bool()?;
+        let waited_tile = reader.read_u8()?;
+        self.waited_tile = if has_waited_tile {
+            Some(waited_tile)
+        } else {
+            None
+        };
+        self.stall_dots = reader.read_u8()?;
+        Ok(())
+    }
+}
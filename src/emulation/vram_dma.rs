@@ -0,0 +1,156 @@
+use std::io;
+
+use crate::emulation::constants::VRAM_START;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+
+pub const VRAM_DMA_BLOCK_SIZE: u16 = 16;
+
+#[derive(Debug, Clone, Copy)]
+pub enum VramDmaRegister {
+    SourceHigh,
+    SourceLow,
+    DestinationHigh,
+    DestinationLow,
+    Control
+}
+
+// The CGB's DMA into VRAM (HDMA1-5). A general purpose transfer copies every block back to
+// back, while an H-blank transfer copies one 16 byte block at the start of every H-blank until
+// it's done or cancelled. Both stop the CPU while a block is copied.
+#[derive(Debug)]
+pub struct VramDma {
+    source: u16,
+    // Relative to the start of VRAM.
+    destination: u16,
+    remaining_blocks: u8,
+    is_general_transfer_active: bool,
+    is_hblank_transfer_active: bool,
+    is_hblank_block_due: bool
+}
+
+impl VramDma {
+    pub fn new() -> VramDma {
+        VramDma {
+            source: 0,
+            destination: 0,
+            remaining_blocks: 0,
+            is_general_transfer_active: false,
+            is_hblank_transfer_active: false,
+            is_hblank_block_due: false
+        }
+    }
+
+    pub fn resolve_address(&self, addr: u16) -> VramDmaRegister {
+        match addr {
+            0xFF51 => VramDmaRegister::SourceHigh,
+            0xFF52 => VramDmaRegister::SourceLow,
+            0xFF53 => VramDmaRegister::DestinationHigh,
+            0xFF54 => VramDmaRegister::DestinationLow,
+            0xFF55 => VramDmaRegister::Control,
+            _ => panic!("Invalid VRAM DMA address: ${:04x}", addr)
+        }
+    }
+
+    // Bit 7 is clear while an H-blank transfer is running, and the rest is the number of
+    // blocks left minus one, so a finished transfer reads 0xFF.
+    pub fn read_8(&self, register: VramDmaRegister) -> u8 {
+        match register {
+            VramDmaRegister::Control => {
+                let status = if self.is_hblank_transfer_active {
+                    0
+                } else {
+                    0x80
+                };
+                status | (self.remaining_blocks.wrapping_sub(1) & 0x7F)
+            }
+            _ => 0xFF
+        }
+    }
+
+    // With the LCD off there are no H-blanks, so an H-blank transfer started then copies its
+    // first block right away.
+    pub fn write_8(&mut self, register: VramDmaRegister, value: u8, is_lcd_on: bool) {
+        match register {
+            VramDmaRegister::SourceHigh => {
+                self.source = (self.source & 0x00FF) | ((value as u16) << 8);
+            }
+            VramDmaRegister::SourceLow => {
+                self.source = (self.source & 0xFF00) | (value & 0xF0) as u16;
+            }
+            VramDmaRegister::DestinationHigh => {
+                self.destination = (self.destination & 0x00FF) | (((value & 0x1F) as u16) << 8);
+            }
+            VramDmaRegister::DestinationLow => {
+                self.destination = (self.destination & 0x1F00) | (value & 0xF0) as u16;
+            }
+            // Writing with bit 7 clear during an H-blank transfer stops it.
+            VramDmaRegister::Control if self.is_hblank_transfer_active && value & 0x80 == 0 => {
+                self.is_hblank_transfer_active = false;
+                self.is_hblank_block_due = false;
+            }
+            VramDmaRegister::Control => {
+                self.remaining_blocks = (value & 0x7F) + 1;
+
+                if value & 0x80 == 0 {
+                    self.is_general_transfer_active = true;
+                } else {
+                    self.is_hblank_transfer_active = true;
+                    self.is_hblank_block_due = !is_lcd_on;
+                }
+            }
+        }
+    }
+
+    pub fn start_hblank(&mut self) {
+        if self.is_hblank_transfer_active {
+            self.is_hblank_block_due = true;
+        }
+    }
+
+    // Whether the CPU has to wait for a block to be copied.
+    pub fn is_block_due(&self) -> bool {
+        self.is_general_transfer_active || self.is_hblank_block_due
+    }
+
+    // Returns the source and destination addresses of the next block, and moves on to the one
+    // after it.
+    pub fn next_block(&mut self) -> Option<(u16, u16)> {
+        if self.remaining_blocks == 0 {
+            return None;
+        }
+
+        let block = (self.source, VRAM_START + self.destination);
+        self.source = self.source.wrapping_add(VRAM_DMA_BLOCK_SIZE);
+        self.destination = (self.destination + VRAM_DMA_BLOCK_SIZE) & 0x1FF0;
+        self.remaining_blocks -= 1;
+        self.is_hblank_block_due = false;
+
+        if self.remaining_blocks == 0 {
+            self.is_general_transfer_active = false;
+            self.is_hblank_transfer_active = false;
+        }
+
+        Some(block)
+    }
+}
+
+impl SaveState for VramDma {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_u16(self.source);
+        writer.write_u16(self.destination);
+        writer.write_u8(self.remaining_blocks);
+        writer.write_bool(self.is_general_transfer_active);
+        writer.write_bool(self.is_hblank_transfer_active);
+        writer.write_bool(self.is_hblank_block_due);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> io::Result<()> {
+        self.source = reader.read_u16()?;
+        self.destination = reader.read_u16()?;
+        self.remaining_blocks = reader.read_u8()?;
+        self.is_general_transfer_active = reader.read_bool()?;
+        self.is_hblank_transfer_active = reader.read_bool()?;
+        self.is_hblank_block_due = reader.read_bool()?;
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@
 extern crate bitflags;
 #[macro_use]
 extern crate bitfield;
+#[cfg(feature = "sdl")]
 extern crate sdl2;
 extern crate time;
 
@@ -16,4 +17,6 @@ pub mod test_util;
 
 pub mod disassembler;
 pub mod emulation;
+#[cfg(feature = "sdl")]
+pub mod playback;
 pub mod rendering;
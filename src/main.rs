@@ -6,8 +6,10 @@ extern crate sdl2;
 extern crate time;
 
 use std::error::Error;
+use std::fs;
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use std::thread::sleep;
@@ -17,14 +19,19 @@ use sdl2::keyboard::Keycode;
 use sdl2::keyboard::Scancode;
 use sdl2::EventPump;
 
+use rgbemu::playback::sdl_audio::SdlAudioSink;
+use rgbemu::rendering::png::encode_png;
 use rgbemu::rendering::sdl_renderer::*;
 use rgbemu::rendering::*;
 
+use rgbemu::emulation::audio::sink::{AudioSink, AudioSinkGroup};
+use rgbemu::emulation::audio::wav_writer::WavWriter;
 use rgbemu::emulation::cartridge::Cartridge;
-use rgbemu::emulation::constants::{GB_CYCLES_PER_SEC, SCREEN_HEIGHT, SCREEN_WIDTH};
-use rgbemu::emulation::device::Device;
+use rgbemu::emulation::constants::{GB_CYCLES_PER_SEC, GB_FRAME_RATE, SCREEN_HEIGHT, SCREEN_WIDTH};
+use rgbemu::emulation::device::{Device, DeviceType, ExecutionState};
 use rgbemu::emulation::input::InputState;
 use rgbemu::emulation::internal_message::RendererMessage::*;
+use rgbemu::emulation::rewind::RewindBuffer;
 
 fn get_input_state(event_pump: &EventPump) -> InputState {
     let sdl_state = event_pump.keyboard_state();
@@ -66,20 +73,126 @@ fn load_game(path: &str) -> Cartridge {
     cartridge
 }
 
+const DEFAULT_ROM_PATH: &str = "./test_roms/Tetris (World).gb";
+// Roughly five seconds of emulated time.
+const SAVE_FLUSH_INTERVAL_FRAMES: u32 = 300;
+// Twenty seconds of history, with a snapshot every other frame.
+const REWIND_CAPACITY: usize = 600;
+const REWIND_INTERVAL_FRAMES: u32 = 2;
+
+struct Options {
+    rom_path: String,
+    wav_path: Option<PathBuf>,
+    record_channels: bool
+}
+
+// Usage: rgbemu_sdl [rom] [--record-wav <path>] [--record-channels]
+fn parse_options() -> Options {
+    let mut options = Options {
+        rom_path: DEFAULT_ROM_PATH.to_string(),
+        wav_path: None,
+        record_channels: false
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record-wav" => options.wav_path = args.next().map(PathBuf::from),
+            "--record-channels" => options.record_channels = true,
+            _ => options.rom_path = arg
+        }
+    }
+
+    options
+}
+
+fn get_save_path(rom_path: &Path) -> PathBuf {
+    rom_path.with_extension("sav")
+}
+
+// Screenshots go next to the ROM, e.g. "Tetris (World) 2018-06-01 12-30-05.png".
+fn get_screenshot_path(rom_path: &Path) -> PathBuf {
+    let stem = rom_path.file_stem().unwrap_or_default().to_string_lossy();
+    let timestamp = time::strftime("%Y-%m-%d %H-%M-%S", &time::now()).unwrap();
+    let mut path = rom_path.with_file_name(format!("{} {}.png", stem, timestamp));
+
+    let mut counter = 2;
+    while path.exists() {
+        path = rom_path.with_file_name(format!("{} {} ({}).png", stem, timestamp, counter));
+        counter += 1;
+    }
+
+    path
+}
+
+fn save_screenshot(renderer: &SdlRenderer, rom_path: &Path) -> std::io::Result<()> {
+    let png = encode_png(SCREEN_WIDTH, SCREEN_HEIGHT, &renderer.get_frame_rgba());
+    let path = get_screenshot_path(rom_path);
+    File::create(&path)?.write_all(&png)?;
+    eprintln!("Saved screenshot to {}", path.display());
+    Ok(())
+}
+
+fn load_save_file(cartridge: &mut Cartridge, save_path: &Path) -> std::io::Result<()> {
+    if !cartridge.has_battery() || !save_path.exists() {
+        return Ok(());
+    }
+
+    let mut save_data: Vec<u8> = Vec::new();
+    File::open(save_path)?.read_to_end(&mut save_data)?;
+    cartridge.load_battery_data(&save_data)
+}
+
+struct SaveFileWriter {
+    path: PathBuf,
+    last_written: Vec<u8>
+}
+
+impl SaveFileWriter {
+    fn new(path: PathBuf, cartridge: &Cartridge) -> SaveFileWriter {
+        SaveFileWriter {
+            path,
+            last_written: cartridge.save_battery_data()
+        }
+    }
+
+    fn flush(&mut self, cartridge: &Cartridge) -> std::io::Result<()> {
+        if !cartridge.has_battery() {
+            return Ok(());
+        }
+
+        let save_data = cartridge.save_battery_data();
+        if save_data == self.last_written {
+            return Ok(());
+        }
+
+        // The old save is only replaced once the new one has been written out in full, so a
+        // crash halfway through doesn't lose it.
+        let temp_path = self.path.with_extension("sav.tmp");
+        let mut file = File::create(&temp_path)?;
+        file.write_all(&save_data)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, &self.path)?;
+
+        self.last_written = save_data;
+        Ok(())
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 enum UseBootromSetting {
     UseBootrom,
     EmulateBootrom
 }
 
-fn create_device(use_bootrom: UseBootromSetting) -> Device {
+fn create_device(device_type: DeviceType, use_bootrom: UseBootromSetting) -> Device {
     let bootrom = if use_bootrom == UseBootromSetting::UseBootrom {
         Some(load_bootrom())
     } else {
         None
     };
 
-    Device::new_gb(bootrom)
+    Device::new(device_type, bootrom)
 }
 
 fn create_renderer(context: &mut sdl2::Sdl) -> Result<SdlRenderer, Box<dyn Error>> {
@@ -104,14 +217,57 @@ fn create_renderer(context: &mut sdl2::Sdl) -> Result<SdlRenderer, Box<dyn Error
 fn main() -> Result<(), Box<dyn Error>> {
     let mut context = sdl2::init()?;
     let mut event_pump = context.event_pump()?;
+    let audio_sink = SdlAudioSink::new(&context.audio()?);
     let mut renderer = create_renderer(&mut context)?;
 
-    let cartridge = load_game("./test_roms/Tetris (World).gb");
+    let options = parse_options();
+    let rom_path = options.rom_path;
+    let mut cartridge = load_game(&rom_path);
     // let cartridge = load_game("./test_roms/pong.gb");
     // let cartridge = load_game("./cpu_instrs/individual/09-op r,r.gb");
-    let mut device = create_device(UseBootromSetting::EmulateBootrom);
+
+    let save_path = get_save_path(Path::new(&rom_path));
+    if let Err(error) = load_save_file(&mut cartridge, &save_path) {
+        eprintln!(
+            "Couldn't load {}, starting with blank RAM: {}",
+            save_path.display(),
+            error
+        );
+    }
+    let mut save_writer = SaveFileWriter::new(save_path, &cartridge);
+
+    let device_type = cartridge.header.get_device_type();
+    let mut device = create_device(device_type, UseBootromSetting::EmulateBootrom);
     device.bus.cartridge = Some(cartridge);
 
+    // Playback paces the emulation. Without an audio device we fall back to sleeping until
+    // each frame is due.
+    let mut audio_sinks: Vec<Box<dyn AudioSink>> = Vec::new();
+    let is_paced_by_audio = match audio_sink {
+        Ok(sink) => {
+            audio_sinks.push(Box::new(sink));
+            true
+        }
+        Err(error) => {
+            eprintln!("Audio output unavailable: {}", error);
+            false
+        }
+    };
+
+    if let Some(ref wav_path) = options.wav_path {
+        let wav_writer = if options.record_channels {
+            WavWriter::create_with_channels(wav_path)?
+        } else {
+            WavWriter::create(wav_path)?
+        };
+        audio_sinks.push(Box::new(wav_writer));
+    }
+
+    device.set_audio_sink(Some(Box::new(AudioSinkGroup::new(audio_sinks))));
+
+    let mut frames_since_save_flush = 0u32;
+    let mut rewind_buffer = RewindBuffer::new(REWIND_CAPACITY, REWIND_INTERVAL_FRAMES);
+
     let mut total_cycles = 0u32;
     let mut last_frame = Instant::now();
 
@@ -125,6 +281,8 @@ fn main() -> Result<(), Box<dyn Error>> {
             wait_keypress(&mut stdin)?;
         }
 
+        let mut is_frame_done = false;
+
         while let Some(msg) = device.next_renderer_message() {
             match msg {
                 PrepareNextFrame => {
@@ -135,48 +293,98 @@ fn main() -> Result<(), Box<dyn Error>> {
                 }
                 PresentFrame => {
                     renderer.present();
+                    is_frame_done = true;
+                }
+            }
+        }
 
-                    //last_update = precise_time_ns() as f64 / 10e9;
-                    //let spent = last_update - last_frame;
-                    //println!("Frametime: {}s (fps: {})", spent, 1f64 / spent);
-
-                    //last_frame = last_update;
+        // Nothing is drawn while the CPU is stopped, so input is still handled once per frame
+        // to catch the button press that wakes it up.
+        if device.execution_state == ExecutionState::Stopped
+            && total_cycles as f64 >= GB_CYCLES_PER_SEC as f64 / GB_FRAME_RATE
+        {
+            is_frame_done = true;
+        }
 
-                    let new_input_state = get_input_state(&event_pump);
+        if !is_frame_done {
+            continue;
+        }
 
-                    for event in event_pump.poll_iter() {
-                        match event {
-                            Event::Quit { .. }
-                            | Event::KeyDown {
-                                keycode: Some(Keycode::Escape),
-                                ..
-                            } => break 'main_loop,
-                            _ => ()
-                        }
+        //last_update = precise_time_ns() as f64 / 10e9;
+        //let spent = last_update - last_frame;
+        //println!("Frametime: {}s (fps: {})", spent, 1f64 / spent);
+
+        //last_frame = last_update;
+
+        let new_input_state = get_input_state(&event_pump);
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => break 'main_loop,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => {
+                    if let Err(error) = save_screenshot(&renderer, Path::new(&rom_path)) {
+                        eprintln!("Couldn't save screenshot: {}", error);
                     }
+                }
+                _ => ()
+            }
+        }
 
-                    device.update_input(new_input_state);
+        device.update_input(new_input_state);
 
-                    let time_spent = Instant::now().duration_since(last_frame);
-                    let expected_time = Duration::new(
-                        0,
-                        ((1f64 / GB_CYCLES_PER_SEC as f64) * total_cycles as f64 * 1e9) as u32
-                    );
+        if event_pump
+            .keyboard_state()
+            .is_scancode_pressed(Scancode::Backspace)
+        {
+            rewind_buffer.step_back(&mut device);
+        } else {
+            rewind_buffer.push_frame(&device);
+        }
 
-                    let sleep_time = if time_spent > expected_time {
-                        Duration::new(0, 0)
-                    } else {
-                        expected_time - time_spent
-                    };
+        frames_since_save_flush += 1;
+        if frames_since_save_flush >= SAVE_FLUSH_INTERVAL_FRAMES {
+            frames_since_save_flush = 0;
+            // A failed write is tried again at the next interval.
+            if let Some(ref cartridge) = device.bus.cartridge {
+                if let Err(error) = save_writer.flush(cartridge) {
+                    eprintln!("Couldn't write save file: {}", error);
+                }
+            }
+        }
 
-                    last_frame = Instant::now();
-                    total_cycles = 0;
+        if !is_paced_by_audio || device.execution_state == ExecutionState::Stopped {
+            let time_spent = Instant::now().duration_since(last_frame);
+            let expected_time =
+                Duration::from_secs_f64(total_cycles as f64 / GB_CYCLES_PER_SEC as f64);
 
-                    // println!("FPS: {}", 1.0f64 / ((time_spent.subsec_nanos() as f64 + time_spent.as_secs() as f64 * 1e9) / 1e9));
-                    // sleep(sleep_time);
-                }
+            if time_spent < expected_time {
+                sleep(expected_time - time_spent);
             }
         }
+
+        last_frame = Instant::now();
+        total_cycles = 0;
+    }
+
+    if let Some(ref cartridge) = device.bus.cartridge {
+        if let Err(error) = save_writer.flush(cartridge) {
+            eprintln!("Couldn't write save file: {}", error);
+        }
+    }
+
+    // Finalizes the WAV recording, if there is one.
+    if let Some(mut sink) = device.set_audio_sink(None) {
+        if let Err(error) = sink.finish() {
+            eprintln!("Audio recording failed: {}", error);
+        }
     }
 
     Ok(())
@@ -0,0 +1 @@
+pub mod sdl_audio;
@@ -0,0 +1,90 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+use sdl2::AudioSubsystem;
+
+use crate::emulation::audio::controller::StereoSample;
+use crate::emulation::audio::resampler::Resampler;
+use crate::emulation::audio::sink::AudioSink;
+use crate::emulation::constants::APU_SAMPLE_RATE;
+
+const OUTPUT_SAMPLE_RATE: i32 = 48000;
+const DEVICE_BUFFER_FRAMES: u16 = 1024;
+// Resampled frames are handed to SDL in chunks of this size.
+const QUEUE_CHUNK_FRAMES: usize = 512;
+// About 60 ms of audio.
+const TARGET_QUEUED_FRAMES: u32 = 2880;
+// The output rate is allowed to drift by at most 0.5%, which is not audible as a pitch change.
+const MAX_RATE_ADJUSTMENT: f64 = 0.005;
+const BYTES_PER_FRAME: u32 = 2 * 4;
+
+// Plays the APU output through an SDL audio queue. Pushing samples blocks while the queue is
+// full, so the emulation runs at exactly the speed the audio device consumes samples. If the
+// emulation falls behind, the resampler produces slightly more samples to stop the queue from
+// running dry.
+pub struct SdlAudioSink {
+    queue: AudioQueue<f32>,
+    resampler: Resampler,
+    buffer: Vec<f32>
+}
+
+impl SdlAudioSink {
+    pub fn new(audio: &AudioSubsystem) -> Result<SdlAudioSink, String> {
+        let spec = AudioSpecDesired {
+            freq: Some(OUTPUT_SAMPLE_RATE),
+            channels: Some(2),
+            samples: Some(DEVICE_BUFFER_FRAMES)
+        };
+
+        let queue = audio.open_queue::<f32, _>(None, &spec)?;
+        let output_rate = queue.spec().freq as u32;
+
+        if queue.spec().channels != 2 {
+            return Err(format!(
+                "Expected a stereo audio device, got {} channels",
+                queue.spec().channels
+            ));
+        }
+
+        queue.resume();
+
+        Ok(SdlAudioSink {
+            queue,
+            resampler: Resampler::new(APU_SAMPLE_RATE, output_rate),
+            buffer: Vec::with_capacity(QUEUE_CHUNK_FRAMES * 2 * 2)
+        })
+    }
+
+    fn get_queued_frames(&self) -> u32 {
+        self.queue.size() / BYTES_PER_FRAME
+    }
+
+    fn flush(&mut self) {
+        for sample in self.resampler.drain_output() {
+            self.buffer.push(sample.left);
+            self.buffer.push(sample.right);
+        }
+
+        while self.get_queued_frames() > TARGET_QUEUED_FRAMES {
+            sleep(Duration::from_millis(1));
+        }
+
+        let fill = self.get_queued_frames() as f64 / TARGET_QUEUED_FRAMES as f64;
+        let adjustment = 1.0 + MAX_RATE_ADJUSTMENT * (1.0 - fill).clamp(-1.0, 1.0);
+        self.resampler.set_rate_adjustment(adjustment);
+
+        self.queue.queue(&self.buffer);
+        self.buffer.clear();
+    }
+}
+
+impl AudioSink for SdlAudioSink {
+    fn push_sample(&mut self, sample: StereoSample) {
+        self.resampler.push_sample(sample);
+
+        if self.resampler.available() >= QUEUE_CHUNK_FRAMES {
+            self.flush();
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::emulation::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::emulation::device::Device;
+use crate::rendering::png;
+use crate::rendering::scanline::ScanlineRasterizer;
+use crate::rendering::*;
+
+const PIXEL_COUNT: usize = (SCREEN_WIDTH * SCREEN_HEIGHT) as usize;
+
+// Renders into memory instead of a window, for tests and headless use. Scanlines are drawn into
+// back buffers, and the visible buffers only change when a frame is presented, so they always
+// hold a complete frame. In CGB mode, there are no shades, so they're all left at 0.
+pub struct FramebufferRenderer {
+    rasterizer: ScanlineRasterizer,
+    back_buffer: Vec<u8>,
+    back_rgba: Vec<u8>,
+    shades: Vec<u8>,
+    rgba: Vec<u8>,
+    frame_count: u64
+}
+
+impl FramebufferRenderer {
+    pub fn new() -> FramebufferRenderer {
+        let mut renderer = FramebufferRenderer {
+            rasterizer: ScanlineRasterizer::new(),
+            back_buffer: vec![0; PIXEL_COUNT],
+            back_rgba: vec![0; PIXEL_COUNT * 4],
+            shades: vec![0; PIXEL_COUNT],
+            rgba: vec![0; PIXEL_COUNT * 4],
+            frame_count: 0
+        };
+
+        renderer.clear_back_buffers();
+        renderer.rgba.copy_from_slice(&renderer.back_rgba);
+        renderer
+    }
+
+    // One byte per pixel, row by row, each a shade from 0 (lightest) to 3 (darkest).
+    pub fn get_shades(&self) -> &[u8] {
+        &self.shades
+    }
+
+    // Four bytes per pixel in R, G, B, A order.
+    pub fn get_rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    pub fn get_shade(&self, x: u32, y: u32) -> u8 {
+        self.shades[(y * SCREEN_WIDTH + x) as usize]
+    }
+
+    pub fn encode_png(&self) -> Vec<u8> {
+        png::encode_png(SCREEN_WIDTH, SCREEN_HEIGHT, &self.rgba)
+    }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    fn clear_back_buffers(&mut self) {
+        let (r, g, b) = SHADE_COLORS[0];
+
+        for shade in self.back_buffer.iter_mut() {
+            *shade = 0;
+        }
+
+        for pixel in self.back_rgba.chunks_mut(4) {
+            pixel.copy_from_slice(&[r, g, b, 255]);
+        }
+    }
+}
+
+impl Renderer for FramebufferRenderer {
+    fn present(&mut self) {
+        self.shades.copy_from_slice(&self.back_buffer);
+        self.rgba.copy_from_slice(&self.back_rgba);
+        self.frame_count += 1;
+    }
+
+    fn prepare_frame(&mut self, device: &Device) {
+        self.rasterizer.refresh(device);
+        self.clear_back_buffers();
+    }
+
+    fn draw_scanline(&mut self, device: &Device, scanline: u8) {
+        if scanline as u32 >= SCREEN_HEIGHT {
+            return;
+        }
+
+        let start = scanline as usize * SCREEN_WIDTH as usize;
+        let end = start + SCREEN_WIDTH as usize;
+        let rgba = &mut self.back_rgba[start * 4..end * 4];
+
+        if device.bus.video.is_color() {
+            let mut colors = [0u16; SCREEN_WIDTH as usize];
+            self.rasterizer
+                .draw_color_scanline(device, scanline, &mut colors);
+
+            for (&color, pixel) in colors.iter().zip(rgba.chunks_mut(4)) {
+                let (r, g, b) = rgb555_to_rgb(color);
+                pixel.copy_from_slice(&[r, g, b, 255]);
+            }
+        } else {
+            let shades = &mut self.back_buffer[start..end];
+            self.rasterizer.draw_scanline(device, scanline, shades);
+
+            for (&shade, pixel) in shades.iter().zip(rgba.chunks_mut(4)) {
+                let (r, g, b) = SHADE_COLORS[shade as usize];
+                pixel.copy_from_slice(&[r, g, b, 255]);
+            }
+        }
+    }
+}
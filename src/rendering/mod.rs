@@ -2,7 +2,8 @@ use crate::emulation::bitutils::*;
 use crate::emulation::bus::Bus;
 use crate::emulation::constants::*;
 use crate::emulation::device::Device;
-use crate::emulation::video::controller::GbPalette;
+use crate::emulation::internal_message::RendererMessage;
+use crate::emulation::video::controller::{GbPalette, VideoController};
 
 pub trait RendererColor
 where
@@ -17,6 +18,11 @@ where
         Self::from_rgb(r, g, b)
     }
 
+    // CGB colors have five bits per channel, from red in the lowest bits to blue.
+    fn from_rgb555(color: u16) -> Self {
+        Self::from_rgb_tuple(rgb555_to_rgb(color))
+    }
+
     fn from_gb_color(color: u8) -> Self {
         match color {
             0 => Self::from_rgb_tuple(LIGHTEST_GREEN),
@@ -59,10 +65,31 @@ where
     }
 }
 
+pub const SHADE_COLORS: [(u8, u8, u8); 4] =
+    [LIGHTEST_GREEN, LIGHT_GREEN, DARK_GREEN, DARKEST_GREEN];
+
+// Channels are scaled up so that 31 becomes 255.
+pub fn rgb555_to_rgb(color: u16) -> (u8, u8, u8) {
+    let scale = |channel: u16| {
+        let channel = (channel & 0x1F) as u8;
+        (channel << 3) | (channel >> 2)
+    };
+
+    (scale(color), scale(color >> 5), scale(color >> 10))
+}
+
 pub trait Renderer {
     fn present(&mut self);
     fn prepare_frame(&mut self, device: &Device);
     fn draw_scanline(&mut self, device: &Device, scanline: u8);
+
+    fn handle_message(&mut self, device: &Device, message: RendererMessage) {
+        match message {
+            RendererMessage::PrepareNextFrame => self.prepare_frame(device),
+            RendererMessage::RenderScanline(scanline) => self.draw_scanline(device, scanline),
+            RendererMessage::PresentFrame => self.present()
+        }
+    }
 }
 
 pub struct NullRenderer {}
@@ -109,28 +136,72 @@ impl CommonRenderer {
         }
     }
 
-    pub fn read_tiles(bus: &Bus, tiles_buffer: &mut [TileData; 256]) {
+    // Reads every tile in VRAM, so tiles are indexed by their position there rather than by
+    // the index the tile maps use for them. The tiles of bank 1 follow those of bank 0, and
+    // are left alone on the DMG.
+    pub fn read_tiles(bus: &Bus, tiles_buffer: &mut [TileData; VRAM_TILE_COUNT * 2]) {
         let mut tile_buffer = [0u8; 16];
+        let bank_count = bus.device_type.get_vram_bank_count();
 
-        let base_addr = bus.video.get_tile_pattern_table_addr() as usize;
-
-        for i in 0..256 {
-            let addr = (base_addr + 16 * i) as u16;
-            bus.read_to_buffer(&mut tile_buffer, addr, 16);
-            tiles_buffer[i] = TileData(tile_buffer);
+        for (i, tile) in tiles_buffer
+            .iter_mut()
+            .take(VRAM_TILE_COUNT * bank_count)
+            .enumerate()
+        {
+            let addr = VRAM_START + 16 * (i % VRAM_TILE_COUNT) as u16;
+            Self::read_vram(bus, i / VRAM_TILE_COUNT, &mut tile_buffer, addr);
+            *tile = TileData(tile_buffer);
         }
     }
 
+    // The renderer reads VRAM directly, whichever bank the CPU has selected.
+    fn read_vram(bus: &Bus, bank: usize, buffer: &mut [u8], address: u16) {
+        let start = (address - VRAM_START) as usize;
+        buffer.copy_from_slice(&bus.video.get_vram_bank(bank)[start..start + buffer.len()]);
+    }
+
+    // Finds the VRAM tile a background or window map entry refers to. Objects always use
+    // unsigned indices from 0x8000, which are the same as VRAM tile numbers.
+    pub fn get_bg_tile_number(video: &VideoController, index: u8) -> usize {
+        let base = (video.get_tile_pattern_table_addr() - VRAM_START) as isize / 16;
+        let offset = if video.has_signed_tile_indices() {
+            index as i8 as isize
+        } else {
+            index as isize
+        };
+
+        (base + offset) as usize
+    }
+
     pub fn read_background_tile_indices(bus: &Bus, bg_buffer: &mut [u8; 1024]) {
-        bus.read_to_buffer(bg_buffer, bus.video.get_bg_tile_table_addr(), 1024);
+        Self::read_vram(bus, 0, bg_buffer, bus.video.get_bg_tile_table_addr());
     }
 
-    pub fn read_window_tile_indices(bus: &Bus, bg_buffer: &mut [u8; 1024]) {
-        bus.read_to_buffer(bg_buffer, bus.video.get_bg_tile_table_addr(), 1024);
+    // On the CGB, the attributes of each map entry are at the same address in VRAM bank 1.
+    pub fn read_background_tile_attributes(bus: &Bus, attribute_buffer: &mut [u8; 1024]) {
+        Self::read_vram(bus, 1, attribute_buffer, bus.video.get_bg_tile_table_addr());
+    }
+
+    pub fn read_window_tile_indices(bus: &Bus, window_buffer: &mut [u8; 1024]) {
+        Self::read_vram(
+            bus,
+            0,
+            window_buffer,
+            bus.video.get_window_tile_table_addr()
+        );
+    }
+
+    pub fn read_window_tile_attributes(bus: &Bus, attribute_buffer: &mut [u8; 1024]) {
+        Self::read_vram(
+            bus,
+            1,
+            attribute_buffer,
+            bus.video.get_window_tile_table_addr()
+        );
     }
 }
 
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SpriteAttributes {
     pub y: u8,
     pub x: u8,
@@ -152,6 +223,65 @@ impl SpriteAttributes {
             flags
         }
     }
+
+    // Only used on the CGB.
+    pub fn get_color_palette(&self) -> u8 {
+        self.flags & 0b111
+    }
+
+    // Only used on the CGB.
+    pub fn get_tile_bank(&self) -> usize {
+        self.flags.get_bit(3) as usize
+    }
+
+    pub fn uses_palette_1(&self) -> bool {
+        self.flags.get_bit(4)
+    }
+
+    pub fn is_x_flipped(&self) -> bool {
+        self.flags.get_bit(5)
+    }
+
+    pub fn is_y_flipped(&self) -> bool {
+        self.flags.get_bit(6)
+    }
+
+    // Such objects only show over background color 0.
+    pub fn is_behind_background(&self) -> bool {
+        self.flags.get_bit(7)
+    }
+}
+
+// The attributes of a background or window map entry, which are all zero on the DMG.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileAttributes(pub u8);
+
+impl TileAttributes {
+    pub fn get_color_palette(&self) -> u8 {
+        self.0 & 0b111
+    }
+
+    pub fn get_tile_bank(&self) -> usize {
+        self.0.get_bit(3) as usize
+    }
+
+    pub fn is_x_flipped(&self) -> bool {
+        self.0.get_bit(5)
+    }
+
+    pub fn is_y_flipped(&self) -> bool {
+        self.0.get_bit(6)
+    }
+
+    // Such tiles are drawn over objects, unless they're color 0.
+    pub fn has_priority(&self) -> bool {
+        self.0.get_bit(7)
+    }
 }
 
+pub mod framebuffer_renderer;
+pub mod png;
+pub mod scanline;
+#[cfg(feature = "sdl")]
 pub mod sdl_renderer;
+pub mod sprites;
@@ -0,0 +1,232 @@
+// A self-contained PNG encoder, so screenshots don't depend on SDL_image. Image data is
+// compressed with a small LZ77 + fixed Huffman deflate implementation, which is plenty for the
+// large flat areas typical of Game Boy graphics.
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const COLOR_TYPE_RGBA: u8 = 6;
+
+const WINDOW_SIZE: usize = 32768;
+const HASH_BITS: u32 = 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13
+];
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+pub fn adler32(bytes: &[u8]) -> u32 {
+    let mut a = 1u32;
+    let mut b = 0u32;
+
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
+
+// Deflate packs bits starting from the least significant bit of each byte.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bit_count: u32
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter {
+            bytes: Vec::new(),
+            buffer: 0,
+            bit_count: 0
+        }
+    }
+
+    fn write_bits(&mut self, value: u32, count: u32) {
+        self.buffer |= value << self.bit_count;
+        self.bit_count += count;
+
+        while self.bit_count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    // Huffman codes are stored most significant bit first, unlike everything else.
+    fn write_code(&mut self, code: u32, length: u32) {
+        let reversed = code.reverse_bits() >> (32 - length);
+        self.write_bits(reversed, length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+fn write_literal_length_code(writer: &mut BitWriter, symbol: u16) {
+    let symbol = symbol as u32;
+    match symbol {
+        0..=143 => writer.write_code(0b0011_0000 + symbol, 8),
+        144..=255 => writer.write_code(0b1_1001_0000 + symbol - 144, 9),
+        256..=279 => writer.write_code(symbol - 256, 7),
+        _ => writer.write_code(0b1100_0000 + symbol - 280, 8)
+    }
+}
+
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    let length_index = LENGTH_BASES
+        .iter()
+        .rposition(|&base| base as usize <= length)
+        .unwrap();
+    write_literal_length_code(writer, 257 + length_index as u16);
+    writer.write_bits(
+        (length - LENGTH_BASES[length_index] as usize) as u32,
+        LENGTH_EXTRA_BITS[length_index] as u32
+    );
+
+    let distance_index = DISTANCE_BASES
+        .iter()
+        .rposition(|&base| base as usize <= distance)
+        .unwrap();
+    writer.write_code(distance_index as u32, 5);
+    writer.write_bits(
+        (distance - DISTANCE_BASES[distance_index] as usize) as u32,
+        DISTANCE_EXTRA_BITS[distance_index] as u32
+    );
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+    (value.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+// Greedy LZ77 with a single candidate per hash bucket, emitted as one fixed Huffman block.
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman codes)
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    let mut last_positions = vec![usize::MAX; 1 << HASH_BITS];
+    let mut position = 0;
+
+    while position < data.len() {
+        let mut match_length = 0;
+        let mut match_distance = 0;
+
+        if position + MIN_MATCH <= data.len() {
+            let bucket = hash(&data[position..]);
+            let candidate = last_positions[bucket];
+            last_positions[bucket] = position;
+
+            if candidate != usize::MAX && position - candidate <= WINDOW_SIZE {
+                let max_length = MAX_MATCH.min(data.len() - position);
+                while match_length < max_length
+                    && data[candidate + match_length] == data[position + match_length]
+                {
+                    match_length += 1;
+                }
+                match_distance = position - candidate;
+            }
+        }
+
+        if match_length >= MIN_MATCH {
+            write_match(&mut writer, match_length, match_distance);
+
+            for skipped in position + 1..position + match_length {
+                if skipped + MIN_MATCH <= data.len() {
+                    last_positions[hash(&data[skipped..])] = skipped;
+                }
+            }
+
+            position += match_length;
+        } else {
+            write_literal_length_code(&mut writer, data[position] as u16);
+            position += 1;
+        }
+    }
+
+    write_literal_length_code(&mut writer, 256);
+    writer.finish()
+}
+
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    // 32K window, no preset dictionary, default compression level.
+    let mut output = vec![0x78, 0x9C];
+    output.extend_from_slice(&deflate(data));
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+    output
+}
+
+fn write_chunk(output: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    let start = output.len();
+    output.extend_from_slice(chunk_type);
+    output.extend_from_slice(data);
+    let crc = crc32(&output[start..]);
+
+    output.extend_from_slice(&crc.to_be_bytes());
+}
+
+// Encodes 8-bit RGBA pixels, four bytes per pixel row by row, as a PNG file.
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    let row_size = width as usize * 4;
+    assert_eq!(
+        row_size * height as usize,
+        rgba.len(),
+        "Pixel data doesn't match the image size"
+    );
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth, color type, compression, filter and interlace methods.
+    header.extend_from_slice(&[8, COLOR_TYPE_RGBA, 0, 0, 0]);
+
+    // Every row starts with its filter type, which is always 0 (none) here.
+    let mut image_data = Vec::with_capacity((row_size + 1) * height as usize);
+    for row in rgba.chunks(row_size) {
+        image_data.push(0);
+        image_data.extend_from_slice(row);
+    }
+
+    let mut output = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut output, b"IHDR", &header);
+    write_chunk(&mut output, b"IDAT", &zlib_compress(&image_data));
+    write_chunk(&mut output, b"IEND", &[]);
+    output
+}
@@ -0,0 +1,258 @@
+use crate::emulation::constants::*;
+use crate::emulation::device::Device;
+use crate::emulation::video::controller::VideoController;
+use crate::rendering::sprites;
+use crate::rendering::*;
+
+// The video memory a frame is drawn from, decoded once per frame, and the scanline rasterizer
+// shared by all renderers. DMG scanlines are produced as 2-bit shades after palette mapping,
+// where 0 is the lightest, and CGB scanlines as 15-bit colors.
+pub struct ScanlineRasterizer {
+    pub tile_cache: Box<[[u8; TILE_SIZE * TILE_SIZE]; VRAM_TILE_COUNT * 2]>,
+    pub sprites: [SpriteAttributes; 40],
+    pub tile_patterns: [TileData; VRAM_TILE_COUNT * 2],
+    pub background_tiles: [u8; 32 * 32],
+    pub window_tiles: [u8; 32 * 32],
+    pub background_attributes: [u8; 32 * 32],
+    pub window_attributes: [u8; 32 * 32]
+}
+
+impl ScanlineRasterizer {
+    pub fn new() -> ScanlineRasterizer {
+        ScanlineRasterizer {
+            tile_cache: Box::new([[0; TILE_SIZE * TILE_SIZE]; VRAM_TILE_COUNT * 2]),
+            sprites: [SpriteAttributes::default(); 40],
+            tile_patterns: [TileData::default(); VRAM_TILE_COUNT * 2],
+            background_tiles: [0u8; 32 * 32],
+            window_tiles: [0u8; 32 * 32],
+            background_attributes: [0u8; 32 * 32],
+            window_attributes: [0u8; 32 * 32]
+        }
+    }
+
+    pub fn get_window_tile(&self, x: u8, y: u8) -> u8 {
+        self.window_tiles[(y as usize) * 32 + (x as usize)]
+    }
+
+    pub fn get_background_tile(&self, x: u8, y: u8) -> u8 {
+        self.background_tiles[(y as usize) * 32 + (x as usize)]
+    }
+
+    fn refresh_tile_cache(&mut self) {
+        for (tile, cached) in self.tile_patterns.iter().zip(self.tile_cache.iter_mut()) {
+            tile.unpack_to(cached);
+        }
+    }
+
+    pub fn refresh(&mut self, device: &Device) {
+        let bus = &device.bus;
+        CommonRenderer::read_background_tile_indices(bus, &mut self.background_tiles);
+        CommonRenderer::read_window_tile_indices(bus, &mut self.window_tiles);
+        CommonRenderer::read_sprites(bus, &mut self.sprites);
+        CommonRenderer::read_tiles(bus, &mut self.tile_patterns);
+        self.refresh_tile_cache();
+
+        if bus.video.is_color() {
+            CommonRenderer::read_background_tile_attributes(bus, &mut self.background_attributes);
+            CommonRenderer::read_window_tile_attributes(bus, &mut self.window_attributes);
+        }
+    }
+
+    // Looks up a pixel of a map entry, where `x` and `y` are relative to the tile.
+    fn get_tile_pixel(
+        &self,
+        video: &VideoController,
+        index: u8,
+        attributes: TileAttributes,
+        x: usize,
+        y: usize
+    ) -> u8 {
+        let tile_number = CommonRenderer::get_bg_tile_number(video, index)
+            + attributes.get_tile_bank() * VRAM_TILE_COUNT;
+        let x = if attributes.is_x_flipped() {
+            TILE_SIZE - 1 - x
+        } else {
+            x
+        };
+        let y = if attributes.is_y_flipped() {
+            TILE_SIZE - 1 - y
+        } else {
+            y
+        };
+
+        self.tile_cache[tile_number][y * TILE_SIZE + x]
+    }
+
+    // The window covers everything right of WX - 7, and shows the lines of its map in order
+    // regardless of which screen line it's drawn on.
+    fn draw_window_line(
+        &self,
+        video: &VideoController,
+        window_line: u8,
+        colors: &mut [u8],
+        attributes: &mut [TileAttributes]
+    ) {
+        let y = window_line as usize;
+        let start_x = (video.window_x as usize).saturating_sub(7);
+
+        for x in start_x..colors.len() {
+            let window_x = x + 7 - video.window_x as usize;
+            let map_index = (y / TILE_SIZE) * 32 + window_x / TILE_SIZE;
+            let tile_attributes = TileAttributes(self.window_attributes[map_index]);
+            let tile_index = self.window_tiles[map_index];
+
+            colors[x] = self.get_tile_pixel(
+                video,
+                tile_index,
+                tile_attributes,
+                window_x % TILE_SIZE,
+                y % TILE_SIZE
+            );
+            attributes[x] = tile_attributes;
+        }
+    }
+
+    fn draw_background_line(
+        &self,
+        video: &VideoController,
+        scanline: u8,
+        colors: &mut [u8],
+        attributes: &mut [TileAttributes]
+    ) {
+        let scroll_x = video.scroll_x as usize;
+        let scroll_y = video.scroll_y as usize;
+        let y = (scanline as usize + scroll_y) % 256;
+
+        for x in 0..colors.len() {
+            let map_x = (x + scroll_x) % 256;
+            let map_index = (y / TILE_SIZE) * 32 + map_x / TILE_SIZE;
+            let tile_attributes = TileAttributes(self.background_attributes[map_index]);
+            let tile_index = self.background_tiles[map_index];
+
+            colors[x] = self.get_tile_pixel(
+                video,
+                tile_index,
+                tile_attributes,
+                map_x % TILE_SIZE,
+                y % TILE_SIZE
+            );
+            attributes[x] = tile_attributes;
+        }
+    }
+
+    fn draw_bg_and_window_line(
+        &self,
+        video: &VideoController,
+        scanline: u8,
+        colors: &mut [u8],
+        attributes: &mut [TileAttributes]
+    ) {
+        self.draw_background_line(video, scanline, colors, attributes);
+
+        if let Some(window_line) = video.get_window_line() {
+            self.draw_window_line(video, window_line, colors, attributes);
+        }
+    }
+
+    fn draw_sprite_pixels(
+        &self,
+        video: &VideoController,
+        scanline: u8,
+        mode: sprites::SpriteMode,
+        pixels: &mut [Option<sprites::SpritePixel>]
+    ) {
+        let height = video.get_sprite_height();
+        let line_sprites = sprites::select_line_sprites(&self.sprites, scanline, height, mode);
+        sprites::draw_sprite_line(
+            &line_sprites,
+            &self.tile_cache[..],
+            scanline,
+            height,
+            mode,
+            pixels
+        );
+    }
+
+    pub fn draw_scanline(&self, device: &Device, scanline: u8, shades: &mut [u8]) {
+        let video = &device.bus.video;
+        let mut colors = [0u8; SCREEN_WIDTH as usize];
+        let mut attributes = [TileAttributes::default(); SCREEN_WIDTH as usize];
+
+        // On the DMG, the window is turned off along with the background.
+        if video.is_bg_enabled() {
+            self.draw_bg_and_window_line(video, scanline, &mut colors, &mut attributes);
+        }
+
+        for (shade, &color) in shades.iter_mut().zip(colors.iter()) {
+            *shade = video.background_palette.get_shade(color);
+        }
+
+        if video.are_sprites_enabled() {
+            let mut sprite_pixels = [None; SCREEN_WIDTH as usize];
+            self.draw_sprite_pixels(
+                video,
+                scanline,
+                sprites::SpriteMode::Dmg,
+                &mut sprite_pixels
+            );
+
+            for (x, pixel) in sprite_pixels.iter().enumerate() {
+                let pixel = match pixel {
+                    Some(pixel) if pixel.is_visible_over(colors[x]) => pixel,
+                    _ => continue
+                };
+
+                let palette = if pixel.sprite.uses_palette_1() {
+                    video.sprite_palette_1
+                } else {
+                    video.sprite_palette_0
+                };
+
+                shades[x] = palette.get_shade(pixel.color);
+            }
+        }
+    }
+
+    // Background color 0 is always below objects. Otherwise, the background is drawn on top
+    // if either its map entry or the object asks for it, unless LCDC bit 0 is cleared.
+    pub fn draw_color_scanline(&self, device: &Device, scanline: u8, output: &mut [u16]) {
+        let video = &device.bus.video;
+        let mut colors = [0u8; SCREEN_WIDTH as usize];
+        let mut attributes = [TileAttributes::default(); SCREEN_WIDTH as usize];
+
+        self.draw_bg_and_window_line(video, scanline, &mut colors, &mut attributes);
+
+        for (x, output) in output.iter_mut().enumerate() {
+            *output = video
+                .background_color_palettes
+                .get_color(attributes[x].get_color_palette(), colors[x]);
+        }
+
+        if video.are_sprites_enabled() {
+            let mut sprite_pixels = [None; SCREEN_WIDTH as usize];
+            self.draw_sprite_pixels(
+                video,
+                scanline,
+                sprites::SpriteMode::Cgb,
+                &mut sprite_pixels
+            );
+
+            for (x, pixel) in sprite_pixels.iter().enumerate() {
+                let pixel = match pixel {
+                    Some(pixel) => pixel,
+                    None => continue
+                };
+
+                let is_bg_on_top = video.has_bg_master_priority()
+                    && colors[x] != 0
+                    && (attributes[x].has_priority() || pixel.sprite.is_behind_background());
+
+                if !is_bg_on_top {
+                    output[x] = video
+                        .sprite_color_palettes
+                        .get_color(pixel.sprite.get_color_palette(), pixel.color);
+                }
+            }
+        }
+    }
+}
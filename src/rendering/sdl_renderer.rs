@@ -1,4 +1,6 @@
-use crate::emulation::constants::{SCREEN_HEIGHT, SCREEN_WIDTH, TILE_SIZE};
+use crate::emulation::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::emulation::device::Device;
+use crate::rendering::scanline::ScanlineRasterizer;
 use crate::rendering::*;
 
 use sdl2;
@@ -56,95 +58,30 @@ impl RendererPalette<SdlColor> for SdlPalette {
 pub struct SdlRendererState<'a> {
     pub background_buffer: SdlSurface<'a>,
     pub window_buffer: SdlSurface<'a>,
-    pub background_palette: GbPalette,
-    pub sprite_palette_0: GbPalette,
-    pub sprite_palette_1: GbPalette,
-    pub tile_cache: Box<[[u8; TILE_SIZE * TILE_SIZE]; 16 * 16]>,
-    pub sprites: [SpriteAttributes; 40],
-    pub tile_patterns: [TileData; 256],
-    pub background_tiles: [u8; 32 * 32],
-    pub window_tiles: [u8; 32 * 32]
+    pub rasterizer: ScanlineRasterizer
 }
 
 impl<'a> SdlRendererState<'a> {
     pub fn new() -> SdlRendererState<'a> {
         let background_buffer = Surface::new(256, 256, PixelFormatEnum::RGB24).unwrap();
         let window_buffer = Surface::new(256, 256, PixelFormatEnum::RGB24).unwrap();
-        let background_palette = GbPalette(0x00);
-        let sprite_palette_0 = GbPalette(0x00);
-        let sprite_palette_1 = GbPalette(0x00);
-        let tile_cache = Box::new([[0; TILE_SIZE * TILE_SIZE]; 16 * 16]);
-        let sprites = [SpriteAttributes::default(); 40];
-        let tile_patterns = [TileData::default(); 256];
-        let background_tiles = [0u8; 32 * 32];
-        let window_tiles = [0u8; 32 * 32];
+        let rasterizer = ScanlineRasterizer::new();
 
         SdlRendererState {
             background_buffer,
             window_buffer,
-            background_palette,
-            sprite_palette_0,
-            sprite_palette_1,
-            tile_cache,
-            sprites,
-            tile_patterns,
-            background_tiles,
-            window_tiles
+            rasterizer
         }
     }
 
-    pub fn get_window_tile(&self, x: u8, y: u8) -> u8 {
-        self.window_tiles[(y as usize) * 32 + (x as usize)]
-    }
-
-    pub fn get_background_tile(&self, x: u8, y: u8) -> u8 {
-        self.background_tiles[(y as usize) * 32 + (x as usize)]
-    }
-
     pub fn get_tile_rect(&self, tile_id: u8) -> Rect {
         let row = (tile_id / 16) as i32;
         let column = (tile_id % 16) as i32;
         Rect::new(column * 8, row * 8, 8, 8)
     }
 
-    fn refresh_tile_maps(&mut self, device: &Device) {
-        CommonRenderer::read_background_tile_indices(&device.bus, &mut self.background_tiles);
-        CommonRenderer::read_window_tile_indices(&device.bus, &mut self.window_tiles);
-    }
-
-    fn refresh_tile_patterns(&mut self, device: &Device) {
-        CommonRenderer::read_tiles(&device.bus, &mut self.tile_patterns);
-    }
-
-    fn refresh_sprites(&mut self, device: &Device) {
-        CommonRenderer::read_sprites(&device.bus, &mut self.sprites);
-    }
-
-    fn refresh_palettes(&mut self, device: &Device) {
-        self.background_palette = device.bus.video.background_palette;
-        self.sprite_palette_0 = device.bus.video.sprite_palette_0;
-        self.sprite_palette_1 = device.bus.video.sprite_palette_1;
-    }
-
-    fn refresh_tile_cache(&mut self) {
-        for y in 0..16 {
-            for x in 0..16 {
-                let tile = self.tile_patterns[y * 16 + x];
-                let offset = (y * 16) + x;
-                tile.unpack_to(&mut self.tile_cache[offset]);
-            }
-        }
-    }
-
     pub fn refresh(&mut self, device: &Device) {
-        self.refresh_tile_maps(device);
-        self.refresh_sprites(device);
-        self.refresh_tile_patterns(device);
-        self.refresh_tile_cache();
-    }
-
-    pub fn refresh_scanline(&mut self, device: &Device) {
-        self.refresh_palettes(device);
+        self.rasterizer.refresh(device);
     }
 }
 
@@ -205,6 +142,21 @@ impl<'a> SdlRenderer<'a> {
             debug_data
         }
     }
+
+    // The most recently drawn frame as RGBA, four bytes per pixel.
+    pub fn get_frame_rgba(&self) -> Vec<u8> {
+        let pitch = self.screen_buffer_cpu.pitch() as usize;
+        let pixels = self.screen_buffer_cpu.without_lock().unwrap();
+        let mut rgba = Vec::with_capacity((SCREEN_WIDTH * SCREEN_HEIGHT * 4) as usize);
+
+        for row in pixels.chunks(pitch).take(SCREEN_HEIGHT as usize) {
+            for pixel in row[..SCREEN_WIDTH as usize * 3].chunks(3) {
+                rgba.extend_from_slice(&[pixel[0], pixel[1], pixel[2], 255]);
+            }
+        }
+
+        rgba
+    }
 }
 
 impl<'a> Renderer for SdlRenderer<'a> {
@@ -263,97 +215,36 @@ impl<'a> Renderer for SdlRenderer<'a> {
     }
 
     fn draw_scanline(&mut self, device: &Device, scanline: u8) {
-        self.state.refresh_scanline(device);
-
-        let scanline = scanline as i32;
-        let scroll_x = device.bus.video.scroll_x as i32;
-        let scroll_y = device.bus.video.scroll_y as i32;
-
-        let window_x = device.bus.video.window_x as i32;
-        let window_y = device.bus.video.window_y as i32;
+        let mut colors = [Color::RGB(0, 0, 0); SCREEN_WIDTH as usize];
 
-        let bg_enabled = device.bus.video.is_bg_enabled();
-        let sprites_enabled = device.bus.video.are_sprites_enabled();
+        if device.bus.video.is_color() {
+            let mut color_line = [0u16; SCREEN_WIDTH as usize];
+            self.state
+                .rasterizer
+                .draw_color_scanline(device, scanline, &mut color_line);
 
-        let base_offset = (SCREEN_WIDTH * 3) as usize * scanline as usize;
-        let pixels = self.screen_buffer_cpu.as_mut().without_lock_mut().unwrap();
-
-        let scanline = scanline as usize;
-
-        for x in 0..SCREEN_WIDTH as usize {
-            if bg_enabled {
-                let tile_x = ((x + scroll_x as usize) / TILE_SIZE) as u8;
-                let tile_y = ((scanline + scroll_y as usize) / TILE_SIZE) as u8;
-
-                let start_of_tile_x = (tile_x as usize) * TILE_SIZE;
-                let start_of_tile_y = (tile_y as usize) * TILE_SIZE;
-
-                let tile_rel_x = x - start_of_tile_x;
-                let tile_rel_y = scanline - start_of_tile_y;
-
-                let tile_index = self.state.get_background_tile(tile_x, tile_y);
-                let tile_data = &self.state.tile_cache[tile_index as usize];
-
-                let tile_pixel_data = tile_data[tile_rel_y * TILE_SIZE + tile_rel_x];
-                let (r, g, b, _) = device
-                    .bus
-                    .video
-                    .background_palette
-                    .get_color(tile_pixel_data, false);
-
-                let screen_offset = base_offset + x * 3;
-                pixels[screen_offset + 0] = r;
-                pixels[screen_offset + 1] = g;
-                pixels[screen_offset + 2] = b;
+            for (color, &line_color) in colors.iter_mut().zip(color_line.iter()) {
+                *color = SdlColor::from_rgb555(line_color);
+            }
+        } else {
+            let mut shades = [0u8; SCREEN_WIDTH as usize];
+            self.state
+                .rasterizer
+                .draw_scanline(device, scanline, &mut shades);
+
+            for (color, &shade) in colors.iter_mut().zip(shades.iter()) {
+                *color = SdlColor::from_rgb_tuple(SHADE_COLORS[shade as usize]);
             }
         }
 
-        // TODO: Render window
-
-        if sprites_enabled {
-            let width = TILE_SIZE as usize;
-            let height = device.bus.video.get_sprite_height() as usize;
-
-            let mut rendered_sprites = 0;
-
-            for sprite in self.state.sprites.iter() {
-                if sprite.x == 0 {
-                    continue;
-                }
-
-                let sprite_y = sprite.y as usize - 16;
-
-                if scanline >= sprite_y && scanline < sprite_y + height {
-                    rendered_sprites += 1;
-
-                    let sprite_x = sprite.x as usize - width;
-                    let sprite_rel_y = scanline - sprite_y;
-
-                    let tile_data = &self.state.tile_cache[sprite.pattern as usize];
-
-                    let palette = if sprite.flags & 0b0001_0000 != 0 {
-                        self.state.sprite_palette_0
-                    } else {
-                        self.state.sprite_palette_1
-                    };
-
-                    for x in 0..TILE_SIZE {
-                        let tile_pixel_data = tile_data[sprite_rel_y * TILE_SIZE + x];
-
-                        let (r, g, b, should_render) = palette.get_color(tile_pixel_data, true);
-
-                        if !should_render {
-                            continue;
-                        }
-
-                        let screen_offset = base_offset + (sprite_x + x) * 3;
+        let base_offset = (SCREEN_WIDTH * 3) as usize * scanline as usize;
+        let pixels = self.screen_buffer_cpu.as_mut().without_lock_mut().unwrap();
 
-                        pixels[screen_offset + 0] = r;
-                        pixels[screen_offset + 1] = g;
-                        pixels[screen_offset + 2] = b;
-                    }
-                }
-            }
+        for (x, color) in colors.iter().enumerate() {
+            let screen_offset = base_offset + x * 3;
+            pixels[screen_offset] = color.r;
+            pixels[screen_offset + 1] = color.g;
+            pixels[screen_offset + 2] = color.b;
         }
     }
 
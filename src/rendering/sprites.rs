@@ -0,0 +1,116 @@
+use crate::emulation::constants::*;
+use crate::emulation::video::pixel_fifo::MAX_SPRITES_PER_LINE;
+use crate::rendering::SpriteAttributes;
+
+// The CGB draws objects in OAM order and can take their tiles from either VRAM bank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpriteMode {
+    Dmg,
+    Cgb
+}
+
+// An object pixel that won out over the other objects at its position. Whether it's actually
+// visible depends on the background under it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpritePixel {
+    pub color: u8,
+    pub sprite: SpriteAttributes
+}
+
+impl SpritePixel {
+    pub fn is_visible_over(&self, background_color: u8) -> bool {
+        !self.sprite.is_behind_background() || background_color == 0
+    }
+}
+
+fn overlaps_line(sprite: &SpriteAttributes, line: u8, height: u8) -> bool {
+    let line = line as u16 + 16;
+    let y = sprite.y as u16;
+    line >= y && line < y + height as u16
+}
+
+// Only the first ten objects in OAM that overlap a line are drawn on it, including ones that
+// are off screen horizontally. Where objects overlap on the DMG, the one with the smaller X
+// coordinate is drawn on top, and OAM order breaks ties, so they are returned in that order.
+pub fn select_line_sprites(
+    sprites: &[SpriteAttributes],
+    line: u8,
+    height: u8,
+    mode: SpriteMode
+) -> Vec<SpriteAttributes> {
+    let mut line_sprites: Vec<SpriteAttributes> = sprites
+        .iter()
+        .filter(|sprite| overlaps_line(sprite, line, height))
+        .take(MAX_SPRITES_PER_LINE)
+        .cloned()
+        .collect();
+
+    if mode == SpriteMode::Dmg {
+        line_sprites.sort_by_key(|sprite| sprite.x);
+    }
+
+    line_sprites
+}
+
+// Draws the objects of a line into `pixels`, which is indexed by screen X. Pixels of color 0
+// are transparent, so objects further back show through them, but an object that is hidden
+// behind the background still hides the objects below it. The tiles of VRAM bank 1 follow
+// those of bank 0 in `tile_cache`.
+pub fn draw_sprite_line(
+    line_sprites: &[SpriteAttributes],
+    tile_cache: &[[u8; TILE_SIZE * TILE_SIZE]],
+    line: u8,
+    height: u8,
+    mode: SpriteMode,
+    pixels: &mut [Option<SpritePixel>]
+) {
+    for pixel in pixels.iter_mut() {
+        *pixel = None;
+    }
+
+    for sprite in line_sprites {
+        let mut row = (line as usize + 16) - sprite.y as usize;
+        if sprite.is_y_flipped() {
+            row = height as usize - 1 - row;
+        }
+
+        // Tall objects span two consecutive tiles, starting from an even one.
+        let pattern = if height == 16 {
+            sprite.pattern & 0xFE
+        } else {
+            sprite.pattern
+        };
+        let bank_offset = match mode {
+            SpriteMode::Dmg => 0,
+            SpriteMode::Cgb => sprite.get_tile_bank() * VRAM_TILE_COUNT
+        };
+        let tile_data = &tile_cache[bank_offset + pattern as usize + row / TILE_SIZE];
+        let row = row % TILE_SIZE;
+
+        for column in 0..TILE_SIZE {
+            let screen_x = sprite.x as i32 - TILE_SIZE as i32 + column as i32;
+            if screen_x < 0 || screen_x >= pixels.len() as i32 {
+                continue;
+            }
+
+            let pixel = &mut pixels[screen_x as usize];
+            if pixel.is_some() {
+                continue;
+            }
+
+            let tile_x = if sprite.is_x_flipped() {
+                TILE_SIZE - 1 - column
+            } else {
+                column
+            };
+
+            let color = tile_data[row * TILE_SIZE + tile_x];
+            if color != 0 {
+                *pixel = Some(SpritePixel {
+                    color,
+                    sprite: *sprite
+                });
+            }
+        }
+    }
+}
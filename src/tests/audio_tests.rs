@@ -0,0 +1,122 @@
+use crate::emulation::address_mapper::AddressMapper;
+use crate::emulation::audio::controller::AudioController;
+
+fn read(audio: &AudioController, address: u16) -> u8 {
+    audio.read_8(audio.resolve_address(address))
+}
+
+fn write(audio: &mut AudioController, address: u16, value: u8) {
+    let location = audio.resolve_address(address);
+    audio.write_8(location, value);
+}
+
+fn create_powered_audio() -> AudioController {
+    let mut audio = AudioController::new();
+    write(&mut audio, 0xFF26, 0x80);
+    audio
+}
+
+#[test]
+fn register_read_masks() {
+    let mut audio = create_powered_audio();
+
+    for address in 0xFF10..=0xFF25 {
+        write(&mut audio, address, 0x00);
+    }
+
+    let expected = [
+        0x80, 0x3F, 0x00, 0xFF, 0xBF, 0xFF, 0x3F, 0x00, 0xFF, 0xBF, 0x7F, 0xFF, 0x9F, 0xFF, 0xBF,
+        0xFF, 0xFF, 0x00, 0x00, 0xBF, 0x00, 0x00
+    ];
+
+    for (i, &value) in expected.iter().enumerate() {
+        assert_eq!(value, read(&audio, 0xFF10 + i as u16), "NR register {}", i);
+    }
+
+    assert_eq!(0xF0, read(&audio, 0xFF26));
+
+    for address in 0xFF27..=0xFF2F {
+        assert_eq!(0xFF, read(&audio, address));
+    }
+}
+
+#[test]
+fn power_off_clears_registers_and_ignores_writes() {
+    let mut audio = create_powered_audio();
+
+    write(&mut audio, 0xFF24, 0x77);
+    write(&mut audio, 0xFF30, 0x12);
+    write(&mut audio, 0xFF26, 0x00);
+
+    assert_eq!(0x00, read(&audio, 0xFF24));
+    assert_eq!(0x70, read(&audio, 0xFF26));
+
+    write(&mut audio, 0xFF24, 0x77);
+    assert_eq!(0x00, read(&audio, 0xFF24));
+
+    // Wave RAM survives and stays accessible while the APU is off.
+    assert_eq!(0x12, read(&audio, 0xFF30));
+    write(&mut audio, 0xFF31, 0x34);
+    assert_eq!(0x34, read(&audio, 0xFF31));
+}
+
+#[test]
+fn trigger_requires_dac() {
+    let mut audio = create_powered_audio();
+
+    write(&mut audio, 0xFF17, 0x00);
+    write(&mut audio, 0xFF19, 0x80);
+    assert_eq!(0, read(&audio, 0xFF26) & 0b10);
+
+    write(&mut audio, 0xFF17, 0xF0);
+    write(&mut audio, 0xFF19, 0x80);
+    assert_eq!(0b10, read(&audio, 0xFF26) & 0b10);
+
+    // Turning the DAC off disables the channel.
+    write(&mut audio, 0xFF17, 0x00);
+    assert_eq!(0, read(&audio, 0xFF26) & 0b10);
+}
+
+#[test]
+fn length_counter_disables_channel() {
+    let mut audio = create_powered_audio();
+
+    write(&mut audio, 0xFF17, 0xF0);
+    // Length of 62, so two length clocks are needed.
+    write(&mut audio, 0xFF16, 62);
+    write(&mut audio, 0xFF19, 0xC0);
+    assert_eq!(0b10, read(&audio, 0xFF26) & 0b10);
+
+    // Length is clocked on every other frame sequencer step.
+    audio.update(8192 * 2, None);
+    assert_eq!(0b10, read(&audio, 0xFF26) & 0b10);
+
+    audio.update(8192 * 2, None);
+    assert_eq!(0, read(&audio, 0xFF26) & 0b10);
+}
+
+#[test]
+fn sweep_overflow_disables_channel_on_trigger() {
+    let mut audio = create_powered_audio();
+
+    write(&mut audio, 0xFF12, 0xF0);
+    write(&mut audio, 0xFF10, 0x11);
+    write(&mut audio, 0xFF13, 0xFF);
+    write(&mut audio, 0xFF14, 0x87);
+
+    assert_eq!(0, read(&audio, 0xFF26) & 0b1);
+}
+
+#[test]
+fn mixer_respects_panning() {
+    let mut audio = create_powered_audio();
+
+    write(&mut audio, 0xFF24, 0x77);
+    write(&mut audio, 0xFF25, 0x80);
+    write(&mut audio, 0xFF21, 0xF0);
+    write(&mut audio, 0xFF23, 0x80);
+
+    let output = audio.get_output();
+    assert_eq!(0.0, output.right);
+    assert!(output.left != 0.0);
+}
@@ -0,0 +1,80 @@
+use crate::emulation::cartridge::Cartridge;
+use crate::emulation::constants::*;
+use crate::emulation::rtc::RTC_SAVE_SIZE;
+
+fn create_cartridge(cartridge_type_id: u8, ram_size_id: u8) -> Cartridge {
+    let mut rom = vec![0u8; 0x8000];
+    rom[0x147] = cartridge_type_id;
+    rom[0x148] = 0x00;
+    rom[0x149] = ram_size_id;
+    Cartridge::from_bytes(&rom).unwrap()
+}
+
+#[test]
+fn ram_round_trip() {
+    // MBC5+RAM+BATTERY, 32 KB RAM
+    let mut cartridge = create_cartridge(0x1B, 0x03);
+    cartridge.write_8(0x0000, 0x0A);
+    cartridge.write_8(0x4000, 0x02);
+    cartridge.write_8(0xA010, 0x77);
+
+    let save_data = cartridge.save_battery_data();
+    assert_eq!(4 * CARTRIDGE_RAM_BANK_SIZE, save_data.len());
+
+    let mut loaded = create_cartridge(0x1B, 0x03);
+    loaded.load_battery_data(&save_data).unwrap();
+    assert_eq!(0x77, loaded.memory.ram[2 * CARTRIDGE_RAM_BANK_SIZE + 0x10]);
+}
+
+#[test]
+fn rtc_footer_round_trip() {
+    // MBC3+TIMER+RAM+BATTERY, 8 KB RAM
+    let mut cartridge = create_cartridge(0x10, 0x02);
+    cartridge.write_8(0x0000, 0x0A);
+    cartridge.write_8(0x4000, 0x0C);
+    cartridge.write_8(0xA000, 0b0100_0001);
+    cartridge.write_8(0x4000, 0x0A);
+    cartridge.write_8(0xA000, 17);
+
+    let save_data = cartridge.save_battery_data();
+    assert_eq!(CARTRIDGE_RAM_BANK_SIZE + RTC_SAVE_SIZE, save_data.len());
+
+    let mut loaded = create_cartridge(0x10, 0x02);
+    loaded.load_battery_data(&save_data).unwrap();
+
+    let rtc = loaded.mapper.get_rtc().unwrap();
+    assert_eq!(17, rtc.live.hours);
+    assert_eq!(256, rtc.live.get_days());
+    assert!(rtc.live.is_halted());
+}
+
+#[test]
+fn rtc_footer_with_32_bit_timestamp() {
+    let mut save_data = vec![0u8; CARTRIDGE_RAM_BANK_SIZE + 44];
+    save_data[CARTRIDGE_RAM_BANK_SIZE + 8] = 5;
+    // Halted, so the 1970 timestamp doesn't advance the clock on load
+    save_data[CARTRIDGE_RAM_BANK_SIZE + 16] = 0b0100_0000;
+
+    let mut cartridge = create_cartridge(0x10, 0x02);
+    cartridge.load_battery_data(&save_data).unwrap();
+    assert_eq!(5, cartridge.mapper.get_rtc().unwrap().live.hours);
+}
+
+#[test]
+fn truncated_save_is_rejected() {
+    let mut cartridge = create_cartridge(0x1B, 0x03);
+    assert!(cartridge.load_battery_data(&[0u8; 16]).is_err());
+}
+
+#[test]
+fn bad_rtc_footer_leaves_ram_untouched() {
+    let mut cartridge = create_cartridge(0x10, 0x02);
+    cartridge.memory.ram[0x10] = 0x77;
+
+    // Seven bytes is too short to be a clock footer.
+    let save_data = vec![0x11u8; CARTRIDGE_RAM_BANK_SIZE + 7];
+    assert!(cartridge.load_battery_data(&save_data).is_err());
+
+    assert_eq!(0x77, cartridge.memory.ram[0x10]);
+    assert_eq!(0, cartridge.mapper.get_rtc().unwrap().live.hours);
+}
@@ -35,3 +35,21 @@ fn load_headers() {
         CartridgeHeader::parse(&data);
     }
 }
+
+#[test]
+fn color_support_flag() {
+    let mut header = vec![0u8; 0x150];
+    header[0x134..0x138].copy_from_slice(b"TEST");
+
+    let parsed = CartridgeHeader::parse(&header);
+    assert!(!parsed.supports_gbc);
+    assert_eq!(Some("TEST".to_string()), parsed.title);
+
+    // The flag is part of the title on older cartridges, and isn't included in it otherwise.
+    for &flag in &[0x80, 0xC0] {
+        header[0x143] = flag;
+        let parsed = CartridgeHeader::parse(&header);
+        assert!(parsed.supports_gbc);
+        assert_eq!(Some("TEST".to_string()), parsed.title);
+    }
+}
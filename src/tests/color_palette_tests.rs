@@ -0,0 +1,70 @@
+use crate::emulation::device::DeviceType;
+use crate::emulation::internal_message::InternalMessage;
+use crate::emulation::video::color_palette::ColorPaletteRam;
+use crate::emulation::video::controller::VideoController;
+use crate::emulation::video::controller::VideoMemoryLocation::*;
+use crate::rendering::rgb555_to_rgb;
+
+#[test]
+fn index_auto_increment() {
+    let mut palettes = ColorPaletteRam::new();
+    palettes.write_index(0x86);
+    assert_eq!(0xC6, palettes.read_index());
+
+    palettes.write_data(0x1F, true);
+    palettes.write_data(0x7C, true);
+    assert_eq!(0xC8, palettes.read_index());
+    assert_eq!(0x7C1F, palettes.get_color(0, 3));
+
+    // Without auto increment, writes keep landing on the same byte.
+    palettes.write_index(0x3F);
+    palettes.write_data(0x12, true);
+    palettes.write_data(0x34, true);
+    assert_eq!(0x7F, palettes.read_index());
+    assert_eq!(0x34, palettes.read_data());
+
+    // The index wraps around.
+    palettes.write_index(0xBF);
+    palettes.write_data(0x00, true);
+    assert_eq!(0xC0, palettes.read_index());
+    assert_eq!(0x00FF, palettes.get_color(7, 3));
+}
+
+#[test]
+fn color_conversion() {
+    assert_eq!((0, 0, 0), rgb555_to_rgb(0x0000));
+    assert_eq!((255, 255, 255), rgb555_to_rgb(0x7FFF));
+    assert_eq!((255, 0, 0), rgb555_to_rgb(0x001F));
+    assert_eq!((0, 255, 0), rgb555_to_rgb(0x03E0));
+    assert_eq!((0, 0, 255), rgb555_to_rgb(0x7C00));
+    assert_eq!((132, 0, 0), rgb555_to_rgb(0x0010));
+}
+
+#[test]
+fn palette_data_is_blocked_while_drawing() {
+    let mut video = VideoController::new(DeviceType::GameBoyColor);
+    video.write_8(BackgroundPaletteData, 0x12);
+    video.write_8(BackgroundPaletteIndex, 0x80);
+    video.write_8(LCDControlRegister, 0x91);
+
+    let mut messages: Vec<InternalMessage> = Vec::new();
+    video.update(80, &mut messages);
+    assert_eq!(0xFF, video.read_8(BackgroundPaletteData));
+
+    // The write is lost, but the index still moves on.
+    video.write_8(BackgroundPaletteData, 0x34);
+    assert_eq!(0xC1, video.read_8(BackgroundPaletteIndex));
+
+    video.update(172, &mut messages);
+    video.write_8(BackgroundPaletteIndex, 0x00);
+    assert_eq!(0x12, video.read_8(BackgroundPaletteData));
+}
+
+#[test]
+fn palettes_are_absent_on_dmg() {
+    let mut video = VideoController::new(DeviceType::GameBoy);
+    video.write_8(SpritePaletteIndex, 0x80);
+    video.write_8(SpritePaletteData, 0x00);
+    assert_eq!(0xFF, video.read_8(SpritePaletteIndex));
+    assert_eq!(0xFF, video.read_8(SpritePaletteData));
+}
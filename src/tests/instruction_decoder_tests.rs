@@ -1,5 +1,3 @@
-use std::panic::catch_unwind;
-
 use crate::emulation::instruction::Instruction::*;
 use crate::emulation::instruction::*;
 use crate::emulation::instruction_decoder::*;
@@ -130,31 +128,49 @@ pub fn special_stores_8() {
     verify_instruction(StoreAIndirectHLDecrement, &[0x32]);
     verify_instruction(StoreAHigh(0xFF), &[0xE0, 0xFF]);
     verify_instruction(StoreAHighC, &[0xE2]);
+    verify_instruction(LoadAHighC, &[0xF2]);
+}
+
+const ILLEGAL_OPCODES: [u8; 11] = [
+    0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD
+];
+
+#[test]
+pub fn removed_instructions_should_be_unknown() {
+    for &opcode in &ILLEGAL_OPCODES {
+        verify_instruction(Unknown(opcode as u16), &[opcode, 0x00, 0x00]);
+    }
 }
 
 #[test]
-pub fn removed_instructions_should_be_unknown_or_panic() {
-    let removed_instructions = vec![
-        vec![0xD3],
-        vec![0xDB],
-        vec![0xDD],
-        vec![0xE3],
-        vec![0xE4],
-        vec![0xEB],
-        vec![0xEC],
-        vec![0xF2],
-        vec![0xF4],
-        vec![0xFC],
-        vec![0xFD],
-    ];
-
-    for i in removed_instructions {
-        let decoded = catch_unwind(|| decode_bytes(&i));
-        match decoded {
-            Err(_) | Ok(Unknown(_)) => {}
-            _ => panic!("Expected Unknown, was {:?}", decoded)
-        }
+pub fn every_legal_opcode_decodes() {
+    for opcode in 0..=0xFFu8 {
+        let instruction = decode_bytes(&[opcode, 0x00, 0x00]);
+
+        assert_eq!(
+            ILLEGAL_OPCODES.contains(&opcode),
+            instruction == Unknown(opcode as u16),
+            "0x{:02X} decoded as {:?}",
+            opcode,
+            instruction
+        );
     }
+
+    for opcode in 0..=0xFFu8 {
+        let instruction = decode_bytes(&[0xCB, opcode]);
+        assert!(
+            instruction != Unknown(0xCB00 | opcode as u16),
+            "0xCB{:02X} is unknown",
+            opcode
+        );
+    }
+}
+
+#[test]
+pub fn stack_pointer_arithmetic() {
+    verify_instruction(AddSignedImmediateToSP(-2), &[0xE8, 0xFE]);
+    verify_instruction(MoveSPOffsetToHL(5), &[0xF8, 0x05]);
+    verify_instruction(MoveHLToSP, &[0xF9]);
 }
 
 #[test]
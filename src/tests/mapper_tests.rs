@@ -0,0 +1,211 @@
+use crate::emulation::cartridge::{CartridgeMemory, CartridgeType};
+use crate::emulation::constants::*;
+use crate::emulation::mappers::*;
+use crate::emulation::rtc::RtcRegisters;
+
+fn create_banked_memory(cartridge_type: CartridgeType) -> CartridgeMemory {
+    let mut memory = CartridgeMemory::new(cartridge_type.rom_size, cartridge_type.ram_size);
+
+    for (bank, chunk) in memory.rom.chunks_mut(ROM_BANK_SIZE).enumerate() {
+        chunk[0] = bank as u8;
+        chunk[1] = (bank >> 8) as u8;
+    }
+
+    memory
+}
+
+#[test]
+fn mbc3_rom_banking() {
+    // MBC3+RAM+BATTERY, 2 MB ROM, 32 KB RAM
+    let cartridge_type = CartridgeType::new(0x13, 0x06, 0x03);
+    let mut memory = create_banked_memory(cartridge_type);
+    let mut mbc = MBC3::new(cartridge_type);
+
+    assert_eq!(1, mbc.read_8(&memory, 0x4000));
+
+    mbc.write_8(&mut memory, 0x2000, 0x7F);
+    assert_eq!(0x7F, mbc.read_8(&memory, 0x4000));
+
+    mbc.write_8(&mut memory, 0x2000, 0x00);
+    assert_eq!(1, mbc.read_8(&memory, 0x4000));
+}
+
+#[test]
+fn mbc3_ram_banking() {
+    let cartridge_type = CartridgeType::new(0x13, 0x06, 0x03);
+    let mut memory = create_banked_memory(cartridge_type);
+    let mut mbc = MBC3::new(cartridge_type);
+
+    mbc.write_8(&mut memory, 0xA000, 0x12);
+    assert_eq!(
+        0xFF,
+        mbc.read_8(&memory, 0xA000),
+        "RAM should start disabled"
+    );
+
+    mbc.write_8(&mut memory, 0x0000, 0x0A);
+
+    for bank in 0..4 {
+        mbc.write_8(&mut memory, 0x4000, bank);
+        mbc.write_8(&mut memory, 0xA000, 0x10 + bank);
+    }
+
+    for bank in 0..4 {
+        mbc.write_8(&mut memory, 0x4000, bank);
+        assert_eq!(0x10 + bank, mbc.read_8(&memory, 0xA000));
+    }
+
+    assert_eq!(0x13, memory.ram[3 * CARTRIDGE_RAM_BANK_SIZE]);
+}
+
+#[test]
+fn mbc3_rtc_latch() {
+    // MBC3+TIMER+RAM+BATTERY
+    let cartridge_type = CartridgeType::new(0x10, 0x06, 0x03);
+    let mut memory = create_banked_memory(cartridge_type);
+    let mut mbc = MBC3::new(cartridge_type);
+
+    mbc.write_8(&mut memory, 0x0000, 0x0A);
+
+    // Halt the clock so wall-clock time doesn't affect the result.
+    mbc.write_8(&mut memory, 0x4000, 0x0C);
+    mbc.write_8(&mut memory, 0xA000, 0b0100_0000);
+
+    mbc.rtc.live.advance(2 * 86400 + 3 * 3600 + 4 * 60 + 5);
+    assert_eq!(
+        0, mbc.rtc.latched.seconds,
+        "Registers shouldn't change before latching"
+    );
+
+    mbc.write_8(&mut memory, 0x6000, 0x00);
+    mbc.write_8(&mut memory, 0x6000, 0x01);
+
+    let expected = [
+        (0x08, 5),
+        (0x09, 4),
+        (0x0A, 3),
+        (0x0B, 2),
+        (0x0C, 0b0100_0000)
+    ];
+    for &(register, value) in &expected {
+        mbc.write_8(&mut memory, 0x4000, register);
+        assert_eq!(value, mbc.read_8(&memory, 0xA000));
+    }
+}
+
+#[test]
+fn rtc_day_counter_overflow() {
+    let mut registers = RtcRegisters::default();
+    registers.advance(511 * 86400 + 86399);
+    assert_eq!(511, registers.get_days());
+    assert!(!registers.has_day_carry());
+
+    registers.advance(1);
+    assert_eq!(0, registers.get_days());
+    assert_eq!(0, registers.hours);
+    assert!(registers.has_day_carry());
+}
+
+#[test]
+fn rtc_out_of_range_seconds_wrap_without_carry() {
+    let mut registers = RtcRegisters {
+        seconds: 62,
+        ..RtcRegisters::default()
+    };
+    registers.advance(2);
+    assert_eq!(0, registers.seconds);
+    assert_eq!(0, registers.minutes);
+}
+
+#[test]
+fn mbc5_9_bit_rom_banking() {
+    // MBC5+RAM+BATTERY, 8 MB ROM, 128 KB RAM
+    let cartridge_type = CartridgeType::new(0x1B, 0x08, 0x04);
+    let mut memory = create_banked_memory(cartridge_type);
+    let mut mbc = MBC5::new(cartridge_type);
+
+    mbc.write_8(&mut memory, 0x2000, 0x34);
+    mbc.write_8(&mut memory, 0x3000, 0x01);
+    assert_eq!(0x34, mbc.read_8(&memory, 0x4000));
+    assert_eq!(0x01, mbc.read_8(&memory, 0x4001));
+
+    mbc.write_8(&mut memory, 0x2000, 0x00);
+    mbc.write_8(&mut memory, 0x3000, 0x00);
+    assert_eq!(
+        0,
+        mbc.read_8(&memory, 0x4000),
+        "Bank 0 should be selectable"
+    );
+}
+
+#[test]
+fn mbc5_ram_banking() {
+    let cartridge_type = CartridgeType::new(0x1B, 0x08, 0x04);
+    let mut memory = create_banked_memory(cartridge_type);
+    let mut mbc = MBC5::new(cartridge_type);
+
+    mbc.write_8(&mut memory, 0x0000, 0x0A);
+    mbc.write_8(&mut memory, 0x4000, 0x0F);
+    mbc.write_8(&mut memory, 0xA123, 0x42);
+
+    assert_eq!(0x42, memory.ram[15 * CARTRIDGE_RAM_BANK_SIZE + 0x123]);
+    assert!(!mbc.is_rumbling());
+}
+
+#[test]
+fn mbc5_rumble() {
+    // MBC5+RUMBLE+RAM+BATTERY, 1 MB ROM, 32 KB RAM
+    let cartridge_type = CartridgeType::new(0x1E, 0x05, 0x03);
+    let mut memory = create_banked_memory(cartridge_type);
+    let mut mbc = MBC5::new(cartridge_type);
+
+    mbc.write_8(&mut memory, 0x0000, 0x0A);
+    mbc.write_8(&mut memory, 0x4000, 0b1011);
+    assert!(mbc.is_rumbling());
+
+    mbc.write_8(&mut memory, 0xA000, 0x99);
+    assert_eq!(0x99, memory.ram[3 * CARTRIDGE_RAM_BANK_SIZE]);
+
+    mbc.write_8(&mut memory, 0x4000, 0b0011);
+    assert!(!mbc.is_rumbling());
+}
+
+#[test]
+fn mbc2_register_selection() {
+    // MBC2+BATTERY, 256 KB ROM
+    let cartridge_type = CartridgeType::new(0x06, 0x03, 0x00);
+    let mut memory = create_banked_memory(cartridge_type);
+    let mut mbc = MBC2::new();
+
+    mbc.write_8(&mut memory, 0x2100, 0x0F);
+    assert_eq!(0x0F, mbc.read_8(&memory, 0x4000));
+
+    mbc.write_8(&mut memory, 0x0100, 0x00);
+    assert_eq!(1, mbc.read_8(&memory, 0x4000));
+
+    // Bit 8 clear: this is a RAM enable write, not a bank switch
+    mbc.write_8(&mut memory, 0x2000, 0x0A);
+    assert_eq!(1, mbc.read_8(&memory, 0x4000));
+    mbc.write_8(&mut memory, 0xA000, 0x05);
+    assert_eq!(0xF5, mbc.read_8(&memory, 0xA000));
+}
+
+#[test]
+fn mbc2_ram_echo_and_nibbles() {
+    let cartridge_type = CartridgeType::new(0x06, 0x03, 0x00);
+    let mut memory = create_banked_memory(cartridge_type);
+    let mut mbc = MBC2::new();
+
+    assert_eq!(MBC2_RAM_SIZE, memory.ram.len());
+
+    mbc.write_8(&mut memory, 0x0000, 0x0A);
+    mbc.write_8(&mut memory, 0xA1FF, 0xAB);
+
+    assert_eq!(0x0B, memory.ram[0x1FF]);
+    assert_eq!(0xFB, mbc.read_8(&memory, 0xA1FF));
+    assert_eq!(0xFB, mbc.read_8(&memory, 0xA3FF));
+    assert_eq!(0xFB, mbc.read_8(&memory, 0xBFFF));
+
+    mbc.write_8(&mut memory, 0x0000, 0x00);
+    assert_eq!(0xFF, mbc.read_8(&memory, 0xA1FF));
+}
@@ -1,3 +1,12 @@
+pub mod audio_tests;
+pub mod battery_save_tests;
 pub mod cartridge_header_parser_tests;
+pub mod color_palette_tests;
 pub mod instruction_decoder_tests;
+pub mod mapper_tests;
+pub mod png_tests;
+pub mod resampler_tests;
+pub mod sprite_tests;
 pub mod tile_decoder_tests;
+pub mod timer_tests;
+pub mod video_tests;
@@ -0,0 +1,55 @@
+use crate::rendering::png::*;
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut word = [0u8; 4];
+    word.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_be_bytes(word)
+}
+
+#[test]
+fn checksums() {
+    assert_eq!(0xCBF4_3926, crc32(b"123456789"));
+    assert_eq!(0x11E6_0398, adler32(b"Wikipedia"));
+}
+
+#[test]
+fn deflate_empty_input() {
+    // A single fixed Huffman block containing only the end-of-block code.
+    assert_eq!(vec![0x03, 0x00], deflate(&[]));
+}
+
+#[test]
+fn deflate_compresses_repetition() {
+    let data = vec![0x42; 10000];
+    assert!(deflate(&data).len() < 100);
+}
+
+#[test]
+fn png_structure() {
+    let rgba: Vec<u8> = (0..4 * 3 * 4).map(|i| i as u8).collect();
+    let png = encode_png(4, 3, &rgba);
+
+    assert_eq!(
+        &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A],
+        &png[0..8]
+    );
+
+    // IHDR
+    assert_eq!(13, read_u32(&png, 8));
+    assert_eq!(b"IHDR", &png[12..16]);
+    assert_eq!(4, read_u32(&png, 16));
+    assert_eq!(3, read_u32(&png, 20));
+    assert_eq!(&[8, 6, 0, 0, 0], &png[24..29]);
+    assert_eq!(crc32(&png[12..29]), read_u32(&png, 29));
+
+    // IDAT
+    let idat_length = read_u32(&png, 33) as usize;
+    assert_eq!(b"IDAT", &png[37..41]);
+    let idat_end = 41 + idat_length;
+    assert_eq!(crc32(&png[37..idat_end]), read_u32(&png, idat_end));
+
+    // IEND
+    assert_eq!(&[0, 0, 0, 0], &png[idat_end + 4..idat_end + 8]);
+    assert_eq!(b"IEND", &png[idat_end + 8..idat_end + 12]);
+    assert_eq!(png.len(), idat_end + 16);
+}
@@ -0,0 +1,59 @@
+use std::f32::consts::PI;
+
+use crate::emulation::audio::controller::StereoSample;
+use crate::emulation::audio::resampler::Resampler;
+use crate::emulation::constants::APU_SAMPLE_RATE;
+
+fn resample_tone(frequency: f32, output_rate: u32, adjustment: f64) -> Vec<StereoSample> {
+    let mut resampler = Resampler::new(APU_SAMPLE_RATE, output_rate);
+    resampler.set_rate_adjustment(adjustment);
+
+    for i in 0..APU_SAMPLE_RATE {
+        let value = (2.0 * PI * frequency * i as f32 / APU_SAMPLE_RATE as f32).sin() * 0.5;
+        resampler.push_sample(StereoSample {
+            left: value,
+            right: -value
+        });
+    }
+
+    resampler.drain_output().collect()
+}
+
+fn rms(samples: &[StereoSample]) -> f32 {
+    let sum: f32 = samples.iter().map(|s| s.left * s.left).sum();
+    (sum / samples.len() as f32).sqrt()
+}
+
+#[test]
+fn resampler_produces_output_rate() {
+    let output = resample_tone(440.0, 48000, 1.0);
+    assert!((output.len() as i32 - 48000).abs() < 64);
+}
+
+#[test]
+fn resampler_rate_adjustment() {
+    let output = resample_tone(440.0, 48000, 1.005);
+    assert!((output.len() as i32 - 48240).abs() < 64);
+}
+
+#[test]
+fn resampler_passes_audible_tones() {
+    let output = resample_tone(1000.0, 48000, 1.0);
+    let level = rms(&output[4800..]);
+    assert!(
+        (level - 0.5 / 2f32.sqrt()).abs() < 0.02,
+        "RMS was {}",
+        level
+    );
+
+    // Channels are resampled independently.
+    assert!((output[10000].left + output[10000].right).abs() < 1e-3);
+}
+
+#[test]
+fn resampler_rejects_ultrasonic_tones() {
+    // Well above the output Nyquist frequency, this would alias into the audible range.
+    let output = resample_tone(40000.0, 48000, 1.0);
+    let level = rms(&output[4800..]);
+    assert!(level < 0.01, "RMS was {}", level);
+}
@@ -0,0 +1,171 @@
+use crate::emulation::constants::*;
+use crate::rendering::sprites::*;
+use crate::rendering::SpriteAttributes;
+
+type Tile = [u8; TILE_SIZE * TILE_SIZE];
+
+fn create_sprite(x: u8, y: u8, pattern: u8, flags: u8) -> SpriteAttributes {
+    SpriteAttributes {
+        x,
+        y,
+        pattern,
+        flags
+    }
+}
+
+// Tile 0 is blank, tile 1 is solid color 1, tile 2 is solid color 2, tile 3 has color 3 in its
+// top left corner only and tile 4 is color 1 on the left half and transparent on the right.
+fn create_tiles() -> Vec<Tile> {
+    let mut tiles = vec![[0u8; TILE_SIZE * TILE_SIZE]; 8];
+    tiles[1] = [1; TILE_SIZE * TILE_SIZE];
+    tiles[2] = [2; TILE_SIZE * TILE_SIZE];
+    tiles[3][0] = 3;
+
+    for row in 0..TILE_SIZE {
+        for column in 0..4 {
+            tiles[4][row * TILE_SIZE + column] = 1;
+        }
+    }
+
+    tiles
+}
+
+fn draw(sprites: &[SpriteAttributes], line: u8, height: u8) -> Vec<u8> {
+    let line_sprites = select_line_sprites(sprites, line, height, SpriteMode::Dmg);
+    let mut pixels = [None; SCREEN_WIDTH as usize];
+    draw_sprite_line(
+        &line_sprites,
+        &create_tiles(),
+        line,
+        height,
+        SpriteMode::Dmg,
+        &mut pixels
+    );
+
+    pixels
+        .iter()
+        .map(|pixel| pixel.map_or(0, |pixel| pixel.color))
+        .collect()
+}
+
+#[test]
+fn at_most_ten_sprites_per_line() {
+    // The first one is off screen, but still counts.
+    let mut sprites = vec![create_sprite(0, 16, 1, 0)];
+    for i in 0..11 {
+        sprites.push(create_sprite(8 + i * 8, 16, 1, 0));
+    }
+    sprites.push(create_sprite(100, 40, 1, 0));
+
+    let line_sprites = select_line_sprites(&sprites, 0, 8, SpriteMode::Dmg);
+    assert_eq!(10, line_sprites.len());
+    assert_eq!(&sprites[..10], &line_sprites[..]);
+
+    let colors = draw(&sprites, 0, 8);
+    assert_eq!(1, colors[71]);
+    assert_eq!(0, colors[72]);
+}
+
+#[test]
+fn smaller_x_is_drawn_on_top() {
+    let sprites = [create_sprite(12, 16, 2, 0), create_sprite(8, 16, 1, 0)];
+    let colors = draw(&sprites, 0, 8);
+    assert_eq!(&[1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2], &colors[..12]);
+
+    // With equal X coordinates, OAM order decides.
+    let sprites = [create_sprite(8, 16, 2, 0), create_sprite(8, 16, 1, 0)];
+    assert_eq!(2, draw(&sprites, 0, 8)[0]);
+}
+
+#[test]
+fn transparent_pixels_show_sprites_behind() {
+    let sprites = [create_sprite(8, 16, 4, 0), create_sprite(10, 16, 2, 0)];
+    let colors = draw(&sprites, 0, 8);
+    assert_eq!(&[1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 0], &colors[..11]);
+}
+
+#[test]
+fn flipped_sprites() {
+    let sprites = [create_sprite(8, 16, 3, 0b0010_0000)];
+    assert_eq!(3, draw(&sprites, 0, 8)[7]);
+
+    let sprites = [create_sprite(8, 16, 3, 0b0100_0000)];
+    assert_eq!(0, draw(&sprites, 0, 8)[0]);
+    assert_eq!(3, draw(&sprites, 7, 8)[0]);
+
+    let sprites = [create_sprite(8, 16, 3, 0b0110_0000)];
+    assert_eq!(3, draw(&sprites, 7, 8)[7]);
+}
+
+#[test]
+fn tall_sprites() {
+    // The low bit of the pattern is ignored, and the next tile holds the bottom half.
+    let sprites = [create_sprite(8, 16, 3, 0)];
+    assert_eq!(2, draw(&sprites, 0, 16)[0]);
+    assert_eq!(3, draw(&sprites, 8, 16)[0]);
+    assert_eq!(0, draw(&sprites, 8, 16)[1]);
+    assert_eq!(0, draw(&sprites, 16, 16)[0]);
+
+    // Flipping vertically swaps the tiles around as well.
+    let sprites = [create_sprite(8, 16, 2, 0b0100_0000)];
+    assert_eq!(3, draw(&sprites, 7, 16)[0]);
+    assert_eq!(2, draw(&sprites, 8, 16)[0]);
+}
+
+#[test]
+fn partially_off_screen_sprites() {
+    // Only the bottom row is on screen, and only the right half of it.
+    let sprites = [create_sprite(4, 9, 3, 0b0110_0000)];
+    assert_eq!(3, draw(&sprites, 0, 8)[3]);
+    assert_eq!(0, draw(&sprites, 1, 8)[3]);
+
+    let sprites = [create_sprite(4, 9, 1, 0)];
+    assert_eq!(&[1, 1, 1, 1, 0], &draw(&sprites, 0, 8)[..5]);
+}
+
+#[test]
+fn background_priority() {
+    let sprites = [
+        create_sprite(8, 16, 1, 0b1000_0000),
+        create_sprite(12, 16, 2, 0)
+    ];
+    let line_sprites = select_line_sprites(&sprites, 0, 8, SpriteMode::Dmg);
+    let mut pixels = [None; SCREEN_WIDTH as usize];
+    draw_sprite_line(
+        &line_sprites,
+        &create_tiles(),
+        0,
+        8,
+        SpriteMode::Dmg,
+        &mut pixels
+    );
+
+    let pixel = pixels[4].unwrap();
+    assert_eq!(1, pixel.color);
+    assert!(pixel.is_visible_over(0));
+    // The hidden sprite still covers the one below it.
+    assert!(!pixel.is_visible_over(2));
+
+    assert!(pixels[8].unwrap().is_visible_over(2));
+}
+
+#[test]
+fn color_mode_uses_oam_order_and_tile_banks() {
+    let sprites = [create_sprite(12, 16, 2, 0), create_sprite(8, 16, 1, 0)];
+    let line_sprites = select_line_sprites(&sprites, 0, 8, SpriteMode::Cgb);
+    assert_eq!(&sprites[..], &line_sprites[..]);
+
+    // Tile 1 of bank 1 is solid color 3.
+    let mut tiles = create_tiles();
+    tiles.resize(VRAM_TILE_COUNT * 2, [0; TILE_SIZE * TILE_SIZE]);
+    tiles[VRAM_TILE_COUNT + 1] = [3; TILE_SIZE * TILE_SIZE];
+
+    let sprites = [create_sprite(8, 16, 1, 0b0000_1000)];
+    let mut pixels = [None; SCREEN_WIDTH as usize];
+    draw_sprite_line(&sprites, &tiles, 0, 8, SpriteMode::Cgb, &mut pixels);
+    assert_eq!(3, pixels[0].unwrap().color);
+
+    // The bank bit is ignored on the DMG.
+    draw_sprite_line(&sprites, &tiles, 0, 8, SpriteMode::Dmg, &mut pixels);
+    assert_eq!(1, pixels[0].unwrap().color);
+}
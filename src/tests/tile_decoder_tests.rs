@@ -1,3 +1,5 @@
+use crate::emulation::device::DeviceType;
+use crate::emulation::video::controller::{VideoController, VideoMemoryLocation};
 use crate::rendering::*;
 
 #[test]
@@ -21,3 +23,19 @@ fn check_copyright_symbol() {
     println!("{:?}", &decompressed_buffer[48..56]);
     println!("{:?}", &decompressed_buffer[56..64]);
 }
+
+#[test]
+fn background_tile_numbers() {
+    let mut video = VideoController::new(DeviceType::GameBoy);
+
+    video.write_8(VideoMemoryLocation::LCDControlRegister, 0x91);
+    assert_eq!(0, CommonRenderer::get_bg_tile_number(&video, 0));
+    assert_eq!(128, CommonRenderer::get_bg_tile_number(&video, 128));
+    assert_eq!(255, CommonRenderer::get_bg_tile_number(&video, 255));
+
+    video.write_8(VideoMemoryLocation::LCDControlRegister, 0x81);
+    assert_eq!(256, CommonRenderer::get_bg_tile_number(&video, 0));
+    assert_eq!(383, CommonRenderer::get_bg_tile_number(&video, 127));
+    assert_eq!(128, CommonRenderer::get_bg_tile_number(&video, 128));
+    assert_eq!(255, CommonRenderer::get_bg_tile_number(&video, 255));
+}
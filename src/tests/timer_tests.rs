@@ -0,0 +1,160 @@
+use crate::emulation::internal_message::InternalMessage;
+use crate::emulation::interrupt::Interrupt;
+use crate::emulation::timers::{TimerRegister, TimerRegisters};
+
+fn run_cycles(timer: &mut TimerRegisters, m_cycles: usize) -> usize {
+    let mut interrupts = 0;
+
+    for _ in 0..m_cycles {
+        if let InternalMessage::TriggerInterrupt(Interrupt::TimerOverflow) = timer.update() {
+            interrupts += 1;
+        }
+    }
+
+    interrupts
+}
+
+// TIMA is clocked every 16 cycles, so it increments every fourth machine cycle.
+fn create_fast_timer() -> TimerRegisters {
+    let mut timer = TimerRegisters::new();
+    timer.write_8(TimerRegister::Control, 0b101);
+    timer
+}
+
+#[test]
+fn divider_increments_every_256_cycles() {
+    let mut timer = TimerRegisters::new();
+
+    run_cycles(&mut timer, 63);
+    assert_eq!(0, timer.read_8(TimerRegister::Divider));
+
+    run_cycles(&mut timer, 1);
+    assert_eq!(1, timer.read_8(TimerRegister::Divider));
+
+    run_cycles(&mut timer, 64 * 9);
+    assert_eq!(10, timer.read_8(TimerRegister::Divider));
+
+    timer.write_8(TimerRegister::Divider, 0x55);
+    assert_eq!(0, timer.read_8(TimerRegister::Divider));
+}
+
+#[test]
+fn divider_runs_while_timer_is_disabled() {
+    let mut timer = TimerRegisters::new();
+    timer.write_8(TimerRegister::Control, 0b001);
+
+    run_cycles(&mut timer, 128);
+
+    assert_eq!(2, timer.read_8(TimerRegister::Divider));
+    assert_eq!(0, timer.read_8(TimerRegister::Counter));
+}
+
+#[test]
+fn counter_frequencies() {
+    for &(control, period) in &[(0b100, 256), (0b101, 4), (0b110, 16), (0b111, 64)] {
+        let mut timer = TimerRegisters::new();
+        timer.write_8(TimerRegister::Control, control);
+
+        run_cycles(&mut timer, period - 1);
+        assert_eq!(
+            0,
+            timer.read_8(TimerRegister::Counter),
+            "TAC {:03b}",
+            control
+        );
+
+        run_cycles(&mut timer, 1);
+        assert_eq!(
+            1,
+            timer.read_8(TimerRegister::Counter),
+            "TAC {:03b}",
+            control
+        );
+
+        run_cycles(&mut timer, period * 4);
+        assert_eq!(
+            5,
+            timer.read_8(TimerRegister::Counter),
+            "TAC {:03b}",
+            control
+        );
+    }
+}
+
+#[test]
+fn overflow_reloads_one_cycle_late() {
+    let mut timer = create_fast_timer();
+    timer.write_8(TimerRegister::Modulo, 0x42);
+    timer.write_8(TimerRegister::Counter, 0xFF);
+
+    assert_eq!(0, run_cycles(&mut timer, 4));
+    assert_eq!(0x00, timer.read_8(TimerRegister::Counter));
+
+    assert_eq!(1, run_cycles(&mut timer, 1));
+    assert_eq!(0x42, timer.read_8(TimerRegister::Counter));
+}
+
+#[test]
+fn writing_counter_before_reload_cancels_it() {
+    let mut timer = create_fast_timer();
+    timer.write_8(TimerRegister::Modulo, 0x42);
+    timer.write_8(TimerRegister::Counter, 0xFF);
+
+    run_cycles(&mut timer, 4);
+    timer.write_8(TimerRegister::Counter, 0x10);
+
+    assert_eq!(0, run_cycles(&mut timer, 1));
+    assert_eq!(0x10, timer.read_8(TimerRegister::Counter));
+}
+
+#[test]
+fn writes_during_reload_cycle() {
+    let mut timer = create_fast_timer();
+    timer.write_8(TimerRegister::Modulo, 0x42);
+    timer.write_8(TimerRegister::Counter, 0xFF);
+
+    run_cycles(&mut timer, 5);
+
+    // TIMA writes are ignored, while TMA writes go through to TIMA as well.
+    timer.write_8(TimerRegister::Counter, 0x10);
+    assert_eq!(0x42, timer.read_8(TimerRegister::Counter));
+
+    timer.write_8(TimerRegister::Modulo, 0x20);
+    assert_eq!(0x20, timer.read_8(TimerRegister::Counter));
+
+    run_cycles(&mut timer, 1);
+    timer.write_8(TimerRegister::Counter, 0x10);
+    assert_eq!(0x10, timer.read_8(TimerRegister::Counter));
+}
+
+#[test]
+fn resetting_divider_can_increment_counter() {
+    let mut timer = create_fast_timer();
+
+    // Bit 3 of the system counter is set after two machine cycles.
+    run_cycles(&mut timer, 2);
+    timer.write_8(TimerRegister::Divider, 0);
+    assert_eq!(1, timer.read_8(TimerRegister::Counter));
+
+    // With the bit clear, the reset doesn't cause an increment.
+    run_cycles(&mut timer, 1);
+    timer.write_8(TimerRegister::Divider, 0);
+    assert_eq!(1, timer.read_8(TimerRegister::Counter));
+}
+
+#[test]
+fn writing_control_can_increment_counter() {
+    let mut timer = create_fast_timer();
+    run_cycles(&mut timer, 2);
+
+    // Disabling the timer while the selected bit is set is a falling edge.
+    timer.write_8(TimerRegister::Control, 0b001);
+    assert_eq!(1, timer.read_8(TimerRegister::Counter));
+
+    // So is switching to a bit that is clear.
+    timer.write_8(TimerRegister::Control, 0b101);
+    timer.write_8(TimerRegister::Control, 0b100);
+    assert_eq!(2, timer.read_8(TimerRegister::Counter));
+
+    assert_eq!(0xF8, timer.read_8(TimerRegister::Control) & 0xF8);
+}
@@ -0,0 +1,250 @@
+use crate::emulation::device::DeviceType;
+use crate::emulation::internal_message::InternalMessage;
+use crate::emulation::interrupt::Interrupt;
+use crate::emulation::save_state::{SaveState, StateReader, StateWriter};
+use crate::emulation::video::controller::VideoController;
+use crate::emulation::video::controller::VideoMemoryLocation::*;
+use crate::emulation::video::pixel_fifo::PixelTransfer;
+
+fn create_video() -> VideoController {
+    let mut video = VideoController::new(DeviceType::GameBoy);
+    video.write_8(LCDControlRegister, 0x91);
+    video
+}
+
+fn get_mode(video: &VideoController) -> u8 {
+    video.read_8(LCDStatusRegister) & 0b11
+}
+
+fn run_dots(video: &mut VideoController, dots: u32) -> usize {
+    let mut messages = Vec::new();
+    video.update(dots, &mut messages);
+
+    messages
+        .iter()
+        .filter(|message| {
+            matches!(
+                message,
+                InternalMessage::TriggerInterrupt(Interrupt::LCDController)
+            )
+        })
+        .count()
+}
+
+fn get_transfer_length(scroll_x: u8, sprites: &[u8], window_x: Option<u8>) -> u32 {
+    let mut transfer = PixelTransfer::new();
+    transfer.start_line(scroll_x, sprites);
+
+    let mut dots = 1;
+    while !transfer.update(window_x) {
+        dots += 1;
+    }
+
+    dots
+}
+
+#[test]
+fn pixel_transfer_length() {
+    assert_eq!(172, get_transfer_length(0, &[], None));
+    assert_eq!(175, get_transfer_length(3, &[], None));
+    assert_eq!(172 + 7, get_transfer_length(15, &[], None));
+
+    // Starting the window restarts the fetcher.
+    assert_eq!(178, get_transfer_length(0, &[], Some(87)));
+    assert_eq!(172, get_transfer_length(0, &[], Some(167)));
+}
+
+#[test]
+fn sprite_penalties() {
+    assert_eq!(172 + 11, get_transfer_length(0, &[0], None));
+    assert_eq!(172 + 11, get_transfer_length(0, &[8], None));
+    assert_eq!(172 + 9, get_transfer_length(0, &[10], None));
+    assert_eq!(172 + 6, get_transfer_length(0, &[13], None));
+    assert_eq!(175 + 10, get_transfer_length(3, &[14], None));
+
+    // The background fetch is only waited for once per tile.
+    assert_eq!(172 + 11 + 6, get_transfer_length(0, &[8, 8], None));
+    assert_eq!(172 + 11 + 11, get_transfer_length(0, &[8, 16], None));
+
+    // Objects past the right edge aren't fetched.
+    assert_eq!(172, get_transfer_length(0, &[168], None));
+}
+
+#[test]
+fn modes_during_a_line() {
+    let mut video = create_video();
+    assert_eq!(2, get_mode(&video));
+
+    run_dots(&mut video, 79);
+    assert_eq!(2, get_mode(&video));
+    run_dots(&mut video, 1);
+    assert_eq!(3, get_mode(&video));
+
+    run_dots(&mut video, 171);
+    assert_eq!(3, get_mode(&video));
+    run_dots(&mut video, 1);
+    assert_eq!(0, get_mode(&video));
+
+    run_dots(&mut video, 456 - 80 - 172 - 1);
+    assert_eq!(0, get_mode(&video));
+    assert_eq!(0, video.read_8(CurrentScanline));
+    run_dots(&mut video, 1);
+    assert_eq!(2, get_mode(&video));
+    assert_eq!(1, video.read_8(CurrentScanline));
+}
+
+#[test]
+fn vblank_and_frame_wrap() {
+    let mut video = create_video();
+
+    run_dots(&mut video, 456 * 144);
+    assert_eq!(144, video.read_8(CurrentScanline));
+    assert_eq!(1, get_mode(&video));
+
+    // LY reads 0 for most of the last line.
+    run_dots(&mut video, 456 * 9 + 4);
+    assert_eq!(0, video.read_8(CurrentScanline));
+    assert_eq!(1, get_mode(&video));
+
+    run_dots(&mut video, 456 - 4);
+    assert_eq!(0, video.read_8(CurrentScanline));
+    assert_eq!(2, get_mode(&video));
+}
+
+#[test]
+fn coincidence_interrupt() {
+    let mut video = create_video();
+    video.write_8(ComparisonScanline, 2);
+    video.write_8(LCDStatusRegister, 0b0100_0000);
+
+    assert_eq!(0, run_dots(&mut video, 456 * 2 - 1));
+    assert_eq!(0, video.read_8(LCDStatusRegister) & 0b100);
+
+    assert_eq!(1, run_dots(&mut video, 1));
+    assert_eq!(0b100, video.read_8(LCDStatusRegister) & 0b100);
+
+    assert_eq!(0, run_dots(&mut video, 456));
+    assert_eq!(0, video.read_8(LCDStatusRegister) & 0b100);
+
+    // Writing LYC can raise the interrupt immediately.
+    let message = video.write_8(ComparisonScanline, 3);
+    match message {
+        InternalMessage::TriggerInterrupt(Interrupt::LCDController) => (),
+        _ => panic!("Expected a STAT interrupt, got {:?}", message)
+    }
+}
+
+#[test]
+fn stat_interrupt_blocking() {
+    let mut video = create_video();
+    video.write_8(LCDStatusRegister, 0b0010_1000);
+
+    // Mode 0 raises the line, which stays high through mode 2 of the next line.
+    assert_eq!(1, run_dots(&mut video, 456));
+    assert_eq!(1, run_dots(&mut video, 456));
+
+    // Mode 2 sources fire once at the start of VBlank, after which mode 1 is not enabled.
+    let mut video = create_video();
+    video.write_8(LCDStatusRegister, 0b0010_0000);
+    assert_eq!(143, run_dots(&mut video, 456 * 143 + 1));
+    assert_eq!(1, run_dots(&mut video, 456));
+    assert_eq!(0, run_dots(&mut video, 456 * 10 - 2));
+    assert_eq!(1, run_dots(&mut video, 1));
+}
+
+#[test]
+fn status_register_writes() {
+    let mut video = create_video();
+    video.write_8(LCDStatusRegister, 0xFF);
+    assert_eq!(0xFE, video.read_8(LCDStatusRegister));
+
+    video.write_8(LCDStatusRegister, 0x00);
+    assert_eq!(0x86, video.read_8(LCDStatusRegister));
+}
+
+#[test]
+fn cpu_access_blocking() {
+    let mut video = create_video();
+    assert!(!video.is_cpu_access_blocked(0x8000));
+    assert!(video.is_cpu_access_blocked(0xFE00));
+
+    run_dots(&mut video, 80);
+    assert!(video.is_cpu_access_blocked(0x9FFF));
+    assert!(video.is_cpu_access_blocked(0xFE9F));
+    assert!(!video.is_cpu_access_blocked(0xFEA0));
+    assert!(!video.is_cpu_access_blocked(0xC000));
+
+    run_dots(&mut video, 172);
+    assert!(!video.is_cpu_access_blocked(0x8000));
+    assert!(!video.is_cpu_access_blocked(0xFE00));
+}
+
+#[test]
+fn turning_lcd_off_resets_ly() {
+    let mut video = create_video();
+    run_dots(&mut video, 456 * 10 + 100);
+    assert_eq!(10, video.read_8(CurrentScanline));
+
+    video.write_8(LCDControlRegister, 0x11);
+    assert_eq!(0, video.read_8(CurrentScanline));
+    assert_eq!(0, get_mode(&video));
+    assert!(!video.is_cpu_access_blocked(0xFE00));
+
+    video.write_8(LCDControlRegister, 0x91);
+    assert_eq!(2, get_mode(&video));
+}
+
+#[test]
+fn window_line_only_advances_when_drawn() {
+    let mut video = VideoController::new(DeviceType::GameBoy);
+    video.write_8(WindowX, 7);
+    video.write_8(WindowY, 1);
+    video.write_8(LCDControlRegister, 0xB1);
+
+    run_dots(&mut video, 300);
+    assert_eq!(None, video.get_window_line());
+
+    run_dots(&mut video, 456);
+    assert_eq!(Some(0), video.get_window_line());
+
+    // Off the right edge of the screen, the window isn't drawn.
+    video.write_8(WindowX, 167);
+    run_dots(&mut video, 456);
+    assert_eq!(None, video.get_window_line());
+
+    video.write_8(WindowX, 100);
+    run_dots(&mut video, 456);
+    assert_eq!(Some(1), video.get_window_line());
+
+    // The counter starts over with the next frame.
+    run_dots(&mut video, 456 * 154);
+    assert_eq!(Some(2), video.get_window_line());
+    run_dots(&mut video, 456 * 152);
+    assert_eq!(Some(0), video.get_window_line());
+}
+
+#[test]
+fn pixel_transfer_state_rejects_invalid_sprite_counts() {
+    let mut transfer = PixelTransfer::new();
+    transfer.start_line(0, &[8, 16, 24]);
+
+    let mut writer = StateWriter::new();
+    transfer.write_state(&mut writer);
+    let state = writer.into_bytes();
+
+    // The object count and the index of the next object come right before the last three bytes.
+    let sprite_count = state.len() - 5;
+    let next_sprite = state.len() - 4;
+
+    let load = |offset: usize, value: u8| {
+        let mut state = state.clone();
+        state[offset] = value;
+        let mut reader = StateReader::new(&state).unwrap();
+        PixelTransfer::new().read_state(&mut reader)
+    };
+
+    assert!(load(sprite_count, 10).is_ok());
+    assert!(load(sprite_count, 11).is_err());
+    assert!(load(next_sprite, 3).is_ok());
+    assert!(load(next_sprite, 4).is_err());
+}
@@ -0,0 +1,166 @@
+extern crate rgbemu;
+use rgbemu::emulation::device::ExecutionState;
+use rgbemu::emulation::registers::StatusFlag;
+
+mod common;
+use common::{load_program, run_program};
+
+#[test]
+fn subtract_with_borrow() {
+    let device = run_program(&[
+        0x3E, 0x10, // LD A, 0x10
+        0x37, // SCF
+        0xDE, 0x01, // SBC A, 0x01
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0x0E, device.regs.a);
+    assert_eq!(false, device.regs.get_flag(StatusFlag::Z));
+    assert_eq!(true, device.regs.get_flag(StatusFlag::N));
+    assert_eq!(true, device.regs.get_flag(StatusFlag::H));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::C));
+}
+
+#[test]
+fn subtract_a_from_itself_with_borrow() {
+    let device = run_program(&[
+        0x3E, 0x80, // LD A, 0x80
+        0x37, // SCF
+        0x9F, // SBC A, A
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0xFF, device.regs.a);
+    assert_eq!(true, device.regs.get_flag(StatusFlag::H));
+    assert_eq!(true, device.regs.get_flag(StatusFlag::C));
+}
+
+#[test]
+fn subtract_without_borrow() {
+    let device = run_program(&[
+        0x3E, 0x05, // LD A, 0x05
+        0x37, // SCF
+        0x3F, // CCF
+        0x06, 0x05, // LD B, 0x05
+        0x98, // SBC A, B
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0x00, device.regs.a);
+    assert_eq!(true, device.regs.get_flag(StatusFlag::Z));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::H));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::C));
+}
+
+#[test]
+fn add_with_carry_sets_half_carry() {
+    let device = run_program(&[
+        0x3E, 0x0F, // LD A, 0x0F
+        0x37, // SCF
+        0xCE, 0x00, // ADC A, 0x00
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0x10, device.regs.a);
+    assert_eq!(false, device.regs.get_flag(StatusFlag::Z));
+    assert_eq!(true, device.regs.get_flag(StatusFlag::H));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::C));
+}
+
+#[test]
+fn shift_right_arithmetic() {
+    let device = run_program(&[
+        0x97, // SUB A
+        0x06, 0x81, // LD B, 0x81
+        0xCB, 0x28, // SRA B
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0xC0, device.regs.b);
+    assert_eq!(true, device.regs.get_flag(StatusFlag::C));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::Z));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::N));
+}
+
+#[test]
+fn shift_right_logical() {
+    let device = run_program(&[
+        0x97, // SUB A
+        0x06, 0x81, // LD B, 0x81
+        0xCB, 0x38, // SRL B
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0x40, device.regs.b);
+    assert_eq!(true, device.regs.get_flag(StatusFlag::C));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::Z));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::N));
+}
+
+#[test]
+fn shift_left_arithmetic() {
+    let device = run_program(&[
+        0x97, // SUB A
+        0x06, 0x81, // LD B, 0x81
+        0xCB, 0x20, // SLA B
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0x02, device.regs.b);
+    assert_eq!(true, device.regs.get_flag(StatusFlag::C));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::Z));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::N));
+}
+
+#[test]
+fn add_negative_immediate_to_sp() {
+    let device = run_program(&[
+        0x31, 0x00, 0xD0, // LD SP, 0xD000
+        0xE8, 0xFE, // ADD SP, -2
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0xCFFE, device.regs.sp);
+    assert_eq!(false, device.regs.get_flag(StatusFlag::H));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::C));
+}
+
+#[test]
+fn add_immediate_to_sp_carry() {
+    let device = run_program(&[
+        0x31, 0xFF, 0xD0, // LD SP, 0xD0FF
+        0xE8, 0x01, // ADD SP, 1
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0xD100, device.regs.sp);
+    assert_eq!(false, device.regs.get_flag(StatusFlag::Z));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::N));
+    assert_eq!(true, device.regs.get_flag(StatusFlag::H));
+    assert_eq!(true, device.regs.get_flag(StatusFlag::C));
+}
+
+#[test]
+fn illegal_opcode_locks_up() {
+    let mut device = load_program(&[
+        0xFB, // EI
+        0xD3, // Illegal
+        0x00
+    ]);
+
+    device.run_tick();
+    device.run_tick();
+    assert_eq!(ExecutionState::Locked, device.execution_state);
+
+    // Pending interrupts don't wake a locked up CPU.
+    device.write_addr_8(0xFFFF, 0x1F);
+    device.write_addr_8(0xFF0F, 0x1F);
+    let pc = device.regs.pc;
+
+    for _ in 0..1000 {
+        device.run_tick();
+    }
+
+    assert_eq!(ExecutionState::Locked, device.execution_state);
+    assert_eq!(pc, device.regs.pc);
+}
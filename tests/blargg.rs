@@ -0,0 +1,83 @@
+extern crate rgbemu;
+
+mod common;
+use common::load_program;
+use common::test_roms::*;
+
+// The combined cpu_instrs ROM needs almost a minute of emulated time.
+const CYCLE_BUDGET_SECONDS: u64 = 70;
+
+fn run_suite(suite: &str) {
+    let dir = match get_test_rom_dir(suite) {
+        Some(dir) => dir,
+        None => return
+    };
+
+    let outcomes: Vec<TestRomOutcome> = find_roms(&dir)
+        .iter()
+        .map(|rom| run_blargg_rom(rom, seconds_to_cycles(CYCLE_BUDGET_SECONDS)))
+        .collect();
+
+    let report = format_outcomes(&outcomes);
+    println!("{}", report);
+
+    assert!(
+        outcomes
+            .iter()
+            .all(|outcome| outcome.result == TestRomResult::Passed),
+        "{} failed:\n{}",
+        suite,
+        report
+    );
+}
+
+// Prints a message over the serial port the same way blargg's ROMs do, then loops forever.
+fn create_serial_program(message: &str) -> Vec<u8> {
+    let mut program = vec![
+        0x21, 0x11, 0x01, // LD HL, message
+        0x2A, // LD A, (HL+)
+        0xB7, // OR A
+        0x28, 0x08, // JR Z, +8
+        0xE0, 0x01, // LDH (SB), A
+        0x3E, 0x81, // LD A, 0x81
+        0xE0, 0x02, // LDH (SC), A
+        0x18, 0xF4, // JR -12
+        0x18, 0xFE, // JR -2
+    ];
+
+    program.extend_from_slice(message.as_bytes());
+    program.push(0);
+    program
+}
+
+#[test]
+fn harness_reads_serial_output() {
+    let passing = load_program(&create_serial_program("cpu_instrs\n\nPassed\n"));
+    let outcome = run_blargg_device(passing, seconds_to_cycles(1));
+    assert_eq!(TestRomResult::Passed, outcome.result);
+    assert_eq!("cpu_instrs\n\nPassed", outcome.output.trim_end());
+
+    let failing = load_program(&create_serial_program("Failed #2\n"));
+    let outcome = run_blargg_device(failing, seconds_to_cycles(2));
+    assert_eq!(TestRomResult::Failed, outcome.result);
+
+    let silent = load_program(&create_serial_program(""));
+    let outcome = run_blargg_device(silent, 10_000);
+    assert_eq!(TestRomResult::TimedOut, outcome.result);
+    assert!(outcome.cycles >= 10_000);
+}
+
+#[test]
+fn cpu_instrs() {
+    run_suite("cpu_instrs");
+}
+
+#[test]
+fn instr_timing() {
+    run_suite("instr_timing");
+}
+
+#[test]
+fn mem_timing() {
+    run_suite("mem_timing");
+}
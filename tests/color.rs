@@ -0,0 +1,222 @@
+extern crate rgbemu;
+use rgbemu::emulation::device::{Device, DeviceType, ExecutionState};
+
+mod common;
+use common::{load_program, load_program_on, read_address};
+
+#[test]
+fn boot_state_identifies_the_color_hardware() {
+    let device = load_program_on(DeviceType::GameBoyColor, &[0x00]);
+    assert_eq!(0x11, device.regs.a);
+
+    let device = load_program(&[0x00]);
+    assert_eq!(0x01, device.regs.a);
+}
+
+#[test]
+fn switches_work_ram_banks() {
+    let mut device = load_program_on(DeviceType::GameBoyColor, &[0x00]);
+
+    for bank in 1..8 {
+        device.write_addr_8(0xFF70, bank);
+        device.write_addr_8(0xD000, bank * 0x10);
+    }
+
+    device.write_addr_8(0xFF70, 3);
+    assert_eq!(0xFB, read_address(&device, 0xFF70));
+    assert_eq!(0x30, read_address(&device, 0xD000));
+    // Echo RAM mirrors the selected bank as well.
+    assert_eq!(0x30, read_address(&device, 0xF000));
+
+    // Selecting bank 0 selects bank 1.
+    device.write_addr_8(0xFF70, 0);
+    assert_eq!(0xF9, read_address(&device, 0xFF70));
+    assert_eq!(0x10, read_address(&device, 0xD000));
+
+    device.write_addr_8(0xFF70, 7);
+    assert_eq!(0x70, read_address(&device, 0xD000));
+}
+
+#[test]
+fn switches_vram_banks() {
+    let mut device = load_program_on(DeviceType::GameBoyColor, &[0x00]);
+    device.write_addr_8(0xFF40, 0x00);
+
+    device.write_addr_8(0x8000, 0x12);
+    device.write_addr_8(0xFF4F, 0x01);
+    assert_eq!(0xFF, read_address(&device, 0xFF4F));
+    assert_eq!(0x00, read_address(&device, 0x8000));
+    device.write_addr_8(0x8000, 0x34);
+
+    device.write_addr_8(0xFF4F, 0x00);
+    assert_eq!(0xFE, read_address(&device, 0xFF4F));
+    assert_eq!(0x12, read_address(&device, 0x8000));
+    assert_eq!(0x12, device.bus.video.get_vram_bank(0)[0]);
+    assert_eq!(0x34, device.bus.video.get_vram_bank(1)[0]);
+}
+
+#[test]
+fn banking_registers_are_absent_on_dmg() {
+    let mut device = load_program(&[0x00]);
+    device.write_addr_8(0xFF40, 0x00);
+
+    device.write_addr_8(0xD000, 0x12);
+    device.write_addr_8(0xFF70, 2);
+    device.write_addr_8(0xFF4F, 1);
+    device.write_addr_8(0xFF4D, 1);
+
+    assert_eq!(0xFF, read_address(&device, 0xFF70));
+    assert_eq!(0xFF, read_address(&device, 0xFF4F));
+    assert_eq!(0xFF, read_address(&device, 0xFF4D));
+    assert_eq!(0x12, read_address(&device, 0xD000));
+}
+
+#[test]
+fn stop_switches_speed_once_armed() {
+    let mut device = load_program_on(
+        DeviceType::GameBoyColor,
+        &[
+            0x10, 0x00, // STOP
+            0x00, // NOP
+            0x10, 0x00, // STOP
+            0x00
+        ]
+    );
+    assert_eq!(0x7E, read_address(&device, 0xFF4D));
+
+    device.write_addr_8(0xFF4D, 0x01);
+    assert_eq!(0x7F, read_address(&device, 0xFF4D));
+
+    device.run_tick();
+    assert_eq!(ExecutionState::Running, device.execution_state);
+    assert!(device.bus.is_double_speed);
+    assert_eq!(0xFE, read_address(&device, 0xFF4D));
+
+    // Instructions take half as long, measured in normal speed cycles.
+    assert_eq!(2, device.run_tick());
+
+    // Without arming the switch again, STOP stops as usual.
+    device.run_tick();
+    assert_eq!(ExecutionState::Stopped, device.execution_state);
+    assert!(device.bus.is_double_speed);
+}
+
+#[test]
+fn double_speed_keeps_the_ppu_at_its_pace() {
+    let mut device = load_program_on(DeviceType::GameBoyColor, &[0x10, 0x00, 0x18, 0xFE]);
+    device.write_addr_8(0xFF4D, 0x01);
+    device.run_tick();
+
+    // Restart the frame, and run a whole line worth of machine cycles.
+    device.write_addr_8(0xFF40, 0x00);
+    device.write_addr_8(0xFF40, 0x91);
+    device.write_addr_8(0xFF04, 0x00);
+
+    let mut cycles = 0;
+    while cycles < 456 {
+        cycles += device.run_tick();
+    }
+
+    assert_eq!(1, read_address(&device, 0xFF44));
+    // The divider runs along with the CPU.
+    assert_eq!(3, read_address(&device, 0xFF04));
+}
+
+fn set_vram_dma_addresses(device: &mut Device, source: u16, destination: u16) {
+    device.write_addr_8(0xFF51, (source >> 8) as u8);
+    device.write_addr_8(0xFF52, source as u8);
+    device.write_addr_8(0xFF53, (destination >> 8) as u8);
+    device.write_addr_8(0xFF54, destination as u8);
+}
+
+fn fill_work_ram(device: &mut Device, length: u16) {
+    for i in 0..length {
+        device.write_addr_8(0xC000 + i, i as u8 + 1);
+    }
+}
+
+#[test]
+fn general_purpose_dma_stops_the_cpu() {
+    let mut device = load_program_on(
+        DeviceType::GameBoyColor,
+        &[
+            0x3E, 0x01, // LD A, 1
+            0xE0, 0x55, // LDH (HDMA5), A
+            0x00
+        ]
+    );
+    device.write_addr_8(0xFF40, 0x00);
+    fill_work_ram(&mut device, 0x20);
+    set_vram_dma_addresses(&mut device, 0xC000, 0x8100);
+
+    device.run_tick();
+    assert_eq!(12, device.run_tick());
+    let pc = device.regs.pc;
+
+    // The CPU then waits eight machine cycles for each block.
+    assert_eq!(8 * 4, device.run_tick());
+    assert_eq!(0x10, read_address(&device, 0x810F));
+    assert_eq!(0x00, read_address(&device, 0x8110));
+    assert_eq!(8 * 4, device.run_tick());
+    assert_eq!(pc, device.regs.pc);
+
+    for i in 0..0x20 {
+        assert_eq!(i as u8 + 1, read_address(&device, 0x8100 + i));
+    }
+    assert_eq!(0x00, read_address(&device, 0x8120));
+    assert_eq!(0xFF, read_address(&device, 0xFF55));
+}
+
+#[test]
+fn hblank_dma_copies_a_block_per_line() {
+    let mut device = load_program_on(DeviceType::GameBoyColor, &[0x18, 0xFE]);
+    fill_work_ram(&mut device, 0x30);
+    set_vram_dma_addresses(&mut device, 0xC000, 0x9000);
+
+    device.write_addr_8(0xFF55, 0x82);
+    assert_eq!(0x02, read_address(&device, 0xFF55));
+
+    while read_address(&device, 0xFF44) != 1 {
+        device.run_tick();
+    }
+
+    assert_eq!(0x01, read_address(&device, 0xFF55));
+    assert_eq!(0x10, read_address(&device, 0x900F));
+    assert_eq!(0x00, read_address(&device, 0x9010));
+
+    // Cancelling leaves the number of blocks that were left.
+    device.write_addr_8(0xFF55, 0x00);
+    assert_eq!(0x81, read_address(&device, 0xFF55));
+
+    while read_address(&device, 0xFF44) != 3 {
+        device.run_tick();
+    }
+
+    assert_eq!(0x00, read_address(&device, 0x9010));
+}
+
+#[test]
+fn hblank_dma_with_lcd_off_copies_a_block_right_away() {
+    let mut device = load_program_on(DeviceType::GameBoyColor, &[0x18, 0xFE]);
+    device.write_addr_8(0xFF40, 0x00);
+    fill_work_ram(&mut device, 0x20);
+    set_vram_dma_addresses(&mut device, 0xC000, 0x9000);
+
+    device.write_addr_8(0xFF55, 0x81);
+    assert_eq!(8 * 4, device.run_tick());
+
+    assert_eq!(0x00, read_address(&device, 0xFF55));
+    assert_eq!(0x10, read_address(&device, 0x900F));
+    assert_eq!(0x00, read_address(&device, 0x9010));
+
+    // The rest waits for H-blank.
+    device.run_tick();
+    assert_eq!(0x00, read_address(&device, 0x9010));
+}
+
+#[test]
+fn vram_dma_is_absent_on_dmg() {
+    let mut device = load_program(&[0x00]);
+    device.write_addr_8(0xFF55, 0x00);
+    assert_eq!(0xFF, read_address(&device, 0xFF55));
+}
@@ -1,8 +1,21 @@
+#![allow(dead_code)]
+
+pub mod test_roms;
+
 use rgbemu::emulation::address_mapper::AddressMapper;
 use rgbemu::emulation::cartridge::{Cartridge, CartridgeHeader, CartridgeMemory, CartridgeType};
 use rgbemu::emulation::device::{Device, DeviceType, ExecutionState};
 use rgbemu::emulation::mappers::Mapper;
 
+// Increments 0xC000 and B forever.
+pub const COUNTER_PROGRAM: [u8; 8] = [
+    0x21, 0x00, 0xC0, // LD HL, 0xC000
+    0x34, // INC (HL)
+    0x04, // INC B
+    0x18, 0xFC, // JR -4
+    0x00
+];
+
 fn create_test_cartridge(rom: &[u8]) -> Cartridge {
     let mut memory = CartridgeMemory::new(16384, 0);
     memory.rom[0x100..0x100 + rom.len()].copy_from_slice(rom);
@@ -24,10 +37,23 @@ fn create_test_cartridge(rom: &[u8]) -> Cartridge {
     }
 }
 
-pub fn run_program(code: &[u8]) -> Device {
-    let mut device = Device::new(DeviceType::GameBoy, None);
+pub fn load_program(code: &[u8]) -> Device {
+    load_program_on(DeviceType::GameBoy, code)
+}
+
+pub fn load_program_on(device_type: DeviceType, code: &[u8]) -> Device {
+    let mut device = Device::new(device_type, None);
     let cartridge = create_test_cartridge(code);
     device.bus.cartridge = Some(cartridge);
+    device
+}
+
+pub fn run_program(code: &[u8]) -> Device {
+    run_program_on(DeviceType::GameBoy, code)
+}
+
+pub fn run_program_on(device_type: DeviceType, code: &[u8]) -> Device {
+    let mut device = load_program_on(device_type, code);
 
     while device.execution_state != ExecutionState::Halted {
         println!("{:?}", device.regs);
@@ -37,6 +63,12 @@ pub fn run_program(code: &[u8]) -> Device {
     device
 }
 
+pub fn run_ticks(device: &mut Device, ticks: usize) {
+    for _ in 0..ticks {
+        device.run_tick();
+    }
+}
+
 pub fn read_address(device: &Device, address: u16) -> u8 {
     device.bus.read_8(device.bus.resolve_address(address))
 }
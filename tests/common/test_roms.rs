@@ -0,0 +1,223 @@
+use std::env;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use rgbemu::emulation::cartridge::Cartridge;
+use rgbemu::emulation::constants::GB_CYCLES_PER_SEC;
+use rgbemu::emulation::device::Device;
+
+// Test ROMs aren't redistributable, so they are looked up from RGBEMU_TEST_ROMS or ./test_roms,
+// and the suites are skipped when they can't be found.
+const TEST_ROM_DIR_VARIABLE: &str = "RGBEMU_TEST_ROMS";
+const DEFAULT_TEST_ROM_DIR: &str = "test_roms";
+
+pub fn get_test_rom_dir(suite: &str) -> Option<PathBuf> {
+    let base = env::var_os(TEST_ROM_DIR_VARIABLE)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_TEST_ROM_DIR));
+    let dir = base.join(suite);
+
+    if dir.is_dir() {
+        Some(dir)
+    } else {
+        println!(
+            "Skipping {}: {} not found (set {} to the test ROM directory)",
+            suite,
+            dir.display(),
+            TEST_ROM_DIR_VARIABLE
+        );
+        None
+    }
+}
+
+// All .gb files under the directory, recursively, in a stable order.
+pub fn find_roms(dir: &Path) -> Vec<PathBuf> {
+    let mut roms = Vec::new();
+
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            roms.extend(find_roms(&path));
+        } else if path.extension().is_some_and(|extension| extension == "gb") {
+            roms.push(path);
+        }
+    }
+
+    roms.sort();
+    roms
+}
+
+pub fn load_rom(path: &Path) -> Device {
+    let rom = fs::read(path).unwrap();
+    let cartridge = Cartridge::from_bytes(&rom).unwrap();
+
+    let mut device = Device::new(cartridge.header.get_device_type(), None);
+    device.bus.cartridge = Some(cartridge);
+    device
+}
+
+// Nothing is rendered, but the renderer messages would otherwise pile up.
+pub fn run_tick_headless(device: &mut Device) -> u64 {
+    let cycles = device.run_tick() as u64;
+    while device.next_renderer_message().is_some() {}
+    cycles
+}
+
+pub fn seconds_to_cycles(seconds: u64) -> u64 {
+    seconds * GB_CYCLES_PER_SEC as u64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestRomResult {
+    Passed,
+    Failed,
+    TimedOut,
+    Crashed
+}
+
+pub struct TestRomOutcome {
+    pub path: PathBuf,
+    pub result: TestRomResult,
+    pub output: String,
+    pub cycles: u64
+}
+
+// Blargg's ROMs print their progress over the serial port and finish with "Passed" or
+// "Failed", followed by details about the failed tests.
+pub fn run_blargg_rom(path: &Path, cycle_budget: u64) -> TestRomOutcome {
+    run_rom(path, |device| run_blargg_device(device, cycle_budget))
+}
+
+// Unimplemented features panic, so a crash is reported as a result of its own instead of
+// taking the rest of the suite down with it.
+fn run_rom(path: &Path, run: impl FnOnce(Device) -> TestRomOutcome) -> TestRomOutcome {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| run(load_rom(path))));
+
+    let mut outcome = result.unwrap_or_else(|error| {
+        let message = error
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| {
+                error
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+            })
+            .unwrap_or_default();
+
+        TestRomOutcome {
+            path: PathBuf::new(),
+            result: TestRomResult::Crashed,
+            output: message,
+            cycles: 0
+        }
+    });
+
+    outcome.path = path.to_owned();
+    outcome
+}
+
+fn get_serial_output(device: &Device) -> &[u8] {
+    device.bus.serial_output.as_deref().unwrap_or(&[])
+}
+
+pub fn run_blargg_device(mut device: Device, cycle_budget: u64) -> TestRomOutcome {
+    device.bus.serial_output = Some(Vec::new());
+
+    let mut cycles = 0u64;
+    let mut checked_length = 0;
+    let mut result = TestRomResult::TimedOut;
+
+    while cycles < cycle_budget {
+        cycles += run_tick_headless(&mut device);
+
+        let serial_output = get_serial_output(&device);
+        if serial_output.len() == checked_length {
+            continue;
+        }
+
+        checked_length = serial_output.len();
+        let output = String::from_utf8_lossy(serial_output);
+
+        if output.contains("Passed") {
+            result = TestRomResult::Passed;
+            break;
+        }
+
+        if output.contains("Failed") {
+            // Let the ROM finish listing what went wrong.
+            result = TestRomResult::Failed;
+            let failed_at = cycles;
+            while cycles < failed_at + seconds_to_cycles(1) {
+                cycles += run_tick_headless(&mut device);
+            }
+            break;
+        }
+    }
+
+    TestRomOutcome {
+        path: PathBuf::new(),
+        result,
+        output: String::from_utf8_lossy(get_serial_output(&device)).into_owned(),
+        cycles
+    }
+}
+
+pub fn run_mooneye_rom(path: &Path, cycle_budget: u64) -> TestRomOutcome {
+    run_rom(path, |device| run_mooneye_device(device, cycle_budget))
+}
+
+// Mooneye's ROMs execute LD B, B when they finish. A passing test leaves the first Fibonacci
+// numbers in the registers, and a failing one fills them with 0x42.
+pub fn run_mooneye_device(mut device: Device, cycle_budget: u64) -> TestRomOutcome {
+    let mut cycles = 0u64;
+    device.software_breakpoint_hit = false;
+
+    while cycles < cycle_budget && !device.software_breakpoint_hit {
+        cycles += run_tick_headless(&mut device);
+    }
+
+    let regs = &device.regs;
+    let signature = [regs.b, regs.c, regs.d, regs.e, regs.h, regs.l];
+
+    let result = if !device.software_breakpoint_hit {
+        TestRomResult::TimedOut
+    } else if signature == [3, 5, 8, 13, 21, 34] {
+        TestRomResult::Passed
+    } else {
+        TestRomResult::Failed
+    };
+
+    TestRomOutcome {
+        path: PathBuf::new(),
+        result,
+        output: format!("{:?}", signature),
+        cycles
+    }
+}
+
+pub fn format_outcomes(outcomes: &[TestRomOutcome]) -> String {
+    let mut report = String::new();
+
+    for outcome in outcomes {
+        report.push_str(&format!(
+            "{:?}\t{}\n",
+            outcome.result,
+            outcome.path.display()
+        ));
+
+        if outcome.result != TestRomResult::Passed {
+            for line in outcome.output.lines() {
+                report.push_str(&format!("\t\t{}\n", line));
+            }
+        }
+    }
+
+    let passed = outcomes
+        .iter()
+        .filter(|outcome| outcome.result == TestRomResult::Passed)
+        .count();
+    report.push_str(&format!("Passed {}/{}\n", passed, outcomes.len()));
+
+    report
+}
@@ -0,0 +1,182 @@
+extern crate rgbemu;
+
+mod common;
+use common::{load_program, load_program_on};
+
+use rgbemu::emulation::constants::{LIGHTEST_GREEN, LIGHT_GREEN, SCREEN_HEIGHT, SCREEN_WIDTH};
+use rgbemu::emulation::device::{Device, DeviceType};
+use rgbemu::rendering::framebuffer_renderer::FramebufferRenderer;
+use rgbemu::rendering::Renderer;
+
+// Sets an identity background palette and gives tile 0 a solid first row of color 1. The
+// background map is all zeroes, so every eighth line of the screen is filled.
+const STRIPES_PROGRAM: [u8; 12] = [
+    0x3E, 0xE4, // LD A, 0xE4
+    0xE0, 0x47, // LDH (BGP), A
+    0x21, 0x00, 0x80, // LD HL, 0x8000
+    0x3E, 0xFF, // LD A, 0xFF
+    0x22, // LD (HL+), A
+    0x18, 0xFE // JR -2
+];
+
+fn run_frames(device: &mut Device, renderer: &mut FramebufferRenderer, frames: u64) {
+    while renderer.get_frame_count() < frames {
+        device.run_tick();
+
+        while let Some(message) = device.next_renderer_message() {
+            renderer.handle_message(device, message);
+        }
+    }
+}
+
+#[test]
+fn renders_background_to_framebuffer() {
+    let mut device = load_program(&STRIPES_PROGRAM);
+    let mut renderer = FramebufferRenderer::new();
+    run_frames(&mut device, &mut renderer, 2);
+
+    assert_eq!(
+        (SCREEN_WIDTH * SCREEN_HEIGHT) as usize,
+        renderer.get_shades().len()
+    );
+
+    for y in 0..SCREEN_HEIGHT {
+        let expected = if y % 8 == 0 { 1 } else { 0 };
+        for x in 0..SCREEN_WIDTH {
+            assert_eq!(expected, renderer.get_shade(x, y), "Pixel {}, {}", x, y);
+        }
+    }
+
+    let rgba = renderer.get_rgba();
+    assert_eq!(
+        [LIGHT_GREEN.0, LIGHT_GREEN.1, LIGHT_GREEN.2, 255],
+        rgba[0..4]
+    );
+
+    let second_line = (SCREEN_WIDTH * 4) as usize;
+    assert_eq!(
+        [LIGHTEST_GREEN.0, LIGHTEST_GREEN.1, LIGHTEST_GREEN.2, 255],
+        rgba[second_line..second_line + 4]
+    );
+}
+
+// Fills the window map with a tile of color 3 and shows the window over the lower right
+// quarter of the screen, on top of a blank background.
+#[rustfmt::skip]
+const WINDOW_PROGRAM: [u8; 41] = [
+    0xAF, // XOR A
+    0xE0, 0x40, // LDH (LCDC), A
+    0x21, 0x10, 0x80, // LD HL, 0x8010
+    0x3E, 0xFF, // LD A, 0xFF
+    0x22, // LD (HL+), A
+    0xCB, 0x65, // BIT 4, L
+    0x20, 0xFB, // JR NZ, -5
+    0x21, 0x00, 0x9C, // LD HL, 0x9C00
+    0x3E, 0x01, // LD A, 1
+    0x22, // LD (HL+), A
+    0xCB, 0x6C, // BIT 5, H
+    0x28, 0xFB, // JR Z, -5
+    0x3E, 0xE4, // LD A, 0xE4
+    0xE0, 0x47, // LDH (BGP), A
+    0x3E, 72, // LD A, 72
+    0xE0, 0x4A, // LDH (WY), A
+    0x3E, 87, // LD A, 87
+    0xE0, 0x4B, // LDH (WX), A
+    0x3E, 0xF1, // LD A, 0xF1
+    0xE0, 0x40, // LDH (LCDC), A
+    0x18, 0xFE // JR -2
+];
+
+#[test]
+fn renders_window_to_framebuffer() {
+    let mut device = load_program(&WINDOW_PROGRAM);
+    let mut renderer = FramebufferRenderer::new();
+    run_frames(&mut device, &mut renderer, 2);
+
+    for y in 0..SCREEN_HEIGHT {
+        for x in 0..SCREEN_WIDTH {
+            let expected = if x >= 80 && y >= 72 { 3 } else { 0 };
+            assert_eq!(expected, renderer.get_shade(x, y), "Pixel {}, {}", x, y);
+        }
+    }
+}
+
+// Clears LCDC bit 4, so map entry 0 refers to the tile at 0x9000, and fills that tile with
+// color 3.
+#[rustfmt::skip]
+const SIGNED_TILES_PROGRAM: [u8; 23] = [
+    0xAF, // XOR A
+    0xE0, 0x40, // LDH (LCDC), A
+    0x21, 0x00, 0x90, // LD HL, 0x9000
+    0x3E, 0xFF, // LD A, 0xFF
+    0x22, // LD (HL+), A
+    0xCB, 0x65, // BIT 4, L
+    0x28, 0xFB, // JR Z, -5
+    0x3E, 0xE4, // LD A, 0xE4
+    0xE0, 0x47, // LDH (BGP), A
+    0x3E, 0x81, // LD A, 0x81
+    0xE0, 0x40, // LDH (LCDC), A
+    0x18, 0xFE // JR -2
+];
+
+#[test]
+fn renders_signed_tile_indices() {
+    let mut device = load_program(&SIGNED_TILES_PROGRAM);
+    let mut renderer = FramebufferRenderer::new();
+    run_frames(&mut device, &mut renderer, 2);
+
+    assert!(renderer.get_shades().iter().all(|&shade| shade == 3));
+}
+
+// Makes background palette 1 red and blue, and uses it for the first map entry, flipped
+// horizontally through its attributes in VRAM bank 1. Only the first pixel of tile 0 isn't
+// color 0, and palette 0 is left white.
+#[rustfmt::skip]
+const COLOR_PROGRAM: [u8; 43] = [
+    0xAF, // XOR A
+    0xE0, 0x40, // LDH (LCDC), A
+    0x3E, 0x88, // LD A, 0x88
+    0xE0, 0x68, // LDH (BCPS), A
+    0x3E, 0x1F, // LD A, 0x1F
+    0xE0, 0x69, // LDH (BCPD), A
+    0xAF, // XOR A
+    0xE0, 0x69, // LDH (BCPD), A
+    0xE0, 0x69, // LDH (BCPD), A
+    0x3E, 0x7C, // LD A, 0x7C
+    0xE0, 0x69, // LDH (BCPD), A
+    0x3E, 0x01, // LD A, 1
+    0xE0, 0x4F, // LDH (VBK), A
+    0x3E, 0x21, // LD A, 0x21
+    0xEA, 0x00, 0x98, // LD (0x9800), A
+    0xAF, // XOR A
+    0xE0, 0x4F, // LDH (VBK), A
+    0x3E, 0x80, // LD A, 0x80
+    0xEA, 0x00, 0x80, // LD (0x8000), A
+    0x3E, 0x91, // LD A, 0x91
+    0xE0, 0x40, // LDH (LCDC), A
+    0x18, 0xFE // JR -2
+];
+
+#[test]
+fn renders_color_background_with_attributes() {
+    let mut device = load_program_on(DeviceType::GameBoyColor, &COLOR_PROGRAM);
+    let mut renderer = FramebufferRenderer::new();
+    run_frames(&mut device, &mut renderer, 2);
+
+    let rgba = renderer.get_rgba();
+    let get_pixel = |x: u32, y: u32| {
+        let offset = ((y * SCREEN_WIDTH + x) * 4) as usize;
+        [rgba[offset], rgba[offset + 1], rgba[offset + 2]]
+    };
+
+    for y in 0..SCREEN_HEIGHT {
+        for x in 0..SCREEN_WIDTH {
+            let expected = match (x, y) {
+                (7, 0) => [0, 0, 255],
+                (0..=7, 0..=7) => [255, 0, 0],
+                _ => [255, 255, 255]
+            };
+            assert_eq!(expected, get_pixel(x, y), "Pixel {}, {}", x, y);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+extern crate rgbemu;
+
+mod common;
+use common::load_program;
+
+// Cycles taken by each opcode with all flags cleared, so NZ and NC branches are taken and
+// Z and C branches are not. Zero marks the CB prefix and the illegal opcodes.
+#[rustfmt::skip]
+const OPCODE_CYCLES: [u32; 256] = [
+    4, 12, 8, 8, 4, 4, 8, 4, 20, 8, 8, 8, 4, 4, 8, 4,
+    4, 12, 8, 8, 4, 4, 8, 4, 12, 8, 8, 8, 4, 4, 8, 4,
+    12, 12, 8, 8, 4, 4, 8, 4, 8, 8, 8, 8, 4, 4, 8, 4,
+    12, 12, 8, 8, 12, 12, 12, 4, 8, 8, 8, 8, 4, 4, 8, 4,
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4,
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4,
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4,
+    8, 8, 8, 8, 8, 8, 4, 8, 4, 4, 4, 4, 4, 4, 8, 4,
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4,
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4,
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4,
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4,
+    20, 12, 16, 16, 24, 16, 8, 16, 8, 16, 12, 0, 12, 24, 8, 16,
+    20, 12, 16, 0, 24, 16, 8, 16, 8, 16, 12, 0, 12, 0, 8, 16,
+    12, 12, 8, 0, 0, 16, 8, 16, 16, 4, 16, 0, 0, 0, 8, 16,
+    12, 12, 8, 4, 0, 16, 8, 16, 12, 8, 16, 4, 0, 0, 8, 16,
+];
+
+fn get_cb_opcode_cycles(opcode: u8) -> u32 {
+    match (opcode >> 6, opcode & 0x07) {
+        (1, 6) => 12,
+        (_, 6) => 16,
+        _ => 8
+    }
+}
+
+fn run_single_instruction(code: &[u8]) -> u32 {
+    let mut device = load_program(code);
+    device.regs.f = 0;
+    device.regs.set_hl(0xC000);
+    device.run_tick()
+}
+
+#[test]
+fn opcode_cycles() {
+    let mut mismatches = Vec::new();
+
+    for opcode in 0..=0xFFu8 {
+        let expected = OPCODE_CYCLES[opcode as usize];
+        if expected == 0 {
+            continue;
+        }
+
+        let cycles = run_single_instruction(&[opcode, 0x00, 0x00]);
+        if cycles != expected {
+            mismatches.push(format!(
+                "0x{:02X}: {} instead of {}",
+                opcode, cycles, expected
+            ));
+        }
+    }
+
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}
+
+#[test]
+fn cb_opcode_cycles() {
+    let mut mismatches = Vec::new();
+
+    for opcode in 0..=0xFFu8 {
+        let expected = get_cb_opcode_cycles(opcode);
+        let cycles = run_single_instruction(&[0xCB, opcode]);
+        if cycles != expected {
+            mismatches.push(format!(
+                "0xCB{:02X}: {} instead of {}",
+                opcode, cycles, expected
+            ));
+        }
+    }
+
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}
@@ -0,0 +1,201 @@
+extern crate rgbemu;
+use rgbemu::emulation::device::{Device, ExecutionState};
+use rgbemu::emulation::input::InputState;
+
+mod common;
+use common::{load_program, read_address};
+
+fn request_timer_interrupt(device: &mut Device) {
+    device.write_addr_8(0xFFFF, 0x04); // IE
+    device.write_addr_8(0xFF0F, 0x04); // IF
+}
+
+#[test]
+fn enable_interrupts_is_delayed_by_one_instruction() {
+    let mut device = load_program(&[
+        0xFB, // EI
+        0x04, // INC B
+        0x04, // INC B
+        0x00
+    ]);
+    request_timer_interrupt(&mut device);
+
+    device.run_tick();
+    assert_eq!(0x0101, device.regs.pc);
+    assert_eq!(false, device.interrupts_enabled);
+
+    // The instruction after EI still runs before the interrupt is dispatched.
+    device.run_tick();
+    assert_eq!(1, device.regs.b);
+    assert_eq!(0x0050, device.regs.pc);
+    assert_eq!(false, device.interrupts_enabled);
+}
+
+#[test]
+fn disable_interrupts_cancels_pending_enable() {
+    let mut device = load_program(&[
+        0xFB, // EI
+        0xF3, // DI
+        0x00, // NOP
+        0x00
+    ]);
+    request_timer_interrupt(&mut device);
+
+    for _ in 0..3 {
+        device.run_tick();
+    }
+
+    assert_eq!(0x0103, device.regs.pc);
+    assert_eq!(false, device.interrupts_enabled);
+}
+
+#[test]
+fn interrupt_dispatch_takes_twenty_cycles() {
+    let mut device = load_program(&[
+        0xFB, // EI
+        0x00, // NOP
+        0x00
+    ]);
+    request_timer_interrupt(&mut device);
+
+    device.run_tick();
+    let cycles = device.run_tick();
+
+    assert_eq!(4 + 20, cycles);
+    assert_eq!(0x0050, device.regs.pc);
+    assert_eq!(0x02, read_address(&device, device.regs.sp));
+    assert_eq!(0x01, read_address(&device, device.regs.sp + 1));
+}
+
+#[test]
+fn halt_wakes_up_with_interrupts_disabled() {
+    let mut device = load_program(&[
+        0x76, // HALT
+        0x04, // INC B
+        0x00
+    ]);
+    device.write_addr_8(0xFFFF, 0x04);
+
+    device.run_tick();
+    device.run_tick();
+    assert_eq!(ExecutionState::Halted, device.execution_state);
+
+    device.write_addr_8(0xFF0F, 0x04);
+    device.run_tick();
+    assert_eq!(ExecutionState::Running, device.execution_state);
+
+    device.run_tick();
+    assert_eq!(1, device.regs.b);
+    assert_eq!(0x0102, device.regs.pc);
+    // The interrupt is left pending since it was never serviced.
+    assert_eq!(0x04, read_address(&device, 0xFF0F) & 0x04);
+}
+
+#[test]
+fn halt_bug_reads_next_byte_twice() {
+    let mut device = load_program(&[
+        0x76, // HALT
+        0x04, // INC B
+        0x00
+    ]);
+    request_timer_interrupt(&mut device);
+
+    device.run_tick();
+    assert_eq!(ExecutionState::Running, device.execution_state);
+
+    device.run_tick();
+    device.run_tick();
+    assert_eq!(2, device.regs.b);
+    assert_eq!(0x0102, device.regs.pc);
+}
+
+#[test]
+fn pushing_pc_over_ie_cancels_dispatch() {
+    let mut device = load_program(&[
+        0xFB, // EI
+        0x00, // NOP
+        0x00
+    ]);
+    request_timer_interrupt(&mut device);
+    device.regs.sp = 0x0000;
+
+    device.run_tick();
+    device.run_tick();
+
+    // The high byte of PC (0x01) replaced IE, which disabled the timer interrupt.
+    assert_eq!(0x0000, device.regs.pc);
+    assert_eq!(0x01, read_address(&device, 0xFFFF));
+}
+
+#[test]
+fn stop_waits_for_button_press() {
+    let mut device = load_program(&[
+        0x10, 0x00, // STOP
+        0x04, // INC B
+        0x00
+    ]);
+
+    device.run_tick();
+    assert_eq!(ExecutionState::Stopped, device.execution_state);
+
+    for _ in 0..100 {
+        device.run_tick();
+    }
+    assert_eq!(0x0102, device.regs.pc);
+
+    device.update_input(InputState::default());
+    assert_eq!(ExecutionState::Stopped, device.execution_state);
+
+    device.update_input(InputState {
+        start: true,
+        ..InputState::default()
+    });
+    assert_eq!(ExecutionState::Running, device.execution_state);
+
+    device.run_tick();
+    assert_eq!(1, device.regs.b);
+}
+
+#[test]
+fn lyc_match_requests_stat_interrupt() {
+    let mut device = load_program(&[
+        0xFB, // EI
+        0x18, 0xFE // JR -2
+    ]);
+    device.write_addr_8(0xFFFF, 0x02); // IE
+    device.write_addr_8(0xFF45, 0x01); // LYC
+    device.write_addr_8(0xFF41, 0x40); // STAT
+
+    let mut cycles = 0;
+    while device.regs.pc != 0x0048 {
+        cycles += device.run_tick();
+        assert!(cycles < 1000, "No STAT interrupt after {} cycles", cycles);
+    }
+
+    assert_eq!(1, read_address(&device, 0xFF44));
+    assert!(cycles >= 456);
+}
+
+#[test]
+fn vram_is_inaccessible_while_drawing() {
+    // Line 0 starts drawing 80 dots in, so the store and the load both land in mode 3.
+    let mut program = vec![0x21, 0x00, 0x80]; // LD HL, 0x8000
+    program.extend_from_slice(&[0x00; 17]); // NOP
+    program.extend_from_slice(&[
+        0x36, 0x42, // LD (HL), 0x42
+        0x7E, // LD A, (HL)
+        0x18, 0xFE // JR -2
+    ]);
+
+    let mut device = load_program(&program);
+    device.write_addr_8(0x8000, 0x11);
+
+    let mut cycles = 0;
+    while device.regs.pc != 0x0100 + program.len() as u16 - 2 {
+        cycles += device.run_tick();
+    }
+
+    assert_eq!(12 + 17 * 4 + 12 + 8, cycles);
+    assert_eq!(0x11, read_address(&device, 0x8000));
+    assert_eq!(0xFF, device.regs.a);
+}
@@ -0,0 +1,58 @@
+extern crate rgbemu;
+
+mod common;
+use common::load_program;
+use common::test_roms::*;
+
+const CYCLE_BUDGET_SECONDS: u64 = 20;
+
+const PASSING_PROGRAM: [u8; 15] = [
+    0x06, 3, // LD B, 3
+    0x0E, 5, // LD C, 5
+    0x16, 8, // LD D, 8
+    0x1E, 13, // LD E, 13
+    0x26, 21, // LD H, 21
+    0x2E, 34,   // LD L, 34
+    0x40, // LD B, B
+    0x18, 0xFE // JR -2
+];
+
+const FAILING_PROGRAM: [u8; 15] = [
+    0x06, 0x42, // LD B, 0x42
+    0x0E, 0x42, // LD C, 0x42
+    0x16, 0x42, // LD D, 0x42
+    0x1E, 0x42, // LD E, 0x42
+    0x26, 0x42, // LD H, 0x42
+    0x2E, 0x42, // LD L, 0x42
+    0x40, // LD B, B
+    0x18, 0xFE // JR -2
+];
+
+#[test]
+fn harness_checks_register_signature() {
+    let outcome = run_mooneye_device(load_program(&PASSING_PROGRAM), 10_000);
+    assert_eq!(TestRomResult::Passed, outcome.result);
+
+    let outcome = run_mooneye_device(load_program(&FAILING_PROGRAM), 10_000);
+    assert_eq!(TestRomResult::Failed, outcome.result);
+
+    let outcome = run_mooneye_device(load_program(&[0x18, 0xFE]), 10_000);
+    assert_eq!(TestRomResult::TimedOut, outcome.result);
+}
+
+// Not every test is expected to pass yet, so this only reports the results. Run with
+// `cargo test --test mooneye -- --nocapture` to see the table.
+#[test]
+fn acceptance_report() {
+    let dir = match get_test_rom_dir("mooneye") {
+        Some(dir) => dir,
+        None => return
+    };
+
+    let outcomes: Vec<TestRomOutcome> = find_roms(&dir)
+        .iter()
+        .map(|rom| run_mooneye_rom(rom, seconds_to_cycles(CYCLE_BUDGET_SECONDS)))
+        .collect();
+
+    println!("{}", format_outcomes(&outcomes));
+}
@@ -0,0 +1,95 @@
+extern crate rgbemu;
+use rgbemu::emulation::device::Device;
+
+mod common;
+use common::{load_program, read_address};
+
+// Fills a page of work RAM with bytes counting up from `first`, and turns off the LCD so it
+// doesn't block OAM.
+fn create_device(page: u8, first: u8) -> Device {
+    let mut device = load_program(&[0x00]);
+    device.write_addr_8(0xFF40, 0x00);
+    fill_page(&mut device, page, first);
+    device
+}
+
+fn fill_page(device: &mut Device, page: u8, first: u8) {
+    for i in 0..0xA0 {
+        device.write_addr_8(((page as u16) << 8) + i, first.wrapping_add(i as u8));
+    }
+}
+
+fn run_cycles(device: &mut Device, m_cycles: u32) {
+    for _ in 0..m_cycles {
+        device.tick_m_cycle();
+    }
+}
+
+#[test]
+fn transfer_takes_160_cycles_after_setup() {
+    let mut device = create_device(0xC0, 1);
+    device.write_cycle(0xFF46, 0xC0);
+
+    // OAM is still accessible while the transfer is set up.
+    assert_eq!(0x00, device.read_cycle(0xFE00));
+    assert_eq!(0xFF, device.read_cycle(0xFE00));
+
+    run_cycles(&mut device, 158);
+    assert_eq!(0xFF, device.read_cycle(0xFE9F));
+    assert_eq!(0x01, device.read_cycle(0xFE00));
+    assert_eq!(0xA0, device.read_cycle(0xFE9F));
+
+    assert_eq!(0xC0, device.read_cycle(0xFF46));
+}
+
+#[test]
+fn cpu_conflicts_with_the_source_bus() {
+    let mut device = create_device(0xC0, 1);
+    device.write_addr_8(0x8000, 0x42);
+    device.write_addr_8(0xFF80, 0x24);
+
+    device.write_cycle(0xFF46, 0xC0);
+    run_cycles(&mut device, 2);
+
+    // Reading work RAM or ROM returns the byte being copied, the second one in this cycle.
+    assert_eq!(0x02, device.read_cycle(0xD000));
+    assert_eq!(0x03, device.read_cycle(0x0100));
+
+    // VRAM and HRAM are on other buses.
+    assert_eq!(0x42, device.read_cycle(0x8000));
+    assert_eq!(0x24, device.read_cycle(0xFF80));
+
+    device.write_cycle(0xC100, 0x99);
+    device.write_cycle(0xFF81, 0x99);
+
+    run_cycles(&mut device, 160);
+    assert_eq!(0x00, read_address(&device, 0xC100));
+    assert_eq!(0x99, read_address(&device, 0xFF81));
+}
+
+#[test]
+fn writing_again_restarts_the_transfer() {
+    let mut device = create_device(0xC0, 1);
+    fill_page(&mut device, 0xC1, 0x80);
+
+    device.write_cycle(0xFF46, 0xC0);
+    run_cycles(&mut device, 20);
+    device.write_cycle(0xFF46, 0xC1);
+
+    // The first transfer keeps OAM blocked while the second one is set up.
+    assert_eq!(0xFF, device.read_cycle(0xFE00));
+
+    run_cycles(&mut device, 160);
+    assert_eq!(0x80, device.read_cycle(0xFE00));
+    assert_eq!(0x80u8.wrapping_add(0x9F), device.read_cycle(0xFE9F));
+}
+
+#[test]
+fn high_sources_read_from_work_ram() {
+    let mut device = create_device(0xDE, 0x10);
+
+    device.write_cycle(0xFF46, 0xFE);
+    run_cycles(&mut device, 161);
+    assert_eq!(0x10, device.read_cycle(0xFE00));
+    assert_eq!(0x10 + 0x9F, device.read_cycle(0xFE9F));
+}
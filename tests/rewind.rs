@@ -0,0 +1,70 @@
+extern crate rgbemu;
+use rgbemu::emulation::rewind::RewindBuffer;
+
+mod common;
+use common::{load_program, read_address, run_ticks, COUNTER_PROGRAM};
+
+#[test]
+fn step_back_restores_frames_in_reverse() {
+    let mut device = load_program(&COUNTER_PROGRAM);
+    let mut rewind = RewindBuffer::new(16, 1);
+    let mut history = vec![];
+
+    for _ in 0..10 {
+        run_ticks(&mut device, 37);
+        rewind.push_frame(&device);
+        history.push((device.regs.clone(), read_address(&device, 0xC000)));
+    }
+
+    for (regs, counter) in history.into_iter().rev() {
+        assert!(rewind.step_back(&mut device));
+        assert_eq!(regs, device.regs);
+        assert_eq!(counter, read_address(&device, 0xC000));
+    }
+
+    assert!(!rewind.step_back(&mut device));
+    assert!(rewind.is_empty());
+}
+
+#[test]
+fn snapshots_follow_interval_and_capacity() {
+    let mut device = load_program(&COUNTER_PROGRAM);
+    let mut rewind = RewindBuffer::new(4, 3);
+    let mut snapshots = vec![];
+
+    for frame in 1..=30 {
+        run_ticks(&mut device, 11);
+        rewind.push_frame(&device);
+
+        if frame % 3 == 0 {
+            snapshots.push(device.regs.clone());
+        }
+    }
+
+    assert_eq!(4, rewind.len());
+
+    for regs in snapshots.iter().rev().take(4) {
+        rewind.step_back(&mut device);
+        assert_eq!(regs, &device.regs);
+    }
+
+    assert!(rewind.is_empty());
+}
+
+#[test]
+fn deltas_reproduce_states_exactly() {
+    let mut device = load_program(&COUNTER_PROGRAM);
+    let mut rewind = RewindBuffer::new(8, 1);
+    let mut states = vec![];
+
+    for _ in 0..8 {
+        run_ticks(&mut device, 1000);
+        let state = device.save_state();
+        rewind.push_state(state.clone());
+        states.push(state);
+    }
+
+    for state in states.into_iter().rev() {
+        assert_eq!(Some(state), rewind.pop_state());
+    }
+}
@@ -0,0 +1,116 @@
+extern crate rgbemu;
+
+mod common;
+use common::{load_program, load_program_on, read_address, run_ticks, COUNTER_PROGRAM};
+use rgbemu::emulation::constants::*;
+use rgbemu::emulation::device::DeviceType;
+
+#[test]
+fn round_trip_restores_state() {
+    let mut device = load_program(&COUNTER_PROGRAM);
+    run_ticks(&mut device, 100);
+
+    let state = device.save_state();
+    let regs = device.regs.clone();
+    let counter = read_address(&device, 0xC000);
+
+    run_ticks(&mut device, 57);
+    assert_ne!(regs, device.regs);
+
+    device.load_state(&state).unwrap();
+    assert_eq!(regs, device.regs);
+    assert_eq!(counter, read_address(&device, 0xC000));
+    assert_eq!(state, device.save_state());
+}
+
+#[test]
+fn execution_is_deterministic_after_load() {
+    let mut device = load_program(&COUNTER_PROGRAM);
+    run_ticks(&mut device, 1000);
+    let state = device.save_state();
+
+    run_ticks(&mut device, 20000);
+    let expected = device.save_state();
+
+    let mut restored = load_program(&COUNTER_PROGRAM);
+    restored.load_state(&state).unwrap();
+    run_ticks(&mut restored, 20000);
+
+    assert_eq!(expected, restored.save_state());
+}
+
+#[test]
+fn invalid_states_are_rejected() {
+    let mut device = load_program(&COUNTER_PROGRAM);
+    let state = device.save_state();
+
+    assert!(device.load_state(&state[..state.len() - 1]).is_err());
+    assert!(device.load_state(b"not a save state").is_err());
+
+    let mut wrong_version = state.clone();
+    wrong_version[8] = 0xFF;
+    assert!(device.load_state(&wrong_version).is_err());
+
+    device.bus.cartridge = None;
+    assert!(device.load_state(&state).is_err());
+}
+
+#[test]
+fn failed_load_leaves_device_unchanged() {
+    let mut device = load_program(&COUNTER_PROGRAM);
+    run_ticks(&mut device, 100);
+    let mut earlier = device.save_state();
+
+    run_ticks(&mut device, 57);
+    let state = device.save_state();
+
+    earlier.pop();
+    assert!(device.load_state(&earlier).is_err());
+    assert_eq!(state, device.save_state());
+
+    earlier.extend_from_slice(&[0, 0]);
+    assert!(device.load_state(&earlier).is_err());
+    assert_eq!(state, device.save_state());
+}
+
+// Returns the offset of the only byte that differs between the two states.
+fn find_changed_byte(before: &[u8], after: &[u8]) -> usize {
+    let changed: Vec<usize> = (0..before.len())
+        .filter(|&i| before[i] != after[i])
+        .collect();
+    assert_eq!(1, changed.len());
+    changed[0]
+}
+
+#[test]
+fn states_with_missing_banks_are_rejected() {
+    let mut device = load_program_on(DeviceType::GameBoyColor, &COUNTER_PROGRAM);
+    let state = device.save_state();
+
+    device.write_addr_8(0xFF70, 2); // SVBK
+    let ram_bank = find_changed_byte(&state, &device.save_state());
+    device.write_addr_8(0xFF70, 1);
+
+    device.write_addr_8(0xFF4F, 1); // VBK
+    let vram_bank = find_changed_byte(&state, &device.save_state());
+    device.write_addr_8(0xFF4F, 0);
+
+    for &(offset, value) in &[(ram_bank, 0), (ram_bank, 8), (vram_bank, 2)] {
+        let mut invalid = state.clone();
+        invalid[offset] = value;
+        assert!(device.load_state(&invalid).is_err());
+    }
+
+    // The DMG has less work RAM and VRAM in front of the bank numbers.
+    let mut device = load_program(&COUNTER_PROGRAM);
+    let state = device.save_state();
+    let ram_bank = ram_bank - (GBC_RAM_SIZE - GB_RAM_SIZE);
+    let vram_bank = vram_bank - (GBC_RAM_SIZE - GB_RAM_SIZE) - (GBC_VRAM_SIZE - GB_VRAM_SIZE);
+    assert_eq!(1, state[ram_bank]);
+
+    for &(offset, value) in &[(ram_bank, 0), (ram_bank, 2), (vram_bank, 1)] {
+        let mut invalid = state.clone();
+        invalid[offset] = value;
+        assert!(device.load_state(&invalid).is_err());
+    }
+}
@@ -50,6 +50,60 @@ fn push_de() {
         0x76  // HALT
     ]);
 
-    assert_eq!(0xAD, read_address(&device, device.regs.sp + 2));
+    assert_eq!(0xFFFC, device.regs.sp);
+    assert_eq!(0xAD, read_address(&device, device.regs.sp));
     assert_eq!(0xDE, read_address(&device, device.regs.sp + 1));
 }
+
+#[test]
+fn push_pop_round_trip() {
+    let device = run_program(&[
+        0x11, 0xAD, 0xDE, // LD DE, 0xDEAD
+        0xD5, // PUSH DE
+        0xC1, // POP BC
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0xFFFE, device.regs.sp);
+    assert_eq!(0xDEAD, device.regs.bc());
+}
+
+#[test]
+fn load_sp_negative_offset_to_hl() {
+    let device = run_program(&[
+        0x31, 0x00, 0xD0, // LD SP, 0xD000
+        0xF8, 0xFF, // LD HL, SP-1
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0xCFFF, device.regs.hl());
+    assert_eq!(false, device.regs.get_flag(StatusFlag::Z));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::N));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::H));
+    assert_eq!(false, device.regs.get_flag(StatusFlag::C));
+}
+
+#[test]
+fn load_hl_to_sp() {
+    let device = run_program(&[
+        0x21, 0x23, 0xC1, // LD HL, 0xC123
+        0xF9, // LD SP, HL
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0xC123, device.regs.sp);
+}
+
+#[test]
+fn load_a_high_c() {
+    let device = run_program(&[
+        0x3E, 0x42, // LD A, 0x42
+        0xE0, 0x80, // LD (0xFF80), A
+        0x3E, 0x00, // LD A, 0
+        0x0E, 0x80, // LD C, 0x80
+        0xF2, // LD A, (C)
+        0x76  // HALT
+    ]);
+
+    assert_eq!(0x42, device.regs.a);
+}
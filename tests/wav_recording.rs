@@ -0,0 +1,123 @@
+extern crate rgbemu;
+
+mod common;
+use common::load_program;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rgbemu::emulation::audio::controller::StereoSample;
+use rgbemu::emulation::audio::sink::AudioSink;
+use rgbemu::emulation::audio::wav_writer::{WavWriter, WAV_SAMPLE_RATE};
+use rgbemu::emulation::constants::{APU_SAMPLE_RATE, GB_CYCLES_PER_SEC};
+
+// Sets up channel 2 with a 50% duty square wave, then loops forever.
+const SQUARE_WAVE_PROGRAM: [u8; 23] = [
+    0x3E, 0x80, // LD A, 0x80
+    0xE0, 0x16, // LDH (NR21), A
+    0x3E, 0xF0, // LD A, 0xF0
+    0xE0, 0x17, // LDH (NR22), A
+    0x3E, 0x00, // LD A, 0x00
+    0xE0, 0x18, // LDH (NR23), A
+    0x3E, 0x87, // LD A, 0x87
+    0xE0, 0x19, // LDH (NR24), A
+    0x18, 0xFE, // JR -2
+    0x00, 0x00, 0x00, 0x00, 0x00
+];
+
+fn get_temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("rgbemu_{}_{}.wav", name, std::process::id()))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut word = [0u8; 4];
+    word.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(word)
+}
+
+fn record(path: &Path, with_channels: bool, seconds: f64) {
+    let mut device = load_program(&SQUARE_WAVE_PROGRAM);
+    let writer = if with_channels {
+        WavWriter::create_with_channels(path).unwrap()
+    } else {
+        WavWriter::create(path).unwrap()
+    };
+    device.set_audio_sink(Some(Box::new(writer)));
+
+    let mut cycles = 0;
+    while (cycles as f64) < GB_CYCLES_PER_SEC as f64 * seconds {
+        cycles += device.run_tick();
+    }
+
+    device.set_audio_sink(None).unwrap().finish().unwrap();
+}
+
+#[test]
+fn records_stereo_wav() {
+    let path = get_temp_path("stereo");
+    record(&path, false, 0.25);
+
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(b"RIFF", &bytes[0..4]);
+    assert_eq!(b"WAVE", &bytes[8..12]);
+    assert_eq!(bytes.len() as u32 - 8, read_u32(&bytes, 4));
+    assert_eq!(WAV_SAMPLE_RATE, read_u32(&bytes, 24));
+
+    let data_size = read_u32(&bytes, 40);
+    assert_eq!(bytes.len() as u32 - 44, data_size);
+
+    // A quarter of a second of 16-bit stereo.
+    let frames = data_size / 4;
+    assert!((frames as i32 - WAV_SAMPLE_RATE as i32 / 4).abs() < 100);
+
+    // The square wave is audible.
+    let peak = bytes[44..]
+        .chunks(2)
+        .map(|sample| i16::from_le_bytes([sample[0], sample[1]]).abs())
+        .max()
+        .unwrap();
+    assert!(peak > 1000);
+}
+
+#[test]
+fn records_channels_separately() {
+    let path = get_temp_path("channels");
+    record(&path, true, 0.1);
+
+    let mixed_size = fs::metadata(&path).unwrap().len();
+    fs::remove_file(&path).unwrap();
+
+    for channel in 1..=4 {
+        let channel_path = WavWriter::get_channel_path(&path, channel);
+        let bytes = fs::read(&channel_path).unwrap();
+        fs::remove_file(&channel_path).unwrap();
+
+        // Mono, so half the data of the mixed recording.
+        assert_eq!(1, u16::from_le_bytes([bytes[22], bytes[23]]));
+        assert_eq!((mixed_size - 44) / 2, read_u32(&bytes, 40) as u64);
+    }
+}
+
+#[test]
+fn finish_finalizes_headers() {
+    let path = get_temp_path("finish");
+    let mut writer = WavWriter::create(&path).unwrap();
+
+    for _ in 0..APU_SAMPLE_RATE / 10 {
+        writer.push_sample(StereoSample {
+            left: 0.5,
+            right: -0.5
+        });
+    }
+    writer.finish().unwrap();
+
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(bytes.len() as u32 - 8, read_u32(&bytes, 4));
+    assert_eq!(bytes.len() as u32 - 44, read_u32(&bytes, 40));
+    assert!(bytes.len() > 44);
+}